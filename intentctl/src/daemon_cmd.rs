@@ -0,0 +1,224 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ipc_client;
+use voxlinux::healing_level::HealingLevel;
+use voxlinux::protocol::HistoryOutcome;
+
+pub fn apply(id: &str, dry_run: bool) -> bool {
+    match ipc_client::apply(id, dry_run) {
+        Ok(report) => {
+            for action in &report.actions {
+                if report.dry_run {
+                    println!("DRY RUN → {}", action);
+                } else {
+                    println!("✔ {}", action);
+                }
+            }
+
+            if report.verifying {
+                println!("\nPlan executed; voxlinuxd is checking that the repair holds (see `intentctl watch`).");
+            } else if !report.dry_run {
+                println!("\nPlan executed successfully.");
+            }
+
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+pub fn dismiss(id: &str, reason: Option<String>) -> bool {
+    match ipc_client::dismiss(id, reason) {
+        Ok(plan) => {
+            println!("Plan {} is now {}", plan.id, plan.status);
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+pub fn rollback(id: &str) -> bool {
+    match ipc_client::rollback(id) {
+        Ok(plan) => {
+            println!("Plan {} is now {}", plan.id, plan.status);
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+pub fn status() -> bool {
+    match ipc_client::status() {
+        Ok(status) => {
+            println!("Healing level   : {:?}", status.healing_level);
+
+            match status.failure_class {
+                Some(class) => println!("Failure class   : {:?}", class),
+                None => println!("Failure class   : (no scan yet)"),
+            }
+
+            println!("Healing allowed : {}", status.healing_allowed);
+            println!("Plans           : {}", status.plans);
+
+            if let Some(ts) = status.last_scan {
+                println!("Last scan       : {}s ago", now_ts().saturating_sub(ts));
+            }
+
+            if let Some(pause) = &status.pause {
+                println!(
+                    "Paused          : {}s left by {} ({})",
+                    pause.until.saturating_sub(now_ts()),
+                    pause.by,
+                    pause.reason
+                );
+            }
+
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+pub fn level_get() -> bool {
+    match ipc_client::status() {
+        Ok(status) => {
+            println!("{}", status.healing_level);
+
+            if let Some(pause) = &status.pause {
+                println!(
+                    "paused for {}s more by {} ({}); resumes at {}",
+                    pause.until.saturating_sub(now_ts()),
+                    pause.by,
+                    pause.reason,
+                    pause.resume_level
+                );
+            }
+
+            if let Some(change) = &status.last_level_change {
+                let reason = change
+                .reason
+                .as_deref()
+                .map(|r| format!(": {}", r))
+                .unwrap_or_default();
+
+                println!(
+                    "last change: {} → {} by {} {}s ago{}",
+                    change.from,
+                    change.to,
+                    change.by,
+                    now_ts().saturating_sub(change.timestamp),
+                    reason
+                );
+            }
+
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+pub fn level_set(level: &str) -> bool {
+    let level: HealingLevel = match level.parse() {
+        Ok(level) => level,
+        Err(e) => return failed(e),
+    };
+
+    match ipc_client::set_healing_level(level) {
+        Ok(level) => {
+            println!("Healing level set to {}", level);
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+pub fn pause(duration: &str, reason: &str) -> bool {
+    let secs = match parse_duration(duration) {
+        Ok(secs) => secs,
+        Err(e) => return failed(e),
+    };
+
+    match ipc_client::pause(secs, reason) {
+        Ok(pause) => {
+            println!(
+                "Autonomy paused for {} (until {}); {} will be restored afterwards",
+                duration, pause.until, pause.resume_level
+            );
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+pub fn resume() -> bool {
+    match ipc_client::resume() {
+        Ok(level) => {
+            println!("Healing level: {}", level);
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+/// "90s", "15m", "2h", "1d" or a bare number of seconds
+fn parse_duration(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let number: u64 = number
+    .parse()
+    .map_err(|_| format!("invalid duration '{}'", s))?;
+
+    let scale = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("invalid duration unit '{}' (use s, m, h or d)", unit)),
+    };
+
+    match number.checked_mul(scale) {
+        Some(0) => Err("pause duration must be positive".into()),
+        Some(secs) => Ok(secs),
+        None => Err(format!("duration '{}' is too long", s)),
+    }
+}
+
+pub fn history(limit: usize) -> bool {
+    match ipc_client::history(limit) {
+        Ok(entries) if entries.is_empty() => {
+            println!("No executions recorded.");
+            true
+        }
+        Ok(entries) => {
+            for entry in entries {
+                let outcome = match entry.outcome {
+                    HistoryOutcome::Succeeded => "succeeded".to_string(),
+                    HistoryOutcome::Failed { error } => format!("failed: {}", error),
+                    HistoryOutcome::Blocked { reason } => format!("blocked: {}", reason),
+                    HistoryOutcome::RolledBack => "rolled back".to_string(),
+                };
+
+                println!("[{}] {} | {} | {}", entry.timestamp, entry.plan_id, entry.issue, outcome);
+            }
+
+            true
+        }
+        Err(e) => failed(e),
+    }
+}
+
+/// Print a daemon or argument error; the command then exits non-zero
+fn failed(e: impl fmt::Display) -> bool {
+    println!("✖ {}", e);
+    false
+}
+
+fn now_ts() -> u64 {
+    SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap()
+    .as_secs()
+}
@@ -2,48 +2,15 @@ use libc;
 use std::fs::{OpenOptions, create_dir_all};
 use std::io::Write;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::process::Command;
 
+use voxlinux::command_runner::SystemRunner;
+use voxlinux::paths;
+use voxlinux::repair_action::RepairAction;
 use voxlinux::repair_plan::{RepairPlan, RiskLevel};
-
-use sha2::{Sha256, Digest};
-use hex;
-
-fn compute_hash(plan: &RepairPlan) -> String {
-
-    let mut hasher = Sha256::new();
-
-    hasher.update(&plan.id);
-    hasher.update(&plan.issue);
-    hasher.update(format!("{:?}", plan.risk));
-    hasher.update(plan.confidence_high.to_string());
-    hasher.update(plan.reversible.to_string());
-    hasher.update(plan.requires_reboot.to_string());
-
-    for action in &plan.actions {
-        hasher.update(action);
-    }
-
-    for block in &plan.explain {
-        hasher.update(block.level.to_string());
-        hasher.update(format!("{:?}", block.category));
-        hasher.update(&block.content);
-    }
-
-    hex::encode(hasher.finalize())
-}
-
-fn verify_integrity(plan: &RepairPlan) -> bool {
-
-    let original_hash = plan.integrity_hash.clone();
-
-    let mut cloned = plan.clone();
-    cloned.integrity_hash = String::new();
-
-    let recalculated = compute_hash(&cloned);
-
-    original_hash == recalculated
-}
+use voxlinux::plan_status::PlanStatus;
+use voxlinux::plan_store::{self, PlanStore};
+use voxlinux::signing::{check_plan, SignatureStatus};
+use voxlinux::transaction;
 
 fn ensure_root() -> bool {
     unsafe { libc::geteuid() == 0 }
@@ -51,7 +18,7 @@ fn ensure_root() -> bool {
 
 fn log_event(message: &str) {
 
-    let _ = create_dir_all("/tmp/voxlinux");
+    let _ = create_dir_all(paths::log_dir());
 
     let ts = SystemTime::now()
     .duration_since(UNIX_EPOCH)
@@ -61,22 +28,62 @@ fn log_event(message: &str) {
     if let Ok(mut file) = OpenOptions::new()
         .create(true)
         .append(true)
-        .open("/tmp/voxlinux/exec.log")
+        .open(paths::exec_log())
         {
             let _ = writeln!(file, "[{}] {}", ts, message);
         }
 }
 
-pub fn apply_plan(plan: RepairPlan, force: bool, dry_run: bool) {
+/// Recorded as the approver of plans applied with --local
+const LOCAL_BY: &str = "intentctl --local";
 
-    if !verify_integrity(&plan) {
+/// Move the stored plan to `to`, as voxlinuxd does for plans it applies
+fn set_status(plan: &RepairPlan, to: PlanStatus, reason: Option<String>) -> bool {
+    match plan_store::system_store().update_status(&plan.id, to, LOCAL_BY, reason) {
+        Ok(_) => {
+            log_event(&format!("STATUS {} → {} by {}", plan.id, to, LOCAL_BY));
+            true
+        }
+        Err(e) => {
+            println!("Cannot mark the plan {}: {}", to, e);
+            log_event(&format!("STATUS {} → {} failed: {}", plan.id, to, e));
+            false
+        }
+    }
+}
 
-        println!("❌ Plan integrity verification failed.");
+/// True if the plan ran (or, with `dry_run`, would run)
+pub fn apply_plan(plan: RepairPlan, force: bool, dry_run: bool) -> bool {
+
+    if let Err(e) = plan.verify_integrity() {
+
+        println!("❌ Plan integrity verification failed: {}", e);
         println!("The repair plan may have been tampered with.");
 
-        log_event(&format!("INTEGRITY FAIL {}", plan.id));
+        log_event(&format!("INTEGRITY FAIL {} ({})", plan.id, e));
 
-        return;
+        return false;
+    }
+
+    let signature = check_plan(&plan);
+
+    if signature != SignatureStatus::Valid {
+
+        println!("❌ Plan signature is {}.", signature.describe());
+        println!("Only plans signed by voxlinuxd can be applied.");
+
+        log_event(&format!("SIGNATURE FAIL {} ({:?})", plan.id, signature));
+
+        return false;
+    }
+
+    if let Some(reason) = plan.apply_blocker() {
+
+        println!("Plan cannot be applied: {}.", reason);
+
+        log_event(&format!("BLOCKED plan {}: {}", plan.id, reason));
+
+        return false;
     }
 
     if !ensure_root() {
@@ -85,7 +92,7 @@ pub fn apply_plan(plan: RepairPlan, force: bool, dry_run: bool) {
 
         log_event("DENIED non-root execution");
 
-        return;
+        return false;
     }
 
     if plan.risk == RiskLevel::High && !force {
@@ -94,7 +101,7 @@ pub fn apply_plan(plan: RepairPlan, force: bool, dry_run: bool) {
 
         log_event("BLOCKED high risk plan");
 
-        return;
+        return false;
     }
 
     if !plan.confidence_high && !force {
@@ -103,52 +110,73 @@ pub fn apply_plan(plan: RepairPlan, force: bool, dry_run: bool) {
 
         log_event("BLOCKED low confidence plan");
 
-        return;
+        return false;
     }
 
-    log_event(&format!("PLAN START {}", plan.id));
-
-    for action in &plan.actions {
+    if dry_run {
 
-        if dry_run {
+        for action in &plan.actions {
 
             println!("DRY RUN → {}", action);
 
-            log_event(&format!("DRY RUN {}", action));
-
-            continue;
+            log_event(&format!("DRY RUN {:?}", action));
         }
 
-        println!("Executing: {}", action);
+        return true;
+    }
 
-        log_event(&format!("EXEC {}", action));
+    // Same lifecycle as a daemon apply; a plan someone else is running
+    // cannot be approved or started again
+    if plan.status != PlanStatus::Approved && !set_status(&plan, PlanStatus::Approved, None) {
+        return false;
+    }
 
-        let status = Command::new("sh")
-        .arg("-c")
-        .arg(action)
-        .status();
+    if !set_status(&plan, PlanStatus::Running, None) {
+        return false;
+    }
 
-        match status {
+    log_event(&format!("PLAN START {}", plan.id));
+
+    let mut observe = |action: &RepairAction, result: &Result<(), String>| {
+
+        println!("Executed: {}", action);
 
-            Ok(s) if s.success() => {
+        match result {
+
+            Ok(()) => {
 
                 println!("✔ Success");
 
-                log_event(&format!("RESULT success action={}", action));
+                log_event(&format!("RESULT success action={:?}", action));
             }
 
-            _ => {
-
-                println!("✖ Failed. Aborting.");
+            Err(e) => {
 
-                log_event(&format!("RESULT failure action={}", action));
+                println!("✖ Failed ({}). Rolling back.", e);
 
-                return;
+                log_event(&format!("RESULT failure action={:?} error={}", action, e));
             }
         }
+    };
+
+    if let Err(failure) = transaction::run(&plan, &SystemRunner, &mut observe) {
+
+        println!("✖ Plan failed: {}", failure.describe());
+
+        log_event(&format!("ROLLBACK {} ({})", plan.id, failure.describe()));
+
+        set_status(&plan, PlanStatus::Failed, Some(failure.describe()));
+
+        return false;
     }
 
     log_event(&format!("PLAN END {}", plan.id));
 
-    println!("\nPlan executed successfully.");
+    // Only voxlinuxd watches the targets for the stability window; until
+    // it has, the repair is not known to hold
+    set_status(&plan, PlanStatus::Unverified, None);
+
+    println!("\nPlan executed. voxlinuxd marks it succeeded once the repair has held for the stability window.");
+
+    true
 }
@@ -1,7 +1,9 @@
 use crate::reader::find_plan;
+use voxlinux::evidence::print_evidence;
 use voxlinux::explain::explain_at_level;
+use voxlinux::signing::check_plan;
 
-pub fn explain_plan(id: &str, level: u8) {
+pub fn explain_plan(id: &str, level: u8) -> bool {
     if let Some(plan) = find_plan(id) {
         println!("Plan ID      : {}", plan.id);
         println!("Issue        : {}", plan.issue);
@@ -9,9 +11,34 @@ pub fn explain_plan(id: &str, level: u8) {
         println!("High Conf    : {}", plan.confidence_high);
         println!("Reversible   : {}", plan.reversible);
         println!("Reboot Req   : {}", plan.requires_reboot);
+        println!("Signature    : {}", check_plan(&plan).describe());
+        println!("Status       : {}", plan.status);
+
+        if !plan.verify.is_empty() {
+            let targets: Vec<String> = plan.verify.iter().map(|t| t.to_string()).collect();
+            println!("Verify       : {}", targets.join(", "));
+        }
+
+        for change in &plan.status_history {
+            println!(
+                "  [{}] {} → {} by {}{}",
+                change.timestamp,
+                change.from,
+                change.to,
+                change.by,
+                change.reason.as_ref().map(|r| format!(" ({})", r)).unwrap_or_default()
+            );
+        }
 
         explain_at_level(&plan.explain, level);
+
+        if level >= 2 {
+            print_evidence(&plan.evidence);
+        }
+
+        true
     } else {
         println!("Plan not found.");
+        false
     }
 }
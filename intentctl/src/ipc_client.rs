@@ -1,41 +1,189 @@
+use std::fmt;
 use std::os::unix::net::UnixStream;
-use std::io::{Write, Read};
 
-const SOCKET_PATH: &str = "/run/voxlinux/voxlinux.sock";
+use voxlinux::protocol::{
+    self, DaemonError, DaemonStatus, Event, ExecutionReport, FrameError, HistoryEntry, Pause,
+    Request, Response,
+};
+use voxlinux::paths;
+use voxlinux::healing_level::HealingLevel;
+use voxlinux::repair_plan::RepairPlan;
 
-pub fn send_apply(plan_id: &str) {
+#[derive(Debug)]
+pub enum ClientError {
+    Connect(std::io::Error),
+    Transport(String),
+    Daemon(DaemonError),
+    UnexpectedResponse,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Connect(e) => write!(f, "cannot connect to voxlinuxd: {}", e),
+            ClientError::Transport(e) => write!(f, "daemon communication failed: {}", e),
+            ClientError::Daemon(e) => write!(f, "{}", e),
+            ClientError::UnexpectedResponse => write!(f, "unexpected response from daemon"),
+        }
+    }
+}
+
+impl From<FrameError> for ClientError {
+    fn from(e: FrameError) -> Self {
+        ClientError::Transport(e.to_string())
+    }
+}
+
+/// Send one request and wait for its response
+pub fn request(req: &Request) -> Result<Response, ClientError> {
+
+    let mut stream = UnixStream::connect(paths::socket_path())
+    .map_err(ClientError::Connect)?;
+
+    protocol::send(&mut stream, req)
+    .map_err(|e| ClientError::Transport(e.to_string()))?;
+
+    match protocol::recv::<_, Response>(&mut stream)? {
+        Response::Error(e) => Err(ClientError::Daemon(e)),
+        response => Ok(response),
+    }
+}
 
-    match UnixStream::connect(SOCKET_PATH) {
+pub fn list_plans() -> Result<Vec<RepairPlan>, ClientError> {
+    match request(&Request::ListPlans)? {
+        Response::Plans(plans) => Ok(plans),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
 
-        Ok(mut stream) => {
+pub fn get_plan(id: &str) -> Result<RepairPlan, ClientError> {
+    match request(&Request::GetPlan { id: id.to_string() })? {
+        Response::Plan(plan) => Ok(plan),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
+
+pub fn apply(id: &str, dry_run: bool) -> Result<ExecutionReport, ClientError> {
+    let req = if dry_run {
+        Request::DryRun { id: id.to_string() }
+    } else {
+        Request::Apply { id: id.to_string() }
+    };
 
-            let message = format!("APPLY:{}", plan_id);
+    match request(&req)? {
+        Response::Executed(report) => Ok(report),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
 
-            if let Err(e) = stream.write_all(message.as_bytes()) {
-                println!("[IPC CLIENT] Failed to send request: {}", e);
-                return;
-            }
+pub fn dismiss(id: &str, reason: Option<String>) -> Result<RepairPlan, ClientError> {
+    let req = Request::Dismiss {
+        id: id.to_string(),
+        reason,
+    };
 
-            let mut response = [0; 128];
+    match request(&req)? {
+        Response::Plan(plan) => Ok(plan),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
 
-            match stream.read(&mut response) {
+pub fn rollback(id: &str) -> Result<RepairPlan, ClientError> {
+    match request(&Request::Rollback { id: id.to_string() })? {
+        Response::Plan(plan) => Ok(plan),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
 
-                Ok(size) => {
+pub fn status() -> Result<DaemonStatus, ClientError> {
+    match request(&Request::GetStatus)? {
+        Response::Status(status) => Ok(status),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
 
-                    let reply =
-                    String::from_utf8_lossy(&response[..size]);
+pub fn history(limit: usize) -> Result<Vec<HistoryEntry>, ClientError> {
+    match request(&Request::GetHistory { limit })? {
+        Response::History(entries) => Ok(entries),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
+
+pub fn set_healing_level(level: HealingLevel) -> Result<HealingLevel, ClientError> {
+    match request(&Request::SetHealingLevel { level })? {
+        Response::HealingLevel(level) => Ok(level),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
+
+pub fn pause(duration_secs: u64, reason: &str) -> Result<Pause, ClientError> {
+    let req = Request::Pause {
+        duration_secs,
+        reason: reason.to_string(),
+    };
+
+    match request(&req)? {
+        Response::Paused(pause) => Ok(pause),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
+
+pub fn resume() -> Result<HealingLevel, ClientError> {
+    match request(&Request::Resume)? {
+        Response::HealingLevel(level) => Ok(level),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
+
+/// Open a subscription; the iterator ends when the daemon goes away
+pub fn subscribe() -> Result<EventStream, ClientError> {
+
+    let mut stream = UnixStream::connect(paths::socket_path())
+    .map_err(ClientError::Connect)?;
+
+    protocol::send(&mut stream, &Request::Subscribe)
+    .map_err(|e| ClientError::Transport(e.to_string()))?;
+
+    match protocol::recv::<_, Response>(&mut stream)? {
+        Response::Subscribed => Ok(EventStream { stream }),
+        Response::Error(e) => Err(ClientError::Daemon(e)),
+        _ => Err(ClientError::UnexpectedResponse),
+    }
+}
+
+pub struct EventStream {
+    stream: UnixStream,
+}
+
+impl Iterator for EventStream {
+    type Item = Result<Event, ClientError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match protocol::recv::<_, Response>(&mut self.stream) {
+            Ok(Response::Event(event)) => Some(Ok(event)),
+            Ok(Response::Error(e)) => Some(Err(ClientError::Daemon(e))),
+            Ok(_) => Some(Err(ClientError::UnexpectedResponse)),
+            Err(e) if e.is_eof() => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+/// Used by the notification GUI: apply and report the outcome on stdout
+pub fn send_apply(plan_id: &str) {
 
-                    println!("[IPC CLIENT] daemon reply: {}", reply);
-                }
+    match apply(plan_id, false) {
 
-                Err(e) => {
-                    println!("[IPC CLIENT] Failed reading response: {}", e);
-                }
-            }
+        Ok(report) => {
+            println!(
+                "[IPC CLIENT] daemon applied plan {} ({} actions)",
+                report.plan_id,
+                report.actions.len()
+            );
         }
 
         Err(e) => {
-            println!("[IPC CLIENT] Failed to connect to daemon: {}", e);
+            println!("[IPC CLIENT] {}", e);
         }
     }
 }
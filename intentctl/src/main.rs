@@ -5,12 +5,18 @@ mod advisor;
 mod gui;
 mod ipc_client;
 mod watcher;
+mod daemon_cmd;
 
+use std::process::ExitCode;
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "intentctl")]
 struct Cli {
+    /// Run against a fixture tree instead of / (also: VOXLINUX_ROOT)
+    #[arg(long, global = true)]
+    root_dir: Option<std::path::PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -27,45 +33,165 @@ enum Commands {
         explanation: String,
     },
     Watch,
+
+    /// Show what the daemon is currently doing
+    Status,
+
+    /// Show recent plan executions
+    History {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Show or change the daemon healing level
+    Level {
+        #[command(subcommand)]
+        action: LevelAction,
+    },
+
+    /// Drop to observe-only for a while, then restore the current level
+    Pause {
+        /// e.g. 30m, 2h, 1d
+        #[arg(long = "for")]
+        duration: String,
+
+        #[arg(long)]
+        reason: String,
+    },
+
+    /// End a pause early
+    Resume,
+}
+
+#[derive(Subcommand)]
+enum LevelAction {
+    Get,
+
+    /// observe | runtime-safe | assisted | autonomous
+    Set { level: String },
 }
 
 #[derive(Subcommand)]
 enum RepairAction {
-    List,
-    Explain { id: String, level: Option<u8> },
-    Apply { id: String, yes: bool, dry_run: bool },
+    List {
+        /// Only plans in this state: proposed, approved, running, succeeded,
+        /// failed, expired, dismissed or rolled-back
+        #[arg(long)]
+        status: Option<String>,
+    },
+    Explain {
+        id: String,
+
+        /// Detail level; 2 and above also show journal evidence
+        #[arg(long, default_value_t = 1)]
+        level: u8,
+    },
+    Apply {
+        id: String,
+
+        /// Apply high-risk or low-confidence plans anyway. Only with --local;
+        /// voxlinuxd never overrides its policy.
+        #[arg(long, requires = "local")]
+        yes: bool,
+
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Execute in this process instead of asking voxlinuxd (requires root)
+        #[arg(long)]
+        local: bool,
+    },
+
+    /// Decline a plan; it stays listed as dismissed and cannot be applied
+    Dismiss {
+        id: String,
+
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
+    /// Undo a plan that was applied, newest action first
+    Rollback {
+        id: String,
+    },
 }
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    match cli.command {
+    if let Some(dir) = &cli.root_dir
+    && let Err(e) = voxlinux::paths::set_root(dir)
+    {
+        eprintln!("intentctl: {}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let ok = match cli.command {
         Commands::Notify { plan_id, explanation } => {
             gui::show_notification(plan_id, explanation);
+            true
         }
 
         Commands::Watch => {
             watcher::run();
+            true
+        }
+
+        Commands::Status => {
+            daemon_cmd::status()
+        }
+
+        Commands::History { limit } => {
+            daemon_cmd::history(limit)
+        }
+
+        Commands::Level { action } => {
+            match action {
+                LevelAction::Get => daemon_cmd::level_get(),
+                LevelAction::Set { level } => daemon_cmd::level_set(&level),
+            }
+        }
+
+        Commands::Pause { duration, reason } => {
+            daemon_cmd::pause(&duration, &reason)
+        }
+
+        Commands::Resume => {
+            daemon_cmd::resume()
         }
 
         Commands::Repair { action } => {
             match action {
-                RepairAction::List => {
-                    reader::list_plans();
+                RepairAction::List { status } => {
+                    reader::list_plans(status.as_deref())
                 }
                 RepairAction::Explain { id, level } => {
-                    let level = level.unwrap_or(1); // default level = 1
-                    explain_cmd::explain_plan(&id, level);
+                    explain_cmd::explain_plan(&id, level)
                 }
 
-                RepairAction::Apply { id, yes, dry_run } => {
-                    if let Some(plan) = reader::find_plan(&id) {
-                        executor::apply_plan(plan, yes, dry_run);
+                RepairAction::Apply { id, yes, dry_run, local } => {
+                    if local {
+                        if let Some(plan) = reader::find_plan(&id) {
+                            executor::apply_plan(plan, yes, dry_run)
+                        } else {
+                            println!("Plan not found.");
+                            false
+                        }
                     } else {
-                        println!("Plan not found.");
+                        daemon_cmd::apply(&id, dry_run)
                     }
                 }
+
+                RepairAction::Dismiss { id, reason } => {
+                    daemon_cmd::dismiss(&id, reason)
+                }
+
+                RepairAction::Rollback { id } => {
+                    daemon_cmd::rollback(&id)
+                }
             }
         }
-    }
+    };
+
+    if ok { ExitCode::SUCCESS } else { ExitCode::FAILURE }
 }
@@ -1,47 +1,85 @@
-use std::fs;
 use voxlinux::repair_plan::RepairPlan;
+use voxlinux::plan_status::PlanStatus;
+use voxlinux::plan_store::{self, PlanStore};
+use crate::ipc_client::{self, ClientError};
 
-const PLAN_DIR: &str = "/run/voxlinux/plans";
-
-pub fn load_plans() -> Vec<RepairPlan> {
-    let mut plans = Vec::new();
-
-    if let Ok(entries) = fs::read_dir(PLAN_DIR) {
-        for entry in entries.flatten() {
-            if let Ok(content) = fs::read_to_string(entry.path()) {
-                match serde_json::from_str::<RepairPlan>(&content) {
-                    Ok(plan) => plans.push(plan),
-                    Err(e) => {
-                        println!("Failed to load plan: {}", entry.path().display());
-                        println!("Reason: {}", e);
-                    }
-                }
-            }
+/// Ask the daemon first; read the plan directory only if it is not running.
+/// None (after printing why) if neither works.
+pub fn load_plans() -> Option<Vec<RepairPlan>> {
+    match ipc_client::list_plans() {
+        Ok(plans) => Some(plans),
+        Err(ClientError::Connect(_)) => load_plans_from_disk(),
+        Err(e) => {
+            println!("Failed to list plans: {}", e);
+            None
         }
     }
+}
 
-    plans
+/// Read the plan directory directly (daemon not running)
+fn load_plans_from_disk() -> Option<Vec<RepairPlan>> {
+    match plan_store::system_store().list() {
+        Ok(plans) => Some(plans),
+        Err(e) => {
+            println!("Failed to load plans: {}", e);
+            None
+        }
+    }
 }
 
 pub fn find_plan(id: &str) -> Option<RepairPlan> {
-    load_plans().into_iter().find(|p| p.id == id)
+    match ipc_client::get_plan(id) {
+        Ok(plan) => Some(plan),
+        Err(ClientError::Connect(_)) => match plan_store::system_store().get(id) {
+            Ok(plan) => plan,
+            Err(e) => {
+                println!("Failed to load plan: {}", e);
+                None
+            }
+        },
+        Err(_) => None,
+    }
 }
 
-pub fn list_plans() {
-    let plans = load_plans();
+pub fn list_plans(status: Option<&str>) -> bool {
+    let status: Option<PlanStatus> = match status.map(str::parse).transpose() {
+        Ok(status) => status,
+        Err(e) => {
+            println!("✖ {}", e);
+            return false;
+        }
+    };
+
+    let Some(plans) = load_plans() else {
+        return false;
+    };
+
+    let plans: Vec<RepairPlan> = plans
+    .into_iter()
+    .filter(|p| status.is_none_or(|s| p.status == s))
+    .collect();
 
     if plans.is_empty() {
         println!("No repair plans available.");
-        return;
+        return true;
     }
 
     for plan in plans {
+        let superseded = plan
+        .superseded_by
+        .as_ref()
+        .map(|id| format!(" (superseded by {})", id))
+        .unwrap_or_default();
+
         println!(
-            "[{}] {} | Risk: {:?} | High Confidence: {}",
+            "[{}] {} | {} | Risk: {:?} | High Confidence: {}{}",
             plan.id,
             plan.issue,
+            plan.status,
             plan.risk,
-            plan.confidence_high
+            plan.confidence_high,
+            superseded
         );
     }
+    true
 }
@@ -1,91 +1,112 @@
-use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::Path;
-use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
 
 use crate::gui;
-use voxlinux::repair_plan::RepairPlan;
+use crate::ipc_client::{self, ClientError};
+use voxlinux::protocol::Event;
 
-const PLAN_DIR: &str = "/run/voxlinux/plans";
+/// Delay before reconnecting after the daemon restarts or goes away
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
 
 pub fn run() {
 
-    println!("[WATCHER] Using filesystem watcher");
-
-    let (tx, rx) = channel();
-
-    let mut watcher = RecommendedWatcher::new(
-        move |res: Result<Event, notify::Error>| {
-            tx.send(res).unwrap();
-        },
-        Config::default(),
-    )
-    .expect("Failed to create watcher");
-
-    watcher
-    .watch(Path::new(PLAN_DIR), RecursiveMode::NonRecursive)
-    .expect("Failed to watch plan directory");
+    println!("[WATCHER] Subscribing to voxlinuxd events");
 
     loop {
 
-        match rx.recv() {
+        match ipc_client::subscribe() {
 
-            Ok(Ok(event)) => {
+            Ok(events) => {
 
-                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                println!("[WATCHER] Connected");
 
-                    for path in event.paths {
+                for event in events {
+                    match event {
+                        Ok(event) => handle_event(event),
+                        Err(e) => {
+                            println!("[WATCHER ERROR] {}", e);
+                            break;
+                        }
+                    }
+                }
 
-                        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                println!("[WATCHER] Daemon closed the subscription");
+            }
 
-                            if !name.ends_with(".json") {
-                                continue;
-                            }
+            Err(ClientError::Connect(e)) => {
+                println!("[WATCHER] voxlinuxd not reachable: {}", e);
+            }
 
-                            // Small delay to avoid partial reads
-                            std::thread::sleep(std::time::Duration::from_millis(100));
+            Err(e) => {
+                println!("[WATCHER ERROR] {}", e);
+            }
+        }
 
-                            println!("[WATCHER] NEW PLAN FILE: {}", name);
+        thread::sleep(RECONNECT_DELAY);
+    }
+}
 
-                            match std::fs::read_to_string(&path) {
+fn handle_event(event: Event) {
 
-                                Ok(contents) => {
+    match event {
 
-                                    match serde_json::from_str::<RepairPlan>(&contents) {
+        Event::PlanCreated { plan_id, issue, risk } => {
 
-                                        Ok(plan) => {
+            println!("[WATCHER] NEW PLAN: {} ({:?}) {}", plan_id, risk, issue);
 
-                                            let explanation = plan
-                                            .explain
-                                            .iter()
-                                            .map(|b| b.content.clone())
-                                            .collect::<Vec<_>>()
-                                            .join("\n\n");
+            match ipc_client::get_plan(&plan_id) {
 
-                                            gui::show_notification(plan.id, explanation);
-                                        }
+                Ok(plan) => {
 
-                                        Err(e) => {
-                                            println!("[WATCHER] JSON parse failed: {}", e);
-                                        }
-                                    }
-                                }
+                    let explanation = plan
+                    .explain
+                    .iter()
+                    .map(|b| b.content.clone())
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
 
-                                Err(e) => {
-                                    println!("[WATCHER] Failed to read plan: {}", e);
-                                }
-                            }
-                        }
-                    }
+                    gui::show_notification(plan.id, explanation);
                 }
-            }
 
-            Ok(Err(e)) => {
-                println!("[WATCHER ERROR] {:?}", e);
+                Err(e) => {
+                    println!("[WATCHER] Failed to fetch plan {}: {}", plan_id, e);
+                }
             }
+        }
 
-            Err(e) => {
-                println!("[WATCHER CHANNEL ERROR] {:?}", e);
-            }
+        Event::PlanSuperseded { old_id, new_id } => {
+            println!("[WATCHER] Plan {} superseded by {}", old_id, new_id);
+        }
+
+        Event::PlanExpired { plan_id } => {
+            println!("[WATCHER] Plan {} expired (issue resolved)", plan_id);
+        }
+
+        Event::ExecutionStarted { plan_id } => {
+            println!("[WATCHER] Executing plan {}", plan_id);
+        }
+
+        Event::ActionResult { plan_id, action, error } => match error {
+            None => println!("[WATCHER] {} ✔ {}", plan_id, action),
+            Some(e) => println!("[WATCHER] {} ✖ {} ({})", plan_id, action, e),
+        },
+
+        Event::ExecutionFinished { plan_id, success } => {
+            let outcome = if success { "succeeded" } else { "failed" };
+            println!("[WATCHER] Plan {} {}", plan_id, outcome);
+        }
+
+        Event::HealingBlocked { plan_id, reason } => match plan_id {
+            Some(id) => println!("[WATCHER] Healing blocked for {}: {}", id, reason),
+            None => println!("[WATCHER] Healing blocked: {}", reason),
+        },
+
+        Event::FailureClassChanged { from, to } => {
+            println!("[WATCHER] Failure class {:?} → {:?}", from, to);
+        }
+
+        Event::HealingLevelChanged(change) => {
+            println!("[WATCHER] Healing level {} → {} by {}", change.from, change.to, change.by);
         }
     }
 }
@@ -0,0 +1,129 @@
+use sha2::{Sha256, Digest};
+
+/// Unambiguous byte encoding used for plan hashing.
+///
+/// Every variable-length value is written with a u64 big-endian length
+/// prefix, and enums are written as fixed tags (never `Debug` output),
+/// so neither field boundaries nor compiler formatting affect the hash.
+pub struct CanonicalEncoder {
+    hasher: Sha256,
+}
+
+impl CanonicalEncoder {
+    /// `domain` separates hashes of different object kinds
+    pub fn new(domain: &str, version: u32) -> Self {
+        let mut encoder = CanonicalEncoder {
+            hasher: Sha256::new(),
+        };
+
+        encoder.str(domain);
+        encoder.u32(version);
+        encoder
+    }
+
+    pub fn bytes(&mut self, data: &[u8]) {
+        self.hasher.update((data.len() as u64).to_be_bytes());
+        self.hasher.update(data);
+    }
+
+    pub fn str(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    pub fn tag(&mut self, tag: &'static str) {
+        self.str(tag);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.hasher.update([value as u8]);
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.hasher.update([value]);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.hasher.update(value.to_be_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.hasher.update(value.to_be_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.hasher.update(value.to_be_bytes());
+    }
+
+    /// Presence flag followed by the value, so None and "" differ
+    pub fn opt_str(&mut self, value: Option<&str>) {
+        match value {
+            Some(v) => {
+                self.bool(true);
+                self.str(v);
+            }
+            None => self.bool(false),
+        }
+    }
+
+    /// Prefix for a sequence of `len` items
+    pub fn list(&mut self, len: usize) {
+        self.hasher.update((len as u64).to_be_bytes());
+    }
+
+    /// Hex-encoded SHA-256 of everything written so far
+    pub fn finish(self) -> String {
+        hex::encode(self.hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(write: impl FnOnce(&mut CanonicalEncoder)) -> String {
+        let mut enc = CanonicalEncoder::new("test", 1);
+        write(&mut enc);
+        enc.finish()
+    }
+
+    #[test]
+    fn same_input_same_hash() {
+        let a = hash(|enc| {
+            enc.str("nginx.service");
+            enc.u32(7);
+        });
+        let b = hash(|enc| {
+            enc.str("nginx.service");
+            enc.u32(7);
+        });
+
+        assert_eq!(a, b);
+        assert_eq!(a.len(), 64);
+    }
+
+    #[test]
+    fn field_boundaries_change_the_hash() {
+        let split_late = hash(|enc| {
+            enc.str("ab");
+            enc.str("c");
+        });
+        let split_early = hash(|enc| {
+            enc.str("a");
+            enc.str("bc");
+        });
+
+        assert_ne!(split_late, split_early);
+    }
+
+    #[test]
+    fn missing_and_empty_differ() {
+        assert_ne!(hash(|enc| enc.opt_str(None)), hash(|enc| enc.opt_str(Some(""))));
+        assert_ne!(hash(|enc| enc.list(0)), hash(|_| {}));
+    }
+
+    #[test]
+    fn domain_and_version_separate_hashes() {
+        assert_ne!(CanonicalEncoder::new("plan", 1).finish(), CanonicalEncoder::new("actions", 1).finish());
+        assert_ne!(CanonicalEncoder::new("plan", 1).finish(), CanonicalEncoder::new("plan", 2).finish());
+    }
+}
@@ -0,0 +1,201 @@
+// command_runner.rs
+//
+// Every external program the engine runs (pgrep, ping, mount, the repair
+// actions, …) goes through a `CommandRunner`. The daemon uses `SystemRunner`;
+// tests and offline debugging use `RecordingRunner` to capture real outputs
+// and `FakeRunner` to replay them on any machine, without systemd or root.
+
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Result of one finished program
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// Exit code; `None` if the program was killed by a signal
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Successful run with the given stdout (for canned responses)
+    pub fn ok(stdout: &str) -> Self {
+        CommandOutput {
+            code: Some(0),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    /// Failed run with the given exit code (for canned responses)
+    pub fn failed(code: i32) -> Self {
+        CommandOutput {
+            code: Some(code),
+            stdout: String::new(),
+            stderr: String::new(),
+        }
+    }
+}
+
+pub trait CommandRunner: Send + Sync {
+    /// Run `program` with `args` (no shell) and wait for it to finish
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput>;
+}
+
+/// Runs real processes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        let output = Command::new(program).args(args).output()?;
+
+        Ok(CommandOutput {
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// One recorded invocation and what it returned
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Exchange {
+    pub program: String,
+    pub args: Vec<String>,
+    pub output: CommandOutput,
+}
+
+/// Wraps another runner and keeps every exchange, e.g. to capture a
+/// broken machine's outputs for replay with `FakeRunner`
+pub struct RecordingRunner<R: CommandRunner> {
+    inner: R,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl<R: CommandRunner> RecordingRunner<R> {
+    pub fn new(inner: R) -> Self {
+        RecordingRunner {
+            inner,
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+
+    /// Write the recording as JSON, loadable with `FakeRunner::load`
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(&self.exchanges())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        fs::write(path, json)
+    }
+}
+
+impl<R: CommandRunner> CommandRunner for RecordingRunner<R> {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        let output = self.inner.run(program, args)?;
+
+        self.exchanges.lock().unwrap().push(Exchange {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            output: output.clone(),
+        });
+
+        Ok(output)
+    }
+}
+
+type Invocation = (String, Vec<String>);
+
+/// Replays canned outputs instead of running anything.
+///
+/// Responses for the same program + args are returned in order; the last
+/// one keeps being returned once the queue is down to it. An invocation
+/// with no canned response fails with `NotFound`. Every call is recorded.
+#[derive(Default)]
+pub struct FakeRunner {
+    responses: Mutex<HashMap<Invocation, VecDeque<CommandOutput>>>,
+    calls: Mutex<Vec<Invocation>>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        FakeRunner::default()
+    }
+
+    /// Builder-style: queue `output` for `program args…`
+    pub fn respond(self, program: &str, args: &[&str], output: CommandOutput) -> Self {
+        self.responses
+        .lock()
+        .unwrap()
+        .entry(invocation(program, args))
+        .or_default()
+        .push_back(output);
+
+        self
+    }
+
+    pub fn from_exchanges(exchanges: Vec<Exchange>) -> Self {
+        let mut runner = FakeRunner::new();
+
+        for exchange in exchanges {
+            let args: Vec<&str> = exchange.args.iter().map(String::as_str).collect();
+            runner = runner.respond(&exchange.program, &args, exchange.output);
+        }
+
+        runner
+    }
+
+    /// Load a recording written by `RecordingRunner::save`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+
+        let exchanges: Vec<Exchange> = serde_json::from_str(&data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        Ok(FakeRunner::from_exchanges(exchanges))
+    }
+
+    /// Every invocation so far, in order
+    pub fn calls(&self) -> Vec<(String, Vec<String>)> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl CommandRunner for FakeRunner {
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<CommandOutput> {
+        let key = invocation(program, args);
+        self.calls.lock().unwrap().push(key.clone());
+
+        let mut responses = self.responses.lock().unwrap();
+
+        let queue = responses.get_mut(&key).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no canned response for {} {}", program, args.join(" ")),
+            )
+        })?;
+
+        match queue.len() {
+            0 => Err(io::Error::new(io::ErrorKind::NotFound, "empty response queue")),
+            1 => Ok(queue[0].clone()),
+            _ => Ok(queue.pop_front().unwrap()),
+        }
+    }
+}
+
+fn invocation(program: &str, args: &[&str]) -> Invocation {
+    (program.to_string(), args.iter().map(|a| a.to_string()).collect())
+}
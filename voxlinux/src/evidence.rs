@@ -0,0 +1,81 @@
+use crate::canonical::CanonicalEncoder;
+use serde::{Serialize, Deserialize};
+
+/// What systemd and the journal said about a failed unit when the plan was built
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Evidence {
+    pub unit: String,
+
+    /// systemd `Result=` ("exit-code", "signal", "timeout", "core-dump", …)
+    pub result: Option<String>,
+
+    /// How the main process ended: "exited", "killed" or "dumped"
+    pub exit_code: Option<String>,
+
+    /// Exit status, or the signal number for "killed"/"dumped"
+    pub exit_status: Option<i32>,
+
+    /// Last journal lines of the unit, oldest first
+    pub journal: Vec<String>,
+}
+
+impl Evidence {
+    /// "Result=exit-code, exited with status 1"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(result) = &self.result {
+            parts.push(format!("Result={}", result));
+        }
+
+        match (self.exit_code.as_deref(), self.exit_status) {
+            (Some("exited"), Some(status)) => parts.push(format!("exited with status {}", status)),
+            (Some(code), Some(signal)) => parts.push(format!("{} by signal {}", code, signal)),
+            (Some(code), None) => parts.push(code.to_string()),
+            _ => {}
+        }
+
+        if parts.is_empty() {
+            "no exit information".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// Canonical encoding used by the plan hash (schema v2)
+    pub(crate) fn encode(&self, enc: &mut CanonicalEncoder) {
+        enc.str(&self.unit);
+        enc.opt_str(self.result.as_deref());
+        enc.opt_str(self.exit_code.as_deref());
+
+        match self.exit_status {
+            Some(status) => {
+                enc.bool(true);
+                enc.i32(status);
+            }
+            None => enc.bool(false),
+        }
+
+        enc.list(self.journal.len());
+        for line in &self.journal {
+            enc.str(line);
+        }
+    }
+}
+
+pub fn print_evidence(evidence: &[Evidence]) {
+    for item in evidence {
+        println!("\nEvidence ({}):", item.unit);
+
+        if item.result.is_some() || item.exit_code.is_some() {
+            println!("  {}", item.summary());
+        }
+
+        if !item.journal.is_empty() {
+            println!("  Journal:");
+            for line in &item.journal {
+                println!("    {}", line);
+            }
+        }
+    }
+}
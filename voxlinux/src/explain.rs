@@ -32,6 +32,21 @@ pub enum ExplainCategory {
 
 
 
+impl ExplainCategory {
+    /// Stable tag used in the canonical plan encoding
+    pub fn tag(&self) -> &'static str {
+        match self {
+            ExplainCategory::WhatHappened => "what_happened",
+            ExplainCategory::WhyDetected => "why_detected",
+            ExplainCategory::WhySafe => "why_safe",
+            ExplainCategory::RiskAnalysis => "risk_analysis",
+            ExplainCategory::WhatWillExecute => "what_will_execute",
+            ExplainCategory::Preconditions => "preconditions",
+            ExplainCategory::WhyBlocked => "why_blocked",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ExplainBlock {
     pub level: u8,
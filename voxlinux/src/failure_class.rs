@@ -0,0 +1,8 @@
+use serde::{Serialize, Deserialize};
+
+/// System-wide failure classification (see voxlinuxd classifier)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FailureClass {
+    RuntimeFailure,
+    CoreIntegrityFailure,
+}
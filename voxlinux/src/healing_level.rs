@@ -0,0 +1,46 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealingLevel {
+    ObserveOnly,      // Stage 0
+    RuntimeSafe,      // Stage 1
+    AssistedRepair,
+    AutonomousRepair,// Stage 2
+}
+
+impl HealingLevel {
+    /// Short name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            HealingLevel::ObserveOnly => "observe",
+            HealingLevel::RuntimeSafe => "runtime-safe",
+            HealingLevel::AssistedRepair => "assisted",
+            HealingLevel::AutonomousRepair => "autonomous",
+        }
+    }
+}
+
+impl fmt::Display for HealingLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HealingLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "observe" => Ok(HealingLevel::ObserveOnly),
+            "runtime-safe" => Ok(HealingLevel::RuntimeSafe),
+            "assisted" => Ok(HealingLevel::AssistedRepair),
+            "autonomous" => Ok(HealingLevel::AutonomousRepair),
+            other => Err(format!(
+                "unknown healing level '{}' (expected observe, runtime-safe, assisted or autonomous)",
+                other
+            )),
+        }
+    }
+}
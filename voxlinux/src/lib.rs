@@ -1,2 +1,15 @@
+pub mod canonical;
 pub mod repair_plan;
+pub mod plan_status;
+pub mod plan_store;
+pub mod repair_action;
 pub mod explain;
+pub mod signing;
+pub mod healing_level;
+pub mod failure_class;
+pub mod protocol;
+pub mod command_runner;
+pub mod paths;
+pub mod evidence;
+pub mod system_state;
+pub mod transaction;
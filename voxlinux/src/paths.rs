@@ -0,0 +1,121 @@
+// paths.rs
+//
+// Every file the daemon and intentctl touch, resolved under an optional
+// root prefix. With `VOXLINUX_ROOT=/tmp/vx` (or `--root-dir /tmp/vx`) the
+// socket lives at /tmp/vx/run/voxlinux/voxlinux.sock, state under
+// /tmp/vx/var/lib/voxlinux, the pacman lock probe looks at
+// /tmp/vx/var/lib/pacman/db.lck, and so on, so everything can run as a
+// normal user against a fixture tree.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const ROOT_ENV: &str = "VOXLINUX_ROOT";
+
+static ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `dir` as the root prefix (from a --root-dir flag).
+/// Must run before any path is resolved; fails if the root is already fixed.
+pub fn set_root(dir: &Path) -> Result<(), String> {
+    let dir = if dir.is_absolute() {
+        dir.to_path_buf()
+    } else {
+        std::env::current_dir()
+        .map_err(|e| format!("cannot resolve root dir {}: {}", dir.display(), e))?
+        .join(dir)
+    };
+
+    ROOT.set(dir)
+    .map_err(|_| "root dir must be set before any path is used".to_string())
+}
+
+/// The root prefix: --root-dir, else $VOXLINUX_ROOT, else "/"
+pub fn root() -> &'static Path {
+    ROOT.get_or_init(|| {
+        std::env::var_os(ROOT_ENV)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/"))
+    })
+}
+
+/// Map an absolute system path into the root prefix
+pub fn resolve(path: &str) -> PathBuf {
+    root().join(path.trim_start_matches('/'))
+}
+
+pub fn runtime_dir() -> PathBuf {
+    resolve("/run/voxlinux")
+}
+
+pub fn plan_dir() -> PathBuf {
+    resolve("/run/voxlinux/plans")
+}
+
+pub fn socket_path() -> PathBuf {
+    resolve("/run/voxlinux/voxlinux.sock")
+}
+
+pub fn state_dir() -> PathBuf {
+    resolve("/var/lib/voxlinux")
+}
+
+/// Files saved before a plan runs, one directory per plan
+pub fn backup_dir() -> PathBuf {
+    resolve("/var/lib/voxlinux/backups")
+}
+
+pub fn log_dir() -> PathBuf {
+    resolve("/tmp/voxlinux")
+}
+
+pub fn exec_log() -> PathBuf {
+    resolve("/tmp/voxlinux/exec.log")
+}
+
+pub fn config_file() -> PathBuf {
+    resolve("/etc/voxlinux/config.toml")
+}
+
+pub fn config_dir() -> PathBuf {
+    resolve("/etc/voxlinux/config.d")
+}
+
+pub fn signing_key() -> PathBuf {
+    resolve("/var/lib/voxlinux/plan-signing.key")
+}
+
+pub fn public_key() -> PathBuf {
+    resolve("/var/lib/voxlinux/plan-signing.pub")
+}
+
+pub fn pacman_lock() -> PathBuf {
+    resolve("/var/lib/pacman/db.lck")
+}
+
+pub fn cgroup_root() -> PathBuf {
+    resolve("/sys/fs/cgroup")
+}
+
+pub fn pacman_cache_dir() -> PathBuf {
+    resolve("/var/cache/pacman/pkg")
+}
+
+pub fn journal_dir() -> PathBuf {
+    resolve("/var/log/journal")
+}
+
+pub fn coredump_dir() -> PathBuf {
+    resolve("/var/lib/systemd/coredump")
+}
+
+/// Root for unit tests that resolve paths: a per-process directory under
+/// the system temp dir. Must be called before anything else resolves one.
+#[cfg(test)]
+pub(crate) fn test_root() -> &'static Path {
+    let dir = std::env::temp_dir().join(format!("voxlinux-test-{}", std::process::id()));
+    let _ = set_root(&dir);
+
+    assert_eq!(root(), dir, "a path was resolved before test_root");
+    root()
+}
@@ -0,0 +1,207 @@
+// plan_status.rs
+//
+// Lifecycle of a stored repair plan. A plan starts Proposed; applying it
+// approves it, runs it and ends in Succeeded or Failed. A plan run by
+// `intentctl --local` waits in Unverified until voxlinuxd has watched its
+// verification targets for the stability window. Plans nobody acted
+// on end in Expired (issue gone) or Dismissed (operator said no), and a
+// successful plan can later be RolledBack. Every change is kept on the
+// plan as a timestamped, attributed `StatusChange`.
+
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PlanStatus {
+    #[default]
+    Proposed,
+    Approved,
+    Running,
+
+    /// Ran outside the daemon; its targets have not been watched yet
+    Unverified,
+    Succeeded,
+    Failed,
+    Expired,
+    Dismissed,
+    RolledBack,
+}
+
+impl PlanStatus {
+    /// States reachable from this one; empty for final states
+    pub fn next(self) -> &'static [PlanStatus] {
+        use PlanStatus::*;
+
+        match self {
+            Proposed => &[Approved, Expired, Dismissed],
+            Approved => &[Running, Expired, Dismissed],
+            Running => &[Succeeded, Failed, Unverified],
+            Unverified => &[Succeeded, Failed, RolledBack],
+            Succeeded => &[RolledBack],
+            // A failed plan may be approved again for a retry
+            Failed => &[Approved, RolledBack, Expired, Dismissed],
+            Expired | Dismissed | RolledBack => &[],
+        }
+    }
+
+    pub fn can_become(self, to: PlanStatus) -> bool {
+        self.next().contains(&to)
+    }
+
+    /// Still waiting for someone to act on it
+    pub fn is_open(self) -> bool {
+        matches!(self, PlanStatus::Proposed | PlanStatus::Approved | PlanStatus::Failed)
+    }
+
+    /// Short name used on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            PlanStatus::Proposed => "proposed",
+            PlanStatus::Approved => "approved",
+            PlanStatus::Running => "running",
+            PlanStatus::Unverified => "unverified",
+            PlanStatus::Succeeded => "succeeded",
+            PlanStatus::Failed => "failed",
+            PlanStatus::Expired => "expired",
+            PlanStatus::Dismissed => "dismissed",
+            PlanStatus::RolledBack => "rolled-back",
+        }
+    }
+}
+
+impl fmt::Display for PlanStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for PlanStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "proposed" => Ok(PlanStatus::Proposed),
+            "approved" => Ok(PlanStatus::Approved),
+            "running" => Ok(PlanStatus::Running),
+            "unverified" => Ok(PlanStatus::Unverified),
+            "succeeded" => Ok(PlanStatus::Succeeded),
+            "failed" => Ok(PlanStatus::Failed),
+            "expired" => Ok(PlanStatus::Expired),
+            "dismissed" => Ok(PlanStatus::Dismissed),
+            "rolled-back" => Ok(PlanStatus::RolledBack),
+            other => Err(format!(
+                "unknown plan status '{}' (expected proposed, approved, running, unverified, succeeded, failed, expired, dismissed or rolled-back)",
+                other
+            )),
+        }
+    }
+}
+
+/// One lifecycle step and who made it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatusChange {
+    pub timestamp: u64,
+    pub from: PlanStatus,
+    pub to: PlanStatus,
+    pub by: String,
+    pub reason: Option<String>,
+}
+
+impl StatusChange {
+    pub fn now(from: PlanStatus, to: PlanStatus, by: &str, reason: Option<String>) -> Self {
+        StatusChange {
+            timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+            from,
+            to,
+            by: by.to_string(),
+            reason,
+        }
+    }
+}
+
+/// A transition the lifecycle does not allow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionError {
+    pub from: PlanStatus,
+    pub to: PlanStatus,
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a {} plan cannot become {}", self.from, self.to)
+    }
+}
+
+impl std::error::Error for TransitionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PlanStatus::*;
+
+    const ALL: [PlanStatus; 9] = [
+        Proposed, Approved, Running, Unverified, Succeeded, Failed, Expired, Dismissed, RolledBack,
+    ];
+
+    #[test]
+    fn transition_table() {
+        let allowed = [
+            (Proposed, Approved),
+            (Proposed, Expired),
+            (Proposed, Dismissed),
+            (Approved, Running),
+            (Approved, Expired),
+            (Approved, Dismissed),
+            (Running, Succeeded),
+            (Running, Failed),
+            (Running, Unverified),
+            (Unverified, Succeeded),
+            (Unverified, Failed),
+            (Unverified, RolledBack),
+            (Succeeded, RolledBack),
+            (Failed, Approved),
+            (Failed, RolledBack),
+            (Failed, Expired),
+            (Failed, Dismissed),
+        ];
+
+        for from in ALL {
+            for to in ALL {
+                assert_eq!(
+                    from.can_become(to),
+                    allowed.contains(&(from, to)),
+                    "{} → {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn final_states() {
+        for status in [Expired, Dismissed, RolledBack] {
+            assert!(status.next().is_empty());
+        }
+    }
+
+    #[test]
+    fn open_states() {
+        let open: Vec<PlanStatus> = ALL.into_iter().filter(|s| s.is_open()).collect();
+        assert_eq!(open, [Proposed, Approved, Failed]);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for status in ALL {
+            assert_eq!(status.name().parse::<PlanStatus>(), Ok(status));
+        }
+
+        assert!("rolledback".parse::<PlanStatus>().is_err());
+    }
+}
@@ -0,0 +1,603 @@
+// plan_store.rs
+//
+// Storage for repair plans between the scan that builds them and whoever
+// applies, dismisses or lists them. `FsPlanStore` is the real one: one
+// `<id>.json` per plan under `paths::plan_dir()`, written via a temp file
+// and rename, with writers serialised by an `flock` on `<dir>/.lock` so
+// voxlinuxd and `intentctl --local` never interleave a read-modify-write.
+// `MemoryPlanStore` keeps plans in a map for offline runs.
+// The lifecycle fields of a stored plan are not signed, so the directory
+// must be writable by root (voxlinuxd) only.
+
+use crate::paths;
+use crate::plan_status::{PlanStatus, TransitionError};
+use crate::repair_plan::RepairPlan;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreError {
+    /// Empty or path-like plan ID
+    InvalidId(String),
+    NotFound(String),
+    Io(String),
+    Corrupt { id: String, error: String },
+    Transition(TransitionError),
+
+    /// The stored plan is no longer in the state an update expected
+    Conflict { id: String, reason: String },
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::InvalidId(id) => write!(f, "invalid plan id {:?}", id),
+            StoreError::NotFound(id) => write!(f, "plan '{}' not found", id),
+            StoreError::Io(e) => write!(f, "plan store i/o error: {}", e),
+            StoreError::Corrupt { id, error } => write!(f, "plan '{}' is unreadable: {}", id, error),
+            StoreError::Transition(e) => write!(f, "{}", e),
+            StoreError::Conflict { id, reason } => write!(f, "plan '{}' changed meanwhile: {}", id, reason),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+pub trait PlanStore: Send + Sync {
+    /// Store `plan` under its ID, atomically replacing any previous copy
+    fn put(&self, plan: &RepairPlan) -> Result<(), StoreError>;
+
+    fn get(&self, id: &str) -> Result<Option<RepairPlan>, StoreError>;
+
+    /// Every readable plan, ordered by ID
+    fn list(&self) -> Result<Vec<RepairPlan>, StoreError>;
+
+    /// `Ok(false)` if there was no such plan
+    fn remove(&self, id: &str) -> Result<bool, StoreError>;
+
+    /// Load the plan, let `change` modify it and store it back without
+    /// another writer in between. If `change` fails nothing is written.
+    fn update_with(
+        &self,
+        id: &str,
+        change: &mut dyn FnMut(&mut RepairPlan) -> Result<(), StoreError>,
+    ) -> Result<RepairPlan, StoreError>;
+
+    /// Transition the stored plan, see `update_with`
+    fn update_status(
+        &self,
+        id: &str,
+        to: PlanStatus,
+        by: &str,
+        reason: Option<String>,
+    ) -> Result<RepairPlan, StoreError> {
+        let mut reason = reason;
+
+        self.update_with(id, &mut |plan| {
+            plan.transition(to, by, reason.take()).map_err(StoreError::Transition)
+        })
+    }
+
+    fn list_by_status(&self, status: PlanStatus) -> Result<Vec<RepairPlan>, StoreError> {
+        Ok(self.list()?.into_iter().filter(|p| p.status == status).collect())
+    }
+
+    /// Remove finished plans (neither open nor running) whose last status
+    /// change is more than `retain_secs` old; returns the removed IDs
+    fn gc(&self, retain_secs: u64) -> Result<Vec<String>, StoreError> {
+        gc_finished(self, retain_secs)
+    }
+}
+
+/// Plan IDs become file names, so anything path-like is refused
+pub fn valid_plan_id(plan_id: &str) -> bool {
+    !plan_id.is_empty()
+    && plan_id
+    .chars()
+    .all(|c| c.is_ascii_alphanumeric() || "-_.@:\\".contains(c))
+    && !plan_id.starts_with('.')
+}
+
+fn check_id(id: &str) -> Result<(), StoreError> {
+    if valid_plan_id(id) {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidId(id.to_string()))
+    }
+}
+
+fn now_ts() -> u64 {
+    SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
+}
+
+/// IDs of the finished plans among `plans` last changed before the cutoff
+fn finished_before(plans: Vec<RepairPlan>, retain_secs: u64) -> Vec<String> {
+    let cutoff = now_ts().saturating_sub(retain_secs);
+
+    plans
+    .into_iter()
+    .filter(|plan| !plan.status.is_open() && !matches!(plan.status, PlanStatus::Running | PlanStatus::Unverified))
+    .filter(|plan| plan.status_history.last().map_or(0, |c| c.timestamp) <= cutoff)
+    .map(|plan| plan.id)
+    .collect()
+}
+
+fn gc_finished<S: PlanStore + ?Sized>(store: &S, retain_secs: u64) -> Result<Vec<String>, StoreError> {
+    let mut removed = Vec::new();
+
+    for id in finished_before(store.list()?, retain_secs) {
+        if store.remove(&id)? {
+            removed.push(id);
+        }
+    }
+
+    Ok(removed)
+}
+
+/// A parsed plan file and the metadata it was parsed at
+struct Cached {
+    modified: SystemTime,
+    len: u64,
+    plan: RepairPlan,
+}
+
+/// One JSON file per plan. Parsed plans are cached by file name and
+/// modification time, so listing only re-reads files that changed.
+pub struct FsPlanStore {
+    dir: PathBuf,
+    index: Mutex<HashMap<String, Cached>>,
+}
+
+static SYSTEM: OnceLock<FsPlanStore> = OnceLock::new();
+
+/// The store at `paths::plan_dir()`, shared by the whole process
+pub fn system_store() -> &'static FsPlanStore {
+    SYSTEM.get_or_init(|| FsPlanStore::new(paths::plan_dir()))
+}
+
+impl FsPlanStore {
+    pub fn new(dir: PathBuf) -> Self {
+        FsPlanStore {
+            dir,
+            index: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Exclusive `flock` on `<dir>/.lock`, released when the file is
+    /// dropped. Taken after the index mutex, which only covers this
+    /// process, by every method that changes the directory.
+    fn lock_dir(&self) -> Result<fs::File, StoreError> {
+        let io = |e: std::io::Error| StoreError::Io(format!("locking {}: {}", self.dir.display(), e));
+
+        fs::create_dir_all(&self.dir).map_err(io)?;
+
+        let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(self.dir.join(".lock"))
+        .map_err(io)?;
+
+        file.lock().map_err(io)?;
+        Ok(file)
+    }
+
+    fn write(&self, index: &mut HashMap<String, Cached>, plan: &RepairPlan) -> Result<(), StoreError> {
+        check_id(&plan.id)?;
+
+        let io = |what: &str, e: std::io::Error| StoreError::Io(format!("{} {}: {}", what, plan.id, e));
+
+        fs::create_dir_all(&self.dir).map_err(|e| io("creating directory for", e))?;
+
+        let json = serde_json::to_string_pretty(plan)
+        .map_err(|e| StoreError::Corrupt { id: plan.id.clone(), error: e.to_string() })?;
+
+        let tmp_path = self.dir.join(format!("{}.tmp", plan.id));
+        let final_path = self.path(&plan.id);
+
+        let mut file = fs::File::create(&tmp_path).map_err(|e| io("creating tmp plan", e))?;
+        file.write_all(json.as_bytes()).map_err(|e| io("writing tmp plan", e))?;
+        file.sync_all().map_err(|e| io("syncing", e))?;
+        fs::rename(&tmp_path, &final_path).map_err(|e| io("renaming", e))?;
+
+        if let Ok(meta) = fs::metadata(&final_path) {
+            index.insert(plan.id.clone(), Cached {
+                modified: meta.modified().unwrap_or(UNIX_EPOCH),
+                len: meta.len(),
+                plan: plan.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Cached plan if the file is unchanged, else re-read it
+    fn read(&self, index: &mut HashMap<String, Cached>, id: &str) -> Result<Option<RepairPlan>, StoreError> {
+        let path = self.path(id);
+
+        let meta = match fs::metadata(&path) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                index.remove(id);
+                return Ok(None);
+            }
+            Err(e) => return Err(StoreError::Io(format!("{}: {}", path.display(), e))),
+        };
+
+        let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+
+        if let Some(cached) = index.get(id) {
+            if cached.modified == modified && cached.len == meta.len() {
+                return Ok(Some(cached.plan.clone()));
+            }
+        }
+
+        let data = fs::read_to_string(&path)
+        .map_err(|e| StoreError::Io(format!("{}: {}", path.display(), e)))?;
+
+        let plan: RepairPlan = serde_json::from_str(&data)
+        .map_err(|e| StoreError::Corrupt { id: id.to_string(), error: e.to_string() })?;
+
+        index.insert(id.to_string(), Cached {
+            modified,
+            len: meta.len(),
+            plan: plan.clone(),
+        });
+
+        Ok(Some(plan))
+    }
+
+    /// `list` with the index already locked
+    fn list_in(&self, index: &mut HashMap<String, Cached>) -> Result<Vec<RepairPlan>, StoreError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(StoreError::Io(format!("{}: {}", self.dir.display(), e))),
+        };
+
+        let ids: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            name.strip_suffix(".json").filter(|id| valid_plan_id(id)).map(str::to_string)
+        })
+        .collect();
+
+        index.retain(|id, _| ids.contains(id));
+
+        let mut plans = BTreeMap::new();
+
+        for id in ids {
+            match self.read(index, &id) {
+                Ok(Some(plan)) => {
+                    plans.insert(id, plan);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("[STORE] skipping {}", e),
+            }
+        }
+
+        Ok(plans.into_values().collect())
+    }
+
+    /// Remove the plan file; the caller holds both locks
+    fn unlink(&self, index: &mut HashMap<String, Cached>, id: &str) -> Result<bool, StoreError> {
+        index.remove(id);
+
+        match fs::remove_file(self.path(id)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(StoreError::Io(format!("removing {}: {}", id, e))),
+        }
+    }
+
+    /// Temp files left behind by a writer that died mid-write
+    fn remove_stale_tmp(&self, retain_secs: u64) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        let cutoff = SystemTime::now()
+        .checked_sub(std::time::Duration::from_secs(retain_secs))
+        .unwrap_or(UNIX_EPOCH);
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if path.extension().and_then(|e| e.to_str()) != Some("tmp") {
+                continue;
+            }
+
+            let stale = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .map(|m| m <= cutoff)
+            .unwrap_or(false);
+
+            if stale {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+impl PlanStore for FsPlanStore {
+    fn put(&self, plan: &RepairPlan) -> Result<(), StoreError> {
+        let mut index = self.index.lock().unwrap();
+        let _lock = self.lock_dir()?;
+        self.write(&mut index, plan)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<RepairPlan>, StoreError> {
+        check_id(id)?;
+
+        let mut index = self.index.lock().unwrap();
+        self.read(&mut index, id)
+    }
+
+    fn list(&self) -> Result<Vec<RepairPlan>, StoreError> {
+        let mut index = self.index.lock().unwrap();
+        self.list_in(&mut index)
+    }
+
+    fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        check_id(id)?;
+
+        let mut index = self.index.lock().unwrap();
+        let _lock = self.lock_dir()?;
+        self.unlink(&mut index, id)
+    }
+
+    fn update_with(
+        &self,
+        id: &str,
+        change: &mut dyn FnMut(&mut RepairPlan) -> Result<(), StoreError>,
+    ) -> Result<RepairPlan, StoreError> {
+        check_id(id)?;
+
+        let mut index = self.index.lock().unwrap();
+        let _lock = self.lock_dir()?;
+
+        let mut plan = self
+        .read(&mut index, id)?
+        .ok_or_else(|| StoreError::NotFound(id.to_string()))?;
+
+        change(&mut plan)?;
+        self.write(&mut index, &plan)?;
+        Ok(plan)
+    }
+
+    /// Listing and removal happen under one lock, so a plan another
+    /// process is rolling back right now is not removed under it
+    fn gc(&self, retain_secs: u64) -> Result<Vec<String>, StoreError> {
+        let mut index = self.index.lock().unwrap();
+        let _lock = self.lock_dir()?;
+
+        self.remove_stale_tmp(retain_secs);
+
+        let mut removed = Vec::new();
+
+        for id in finished_before(self.list_in(&mut index)?, retain_secs) {
+            if self.unlink(&mut index, &id)? {
+                removed.push(id);
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Plans in a map; nothing touches the disk
+#[derive(Default)]
+pub struct MemoryPlanStore {
+    plans: Mutex<BTreeMap<String, RepairPlan>>,
+}
+
+impl MemoryPlanStore {
+    pub fn new() -> Self {
+        MemoryPlanStore::default()
+    }
+}
+
+impl PlanStore for MemoryPlanStore {
+    fn put(&self, plan: &RepairPlan) -> Result<(), StoreError> {
+        check_id(&plan.id)?;
+        self.plans.lock().unwrap().insert(plan.id.clone(), plan.clone());
+        Ok(())
+    }
+
+    fn get(&self, id: &str) -> Result<Option<RepairPlan>, StoreError> {
+        check_id(id)?;
+        Ok(self.plans.lock().unwrap().get(id).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<RepairPlan>, StoreError> {
+        Ok(self.plans.lock().unwrap().values().cloned().collect())
+    }
+
+    fn remove(&self, id: &str) -> Result<bool, StoreError> {
+        check_id(id)?;
+        Ok(self.plans.lock().unwrap().remove(id).is_some())
+    }
+
+    fn update_with(
+        &self,
+        id: &str,
+        change: &mut dyn FnMut(&mut RepairPlan) -> Result<(), StoreError>,
+    ) -> Result<RepairPlan, StoreError> {
+        check_id(id)?;
+
+        let mut plans = self.plans.lock().unwrap();
+
+        let stored = plans
+        .get_mut(id)
+        .ok_or_else(|| StoreError::NotFound(id.to_string()))?;
+
+        // Work on a copy so a failed change leaves the stored plan alone
+        let mut plan = stored.clone();
+        change(&mut plan)?;
+        *stored = plan.clone();
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan_status::StatusChange;
+    use crate::repair_action::RepairAction;
+    use crate::repair_plan::RiskLevel;
+
+    fn plan(unit: &str) -> RepairPlan {
+        RepairPlan::new(
+            format!("{} failed", unit),
+            format!("restart-{}", unit),
+            RiskLevel::Low,
+            vec![RepairAction::RestartUnit { unit: unit.into() }],
+        )
+        .sealed()
+    }
+
+    fn temp_store(name: &str) -> FsPlanStore {
+        let dir = std::env::temp_dir().join(format!("voxlinux-store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        FsPlanStore::new(dir)
+    }
+
+    /// What every store must do
+    fn round_trip(store: &dyn PlanStore) {
+        let a = plan("a.service");
+        let b = plan("b.service");
+
+        store.put(&b).unwrap();
+        store.put(&a).unwrap();
+
+        assert_eq!(store.get(&a.id).unwrap(), Some(a.clone()));
+        assert_eq!(store.get("missing").unwrap(), None);
+
+        let ids: Vec<String> = store.list().unwrap().into_iter().map(|p| p.id).collect();
+        assert_eq!(ids, [a.id.clone(), b.id.clone()]);
+
+        assert!(store.remove(&b.id).unwrap());
+        assert!(!store.remove(&b.id).unwrap());
+        assert_eq!(store.list().unwrap(), [a]);
+    }
+
+    fn status_updates(store: &dyn PlanStore) {
+        let plan = plan("c.service");
+        store.put(&plan).unwrap();
+
+        let approved = store.update_status(&plan.id, PlanStatus::Approved, "test", None).unwrap();
+        assert_eq!(approved.status, PlanStatus::Approved);
+        assert_eq!(store.get(&plan.id).unwrap(), Some(approved.clone()));
+
+        let refused = store.update_status(&plan.id, PlanStatus::Succeeded, "test", None);
+        assert!(matches!(refused, Err(StoreError::Transition(_))));
+
+        // A failed change writes nothing
+        let conflict = store.update_with(&plan.id, &mut |stored| {
+            stored.superseded_by = Some("newer".into());
+            Err(StoreError::Conflict { id: stored.id.clone(), reason: "test".into() })
+        });
+        assert!(matches!(conflict, Err(StoreError::Conflict { .. })));
+        assert_eq!(store.get(&plan.id).unwrap(), Some(approved));
+
+        assert_eq!(
+            store.update_status("missing", PlanStatus::Approved, "test", None),
+            Err(StoreError::NotFound("missing".into()))
+        );
+    }
+
+    fn gc_keeps_open_and_recent(store: &dyn PlanStore) {
+        let finished_at = |status: PlanStatus, timestamp: u64, unit: &str| {
+            let mut plan = plan(unit);
+            plan.status = status;
+            plan.status_history.push(StatusChange {
+                timestamp,
+                ..StatusChange::now(PlanStatus::Proposed, status, "test", None)
+            });
+            store.put(&plan).unwrap();
+            plan.id
+        };
+
+        let old_done = finished_at(PlanStatus::Succeeded, 1, "old.service");
+        let recent_done = finished_at(PlanStatus::Expired, now_ts(), "recent.service");
+        let old_open = finished_at(PlanStatus::Failed, 1, "open.service");
+        let old_unverified = finished_at(PlanStatus::Unverified, 1, "unverified.service");
+
+        assert_eq!(store.gc(3600).unwrap(), [old_done]);
+
+        for id in [recent_done, old_open, old_unverified] {
+            assert!(store.get(&id).unwrap().is_some(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn memory_store() {
+        round_trip(&MemoryPlanStore::new());
+        status_updates(&MemoryPlanStore::new());
+        gc_keeps_open_and_recent(&MemoryPlanStore::new());
+    }
+
+    #[test]
+    fn fs_store() {
+        round_trip(&temp_store("round-trip"));
+        status_updates(&temp_store("status"));
+        gc_keeps_open_and_recent(&temp_store("gc"));
+    }
+
+    #[test]
+    fn fs_store_sees_other_writers() {
+        let store = temp_store("writers");
+        let plan = plan("d.service");
+        store.put(&plan).unwrap();
+
+        // Another process (a second store on the same directory) moves it on
+        let other = FsPlanStore::new(store.dir().to_path_buf());
+        other.update_status(&plan.id, PlanStatus::Dismissed, "operator", None).unwrap();
+
+        assert_eq!(store.get(&plan.id).unwrap().unwrap().status, PlanStatus::Dismissed);
+    }
+
+    #[test]
+    fn fs_store_skips_corrupt_and_foreign_files() {
+        let store = temp_store("corrupt");
+        let plan = plan("e.service");
+        store.put(&plan).unwrap();
+
+        fs::write(store.dir().join("broken.json"), "{").unwrap();
+        fs::write(store.dir().join("notes.txt"), "hello").unwrap();
+
+        assert_eq!(store.list().unwrap(), [plan]);
+        assert!(matches!(store.get("broken"), Err(StoreError::Corrupt { .. })));
+    }
+
+    #[test]
+    fn path_like_ids_are_refused() {
+        let store = temp_store("ids");
+
+        for id in ["", "../etc/passwd", "a/b", ".lock"] {
+            assert_eq!(store.get(id), Err(StoreError::InvalidId(id.into())));
+        }
+
+        let mut plan = plan("f.service");
+        plan.id = "../escape".into();
+        assert!(matches!(store.put(&plan), Err(StoreError::InvalidId(_))));
+    }
+}
@@ -0,0 +1,286 @@
+// protocol.rs
+//
+// Request/response protocol spoken on the voxlinuxd Unix socket.
+//
+// Every message is a frame: a u32 big-endian length followed by that many
+// bytes of JSON. The JSON is an `Envelope` carrying the protocol version,
+// so a daemon can refuse clients it does not understand with a typed error
+// instead of misreading them.
+
+use crate::failure_class::FailureClass;
+use crate::healing_level::HealingLevel;
+use crate::repair_action::RepairAction;
+use crate::repair_plan::{RepairPlan, RiskLevel};
+use serde::de::DeserializeOwned;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::io::{self, Read, Write};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Upper bound for a single frame; plans are a few KiB at most
+pub const MAX_FRAME_LEN: u32 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub version: u32,
+    pub body: T,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Request {
+    ListPlans,
+    GetPlan { id: String },
+    Apply { id: String },
+    DryRun { id: String },
+
+    /// Mark a plan as not wanted; it stays stored but cannot be applied
+    Dismiss { id: String, reason: Option<String> },
+
+    /// Undo a plan that ran, using the journal kept by its transaction
+    Rollback { id: String },
+
+    GetStatus,
+    GetHistory { limit: usize },
+    SetHealingLevel { level: HealingLevel },
+
+    /// Drop to ObserveOnly for `duration_secs`, then restore the current level
+    Pause { duration_secs: u64, reason: String },
+
+    /// End a pause early
+    Resume,
+
+    /// Keep the connection open and stream `Response::Event`s
+    Subscribe,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Response {
+    Plans(Vec<RepairPlan>),
+    Plan(RepairPlan),
+    Executed(ExecutionReport),
+    Status(DaemonStatus),
+    History(Vec<HistoryEntry>),
+    HealingLevel(HealingLevel),
+    Paused(Pause),
+    Subscribed,
+    Event(Event),
+    Error(DaemonError),
+}
+
+/// Something the daemon did, pushed to subscribers as it happens
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Event {
+    PlanCreated { plan_id: String, issue: String, risk: RiskLevel },
+    PlanSuperseded { old_id: String, new_id: String },
+
+    /// The issue behind an open plan is gone; the plan is now Expired
+    PlanExpired { plan_id: String },
+    ExecutionStarted { plan_id: String },
+    ActionResult {
+        plan_id: String,
+        action: RepairAction,
+        error: Option<String>,
+    },
+    ExecutionFinished { plan_id: String, success: bool },
+
+    /// `plan_id` is None when the healing gate closed for the whole system
+    HealingBlocked { plan_id: Option<String>, reason: String },
+
+    FailureClassChanged {
+        from: Option<FailureClass>,
+        to: FailureClass,
+    },
+
+    HealingLevelChanged(LevelChange),
+}
+
+/// A timed autonomy pause
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Pause {
+    /// UNIX timestamp at which the pause ends
+    pub until: u64,
+    pub reason: String,
+
+    /// Level restored when the pause ends
+    pub resume_level: HealingLevel,
+    pub by: String,
+}
+
+/// One healing-level change and who made it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LevelChange {
+    pub timestamp: u64,
+    pub from: HealingLevel,
+    pub to: HealingLevel,
+    pub by: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExecutionReport {
+    pub plan_id: String,
+    pub dry_run: bool,
+    pub actions: Vec<RepairAction>,
+
+    /// The actions ran and the plan's targets are being watched for the
+    /// stability window; `ExecutionFinished` reports the outcome
+    #[serde(default)]
+    pub verifying: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DaemonStatus {
+    pub healing_level: HealingLevel,
+    pub failure_class: Option<FailureClass>,
+    pub healing_allowed: bool,
+    pub last_scan: Option<u64>,
+    pub plans: usize,
+
+    #[serde(default)]
+    pub pause: Option<Pause>,
+
+    #[serde(default)]
+    pub last_level_change: Option<LevelChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum HistoryOutcome {
+    Succeeded,
+    Failed { error: String },
+    Blocked { reason: String },
+    RolledBack,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HistoryEntry {
+    pub timestamp: u64,
+    pub plan_id: String,
+    pub issue: String,
+    pub outcome: HistoryOutcome,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DaemonError {
+    UnsupportedVersion { got: u32, supported: u32 },
+    MalformedRequest(String),
+    PlanNotFound(String),
+    IntegrityFailure(String),
+    SignatureInvalid(String),
+    PolicyBlocked(String),
+    PermissionDenied(String),
+    ExecutionFailed { action: String, error: String },
+
+    /// Every action ran, but a verification target broke within the stability window
+    VerificationFailed(String),
+
+    Internal(String),
+}
+
+impl fmt::Display for DaemonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DaemonError::UnsupportedVersion { got, supported } => {
+                write!(f, "protocol version {} not supported (daemon speaks {})", got, supported)
+            }
+            DaemonError::MalformedRequest(e) => write!(f, "malformed request: {}", e),
+            DaemonError::PlanNotFound(id) => write!(f, "plan '{}' not found", id),
+            DaemonError::IntegrityFailure(e) => write!(f, "integrity check failed: {}", e),
+            DaemonError::SignatureInvalid(e) => write!(f, "signature check failed: {}", e),
+            DaemonError::PolicyBlocked(e) => write!(f, "blocked by policy: {}", e),
+            DaemonError::PermissionDenied(e) => write!(f, "permission denied: {}", e),
+            DaemonError::ExecutionFailed { action, error } => {
+                write!(f, "action '{}' failed: {}", action, error)
+            }
+            DaemonError::VerificationFailed(e) => write!(f, "repair did not hold: {}", e),
+            DaemonError::Internal(e) => write!(f, "internal daemon error: {}", e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    Io(io::Error),
+    TooLarge(u32),
+    Malformed(String),
+    UnsupportedVersion(u32),
+}
+
+impl From<io::Error> for FrameError {
+    fn from(e: io::Error) -> Self {
+        FrameError::Io(e)
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Io(e) => write!(f, "i/o error: {}", e),
+            FrameError::TooLarge(len) => write!(f, "frame of {} bytes exceeds limit", len),
+            FrameError::Malformed(e) => write!(f, "malformed message: {}", e),
+            FrameError::UnsupportedVersion(v) => write!(f, "unsupported protocol version {}", v),
+        }
+    }
+}
+
+impl FrameError {
+    /// Peer closed the connection between frames
+    pub fn is_eof(&self) -> bool {
+        matches!(self, FrameError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof)
+    }
+}
+
+/// Write one length-prefixed frame
+pub fn write_frame<W: Write>(w: &mut W, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+    .ok()
+    .filter(|len| *len <= MAX_FRAME_LEN)
+    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+
+    w.write_all(&len.to_be_bytes())?;
+    w.write_all(payload)?;
+    w.flush()
+}
+
+/// Read one length-prefixed frame
+pub fn read_frame<R: Read>(r: &mut R) -> Result<Vec<u8>, FrameError> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME_LEN {
+        return Err(FrameError::TooLarge(len));
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    r.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Wrap `body` in a versioned envelope and send it as one frame
+pub fn send<W: Write, T: Serialize>(w: &mut W, body: &T) -> io::Result<()> {
+    let envelope = Envelope {
+        version: PROTOCOL_VERSION,
+        body,
+    };
+
+    let json = serde_json::to_vec(&envelope)
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    write_frame(w, &json)
+}
+
+/// Receive one frame and unwrap its envelope, checking the version
+pub fn recv<R: Read, T: DeserializeOwned>(r: &mut R) -> Result<T, FrameError> {
+    let payload = read_frame(r)?;
+
+    let envelope: Envelope<serde_json::Value> = serde_json::from_slice(&payload)
+    .map_err(|e| FrameError::Malformed(e.to_string()))?;
+
+    if envelope.version != PROTOCOL_VERSION {
+        return Err(FrameError::UnsupportedVersion(envelope.version));
+    }
+
+    serde_json::from_value(envelope.body)
+    .map_err(|e| FrameError::Malformed(e.to_string()))
+}
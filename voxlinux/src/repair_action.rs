@@ -0,0 +1,320 @@
+use crate::canonical::CanonicalEncoder;
+use crate::command_runner::CommandRunner;
+use crate::paths;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+
+/// A single step of a repair plan.
+///
+/// Actions are executed directly (program + argv), never through a shell,
+/// so unit names and paths can't be used to inject extra commands.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RepairAction {
+    RestartUnit { unit: String },
+    ResetFailed { unit: String },
+    DisableUnit { unit: String },
+    EnableUnit { unit: String },
+    DaemonReload,
+    RemoveFile { path: String },
+    RunPackageSync,
+
+    /// Runtime MemoryHigh/MemoryMax drop-in (`systemctl set-property --runtime`);
+    /// gone after a reboot or `systemctl revert`
+    SetMemoryLimit { unit: String, memory_high: u64, memory_max: u64 },
+
+    /// Remove cached packages, keeping the newest `keep` versions of each
+    TrimPackageCache { dir: String, keep: u32 },
+
+    /// Shrink the journal in `dir` to at most `max_size_mb`
+    VacuumJournal { dir: String, max_size_mb: u32 },
+
+    /// Delete core dumps in `dir` older than `older_than_days`
+    PruneCoredumps { dir: String, older_than_days: u32 },
+
+    /// Step that needs a human; never executed automatically.
+    Manual { instruction: String },
+}
+
+impl RepairAction {
+    /// systemd unit this action operates on, if any
+    pub fn unit(&self) -> Option<&str> {
+        match self {
+            RepairAction::RestartUnit { unit }
+            | RepairAction::ResetFailed { unit }
+            | RepairAction::DisableUnit { unit }
+            | RepairAction::EnableUnit { unit }
+            | RepairAction::SetMemoryLimit { unit, .. } => Some(unit),
+            _ => None,
+        }
+    }
+
+    /// Filesystem path this action operates on, if any
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            RepairAction::RemoveFile { path }
+            | RepairAction::TrimPackageCache { dir: path, .. }
+            | RepairAction::VacuumJournal { dir: path, .. }
+            | RepairAction::PruneCoredumps { dir: path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Program and arguments for this action.
+    /// `None` means the action cannot be executed automatically.
+    pub fn argv(&self) -> Option<(&'static str, Vec<String>)> {
+        match self {
+            RepairAction::RestartUnit { unit } => {
+                Some(("systemctl", vec!["restart".into(), "--".into(), unit.clone()]))
+            }
+            RepairAction::ResetFailed { unit } => {
+                Some(("systemctl", vec!["reset-failed".into(), "--".into(), unit.clone()]))
+            }
+            RepairAction::DisableUnit { unit } => {
+                Some(("systemctl", vec!["disable".into(), "--".into(), unit.clone()]))
+            }
+            RepairAction::EnableUnit { unit } => {
+                Some(("systemctl", vec!["enable".into(), "--".into(), unit.clone()]))
+            }
+            RepairAction::DaemonReload => {
+                Some(("systemctl", vec!["daemon-reload".into()]))
+            }
+            RepairAction::RemoveFile { path } => {
+                Some(("rm", vec!["-f".into(), "--".into(), path.clone()]))
+            }
+            RepairAction::RunPackageSync => {
+                Some(("pacman", vec!["-Sy".into()]))
+            }
+            RepairAction::SetMemoryLimit { unit, memory_high, memory_max } => {
+                Some(("systemctl", vec![
+                    "set-property".into(),
+                    "--runtime".into(),
+                    "--".into(),
+                    unit.clone(),
+                    format!("MemoryHigh={}", memory_high),
+                    format!("MemoryMax={}", memory_max),
+                ]))
+            }
+            RepairAction::TrimPackageCache { dir, keep } => {
+                Some(("paccache", vec![
+                    "-r".into(),
+                    "-k".into(),
+                    keep.to_string(),
+                    "-c".into(),
+                    dir.clone(),
+                ]))
+            }
+            RepairAction::VacuumJournal { dir, max_size_mb } => {
+                Some(("journalctl", vec![
+                    "-D".into(),
+                    dir.clone(),
+                    format!("--vacuum-size={}M", max_size_mb),
+                ]))
+            }
+            RepairAction::PruneCoredumps { dir, older_than_days } => {
+                // dir is validated absolute, so find never reads it as an option
+                Some(("find", vec![
+                    dir.clone(),
+                    "-xdev".into(),
+                    "-type".into(),
+                    "f".into(),
+                    "-mtime".into(),
+                    format!("+{}", older_than_days),
+                    "-delete".into(),
+                ]))
+            }
+            RepairAction::Manual { .. } => None,
+        }
+    }
+
+    /// How to reverse this action after it ran
+    pub fn undo(&self) -> Undo {
+        match self {
+            // Nothing persistent changes; the unit is just in a new run
+            RepairAction::RestartUnit { .. }
+            | RepairAction::ResetFailed { .. }
+            | RepairAction::DaemonReload
+            | RepairAction::Manual { .. } => Undo::Nothing,
+
+            RepairAction::DisableUnit { unit } => Undo::Run(RepairAction::EnableUnit { unit: unit.clone() }),
+            RepairAction::EnableUnit { unit } => Undo::Run(RepairAction::DisableUnit { unit: unit.clone() }),
+
+            RepairAction::RemoveFile { .. } => Undo::RestoreFiles { then: None },
+
+            // Putting the runtime drop-ins back only takes effect after a reload
+            RepairAction::SetMemoryLimit { .. } => Undo::RestoreFiles {
+                then: Some(RepairAction::DaemonReload),
+            },
+
+            // Backing up what these delete would defeat their purpose
+            RepairAction::RunPackageSync
+            | RepairAction::TrimPackageCache { .. }
+            | RepairAction::VacuumJournal { .. }
+            | RepairAction::PruneCoredumps { .. } => Undo::Irreversible,
+        }
+    }
+
+    /// Files to copy before the plan runs, so `Undo::RestoreFiles` can put
+    /// them back (including files that do not exist yet)
+    pub fn backup_paths(&self) -> Vec<String> {
+        match self {
+            RepairAction::RemoveFile { path } => vec![path.clone()],
+            RepairAction::SetMemoryLimit { unit, .. } => {
+                let dropins = paths::resolve(&format!("/run/systemd/system.control/{}.d", unit));
+
+                ["50-MemoryHigh.conf", "50-MemoryMax.conf"]
+                .iter()
+                .map(|name| dropins.join(name).to_string_lossy().into_owned())
+                .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Reject targets that could be misread by the invoked program
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(unit) = self.unit() {
+            if !is_valid_unit_name(unit) {
+                return Err(format!("invalid unit name '{}'", unit));
+            }
+        }
+
+        if let Some(path) = self.path() {
+            if !path.starts_with('/') || path.split('/').any(|c| c == "..") {
+                return Err(format!("path '{}' must be absolute and normalized", path));
+            }
+        }
+
+        match self {
+            RepairAction::TrimPackageCache { keep: 0, .. } => {
+                return Err("package cache trim must keep at least one version".into());
+            }
+            RepairAction::SetMemoryLimit { memory_high, memory_max, .. }
+                if *memory_high == 0 || memory_high > memory_max =>
+            {
+                return Err("MemoryHigh must be non-zero and at most MemoryMax".into());
+            }
+            RepairAction::VacuumJournal { max_size_mb: 0, .. } => {
+                return Err("journal vacuum size must be at least 1M".into());
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Canonical encoding used by the plan hash
+    pub(crate) fn encode(&self, enc: &mut CanonicalEncoder) {
+        match self {
+            RepairAction::RestartUnit { unit } => {
+                enc.tag("restart_unit");
+                enc.str(unit);
+            }
+            RepairAction::ResetFailed { unit } => {
+                enc.tag("reset_failed");
+                enc.str(unit);
+            }
+            RepairAction::DisableUnit { unit } => {
+                enc.tag("disable_unit");
+                enc.str(unit);
+            }
+            RepairAction::EnableUnit { unit } => {
+                enc.tag("enable_unit");
+                enc.str(unit);
+            }
+            RepairAction::DaemonReload => enc.tag("daemon_reload"),
+            RepairAction::RemoveFile { path } => {
+                enc.tag("remove_file");
+                enc.str(path);
+            }
+            RepairAction::RunPackageSync => enc.tag("run_package_sync"),
+            RepairAction::SetMemoryLimit { unit, memory_high, memory_max } => {
+                enc.tag("set_memory_limit");
+                enc.str(unit);
+                enc.u64(*memory_high);
+                enc.u64(*memory_max);
+            }
+            RepairAction::TrimPackageCache { dir, keep } => {
+                enc.tag("trim_package_cache");
+                enc.str(dir);
+                enc.u32(*keep);
+            }
+            RepairAction::VacuumJournal { dir, max_size_mb } => {
+                enc.tag("vacuum_journal");
+                enc.str(dir);
+                enc.u32(*max_size_mb);
+            }
+            RepairAction::PruneCoredumps { dir, older_than_days } => {
+                enc.tag("prune_coredumps");
+                enc.str(dir);
+                enc.u32(*older_than_days);
+            }
+            RepairAction::Manual { instruction } => {
+                enc.tag("manual");
+                enc.str(instruction);
+            }
+        }
+    }
+
+    /// Validate and run the action, returning an error on failure
+    pub fn execute(&self, runner: &dyn CommandRunner) -> Result<(), String> {
+        self.validate()?;
+
+        let (program, args) = self
+        .argv()
+        .ok_or_else(|| format!("manual step: {}", self))?;
+
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let output = runner
+        .run(program, &args)
+        .map_err(|e| format!("failed to invoke {}: {}", program, e))?;
+
+        if !output.success() {
+            return Err(match output.code {
+                Some(code) => format!("{} exited with status {}", program, code),
+                None => format!("{} was killed by a signal", program),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Reverse step of an action, see `RepairAction::undo`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Undo {
+    /// The action leaves nothing behind that needs reverting
+    Nothing,
+
+    /// Run this action
+    Run(RepairAction),
+
+    /// Put the files from `backup_paths` back as they were, then run `then`
+    RestoreFiles { then: Option<RepairAction> },
+
+    /// Cannot be reversed; whatever it removed is gone
+    Irreversible,
+}
+
+/// Characters accepted by systemd in unit names, plus a mandatory suffix
+fn is_valid_unit_name(unit: &str) -> bool {
+    !unit.is_empty()
+    && unit.len() <= 255
+    && !unit.starts_with('-')
+    && unit.contains('.')
+    && unit
+    .chars()
+    .all(|c| c.is_ascii_alphanumeric() || ":-_.\\@".contains(c))
+}
+
+impl fmt::Display for RepairAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepairAction::Manual { instruction } => write!(f, "{}", instruction),
+            _ => {
+                let (program, args) = self.argv().unwrap_or(("", Vec::new()));
+                write!(f, "{} {}", program, args.join(" "))
+            }
+        }
+    }
+}
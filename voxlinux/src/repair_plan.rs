@@ -1,8 +1,21 @@
-use crate::explain::ExplainBlock;
+use crate::evidence::Evidence;
+use crate::explain::{ExplainBlock, ExplainCategory};
+use crate::repair_action::{RepairAction, Undo};
+use crate::canonical::CanonicalEncoder;
+use crate::system_state::SystemState;
+use crate::plan_status::{PlanStatus, StatusChange, TransitionError};
 use serde::{Serialize, Deserialize};
-use sha2::{Sha256, Digest};
+use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// Schema (and hash algorithm) version written into new plans.
+/// v2 adds `evidence` to the hash, v3 adds `fingerprint`, v4 `verify`.
+pub const PLAN_SCHEMA_VERSION: u32 = 4;
+
+/// Versions this build knows how to hash; anything else is rejected
+pub const SUPPORTED_SCHEMA_VERSIONS: &[u32] = &[1, 2, 3, 4];
+
+/// Ordered from least to most dangerous
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum RiskLevel {
     Low,
     Medium,
@@ -11,7 +24,17 @@ pub enum RiskLevel {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RepairPlan {
-    pub id: String,                 // NEW
+    /// Missing in pre-versioned plans, which deserialize as 0 and are rejected
+    #[serde(default)]
+    pub schema_version: u32,
+
+    pub id: String,
+
+    /// Stable key of the issue and its target ("restart-nginx.service"):
+    /// a rebuilt plan for the same issue has the same fingerprint (schema v3+)
+    #[serde(default)]
+    pub fingerprint: String,
+
     pub issue: String,
     pub risk: RiskLevel,
     pub confidence_high: bool,
@@ -19,34 +42,485 @@ pub struct RepairPlan {
     pub reversible: bool,
     pub requires_reboot: bool,
 
-    pub actions: Vec<String>,
+    pub actions: Vec<RepairAction>,
     pub explain: Vec<ExplainBlock>,
 
+    /// Journal and exit details backing the diagnosis (schema v2+)
+    #[serde(default)]
+    pub evidence: Vec<Evidence>,
+
+    /// States that must hold through the stability window after the
+    /// repair for it to count as a success (schema v4+)
+    #[serde(default)]
+    pub verify: Vec<SystemState>,
+
     pub integrity_hash: String,
+
+    /// hex ed25519 signature over `integrity_hash`, see `signing`
+    #[serde(default)]
+    pub signature: String,
+
+    /// Set when a rebuild for the same fingerprint changed the actions.
+    /// Lifecycle metadata, outside the hash like the signature: it, `status`
+    /// and `status_history` are only as trustworthy as the plan directory,
+    /// which voxlinuxd keeps at mode 0700 and refuses to start without.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
+
+    /// Where the plan is in its lifecycle; like `superseded_by` not hashed
+    #[serde(default)]
+    pub status: PlanStatus,
+
+    /// Every status change, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub status_history: Vec<StatusChange>,
+}
+
+impl RiskLevel {
+    /// Stable tag used in the canonical plan encoding
+    pub fn tag(&self) -> &'static str {
+        match self {
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityError {
+    UnsupportedSchema(u32),
+    HashMismatch,
+}
+
+impl fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityError::UnsupportedSchema(v) => write!(
+                f,
+                "unsupported plan schema version {} (supported: {:?})",
+                v, SUPPORTED_SCHEMA_VERSIONS
+            ),
+            IntegrityError::HashMismatch => write!(f, "integrity hash mismatch"),
+        }
+    }
 }
 
 impl RepairPlan {
-    pub fn compute_hash(&self) -> String {
-        let mut hasher = Sha256::new();
+    /// Hash of the plan content using the algorithm of `schema_version`.
+    /// `integrity_hash` and `signature` are never part of the hash.
+    pub fn compute_hash(&self) -> Result<String, IntegrityError> {
+        match self.schema_version {
+            1 => Ok(self.hash_v1()),
+            2 => Ok(self.hash_v2()),
+            3 => Ok(self.hash_v3()),
+            4 => Ok(self.hash_v4()),
+            v => Err(IntegrityError::UnsupportedSchema(v)),
+        }
+    }
+
+    /// Unsealed plan of the current schema: low confidence, reversible,
+    /// no reboot and nothing to explain, attach or verify yet. Finish it
+    /// with the `with_*` setters and `sealed`.
+    pub fn new(
+        issue: impl Into<String>,
+        fingerprint: impl Into<String>,
+        risk: RiskLevel,
+        actions: Vec<RepairAction>,
+    ) -> Self {
+        RepairPlan {
+            schema_version: PLAN_SCHEMA_VERSION,
+            id: String::new(),
+            fingerprint: fingerprint.into(),
+            issue: issue.into(),
+            risk,
+            confidence_high: false,
+            reversible: true,
+            requires_reboot: false,
+            actions,
+            explain: Vec::new(),
+            evidence: Vec::new(),
+            verify: Vec::new(),
+            integrity_hash: String::new(),
+            signature: String::new(),
+            superseded_by: None,
+            status: PlanStatus::Proposed,
+            status_history: Vec::new(),
+        }
+    }
+
+    pub fn with_confidence(mut self, high: bool) -> Self {
+        self.confidence_high = high;
+        self
+    }
 
-        hasher.update(&self.id);
-        hasher.update(&self.issue);
-        hasher.update(format!("{:?}", self.risk));
-        hasher.update(self.confidence_high.to_string());
-        hasher.update(self.reversible.to_string());
-        hasher.update(self.requires_reboot.to_string());
+    pub fn with_reversible(mut self, reversible: bool) -> Self {
+        self.reversible = reversible;
+        self
+    }
 
+    pub fn with_reboot(mut self, requires_reboot: bool) -> Self {
+        self.requires_reboot = requires_reboot;
+        self
+    }
+
+    /// Append one explanation block
+    pub fn with_explain(mut self, level: u8, category: ExplainCategory, content: impl Into<String>) -> Self {
+        self.explain.push(ExplainBlock {
+            level,
+            category,
+            content: content.into(),
+        });
+        self
+    }
+
+    pub fn with_evidence(mut self, evidence: impl IntoIterator<Item = Evidence>) -> Self {
+        self.evidence.extend(evidence);
+        self
+    }
+
+    /// Add a state that must hold after the repair
+    pub fn with_verify(mut self, state: SystemState) -> Self {
+        self.verify.push(state);
+        self
+    }
+
+    /// `seal` as the last step of building
+    pub fn sealed(mut self) -> Self {
+        self.seal();
+        self
+    }
+
+    /// Stamp the current schema version and store the content hash.
+    /// A plan built without an ID gets its `stable_id` first.
+    pub fn seal(&mut self) {
+        if self.id.is_empty() {
+            self.id = self.stable_id();
+        }
+
+        self.schema_version = PLAN_SCHEMA_VERSION;
+        self.integrity_hash = self.hash_v4();
+    }
+
+    /// "<fingerprint>-<first 8 hex of the action digest>": the same issue
+    /// with the same actions always gets the same ID, so a rebuild can
+    /// replace the stored plan in place instead of piling up copies
+    pub fn stable_id(&self) -> String {
+        format!("{}-{}", self.fingerprint, &self.action_digest()[..8])
+    }
+
+    /// ID of the `generation`th plan for the same issue and actions: the
+    /// first keeps `stable_id`, a recurrence after the earlier plan was
+    /// finished gets ".2", ".3", … so the finished record is not replaced
+    pub fn recurrence_id(&self, generation: u32) -> String {
+        match generation {
+            0 | 1 => self.stable_id(),
+            n => format!("{}.{}", self.stable_id(), n),
+        }
+    }
+
+    /// Generation of `id` if it is `recurrence_id` of this plan
+    pub fn generation_of(&self, id: &str) -> Option<u32> {
+        let rest = id.strip_prefix(&self.stable_id())?;
+
+        match rest.strip_prefix('.') {
+            None if rest.is_empty() => Some(1),
+            Some(n) => n.parse().ok().filter(|&n| n > 1),
+            None => None,
+        }
+    }
+
+    /// Hash of what the plan would execute, ignoring its explanation
+    pub fn action_digest(&self) -> String {
+        let mut enc = CanonicalEncoder::new("voxlinux.repair-actions", 1);
+
+        enc.list(self.actions.len());
         for action in &self.actions {
-            hasher.update(action);
+            action.encode(&mut enc);
+        }
+
+        enc.finish()
+    }
+
+    /// First action that cannot be undone, if any
+    pub fn irreversible_action(&self) -> Option<&RepairAction> {
+        self.actions.iter().find(|a| a.undo() == Undo::Irreversible)
+    }
+
+    /// Why the plan must not run whatever the risk policy: it was
+    /// superseded, its status cannot be approved (expired, dismissed,
+    /// already run), or it claims to be reversible and is not. Checked by
+    /// voxlinuxd and by `intentctl --local` alike.
+    pub fn apply_blocker(&self) -> Option<String> {
+        if let Some(newer) = &self.superseded_by {
+            return Some(format!("superseded by {}", newer));
+        }
+
+        if self.status != PlanStatus::Approved && !self.status.can_become(PlanStatus::Approved) {
+            return Some(format!("plan is {}", self.status));
+        }
+
+        if let (true, Some(action)) = (self.reversible, self.irreversible_action()) {
+            return Some(format!("'{}' cannot be undone", action));
         }
 
+        None
+    }
+
+    /// Move to `to` if the lifecycle allows it and record who did it
+    pub fn transition(
+        &mut self,
+        to: PlanStatus,
+        by: &str,
+        reason: Option<String>,
+    ) -> Result<(), TransitionError> {
+        if !self.status.can_become(to) {
+            return Err(TransitionError { from: self.status, to });
+        }
+
+        self.status_history.push(StatusChange::now(self.status, to, by, reason));
+        self.status = to;
+        Ok(())
+    }
+
+    pub fn verify_integrity(&self) -> Result<(), IntegrityError> {
+        if self.compute_hash()? != self.integrity_hash {
+            return Err(IntegrityError::HashMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// v1 plans carry no evidence; any present is not covered by the hash
+    fn hash_v1(&self) -> String {
+        let mut enc = CanonicalEncoder::new("voxlinux.repair-plan", 1);
+        self.encode_v1_fields(&mut enc);
+        enc.finish()
+    }
+
+    fn hash_v2(&self) -> String {
+        let mut enc = CanonicalEncoder::new("voxlinux.repair-plan", 2);
+        self.encode_v1_fields(&mut enc);
+
+        enc.list(self.evidence.len());
+        for item in &self.evidence {
+            item.encode(&mut enc);
+        }
+
+        enc.finish()
+    }
+
+    fn hash_v3(&self) -> String {
+        let mut enc = CanonicalEncoder::new("voxlinux.repair-plan", 3);
+        self.encode_v1_fields(&mut enc);
+
+        enc.list(self.evidence.len());
+        for item in &self.evidence {
+            item.encode(&mut enc);
+        }
+
+        enc.str(&self.fingerprint);
+        enc.finish()
+    }
+
+    fn hash_v4(&self) -> String {
+        let mut enc = CanonicalEncoder::new("voxlinux.repair-plan", 4);
+        self.encode_v1_fields(&mut enc);
+
+        enc.list(self.evidence.len());
+        for item in &self.evidence {
+            item.encode(&mut enc);
+        }
+
+        enc.str(&self.fingerprint);
+
+        enc.list(self.verify.len());
+        for state in &self.verify {
+            state.encode(&mut enc);
+        }
+
+        enc.finish()
+    }
+
+    fn encode_v1_fields(&self, enc: &mut CanonicalEncoder) {
+        enc.str(&self.id);
+        enc.str(&self.issue);
+        enc.tag(self.risk.tag());
+        enc.bool(self.confidence_high);
+        enc.bool(self.reversible);
+        enc.bool(self.requires_reboot);
+
+        enc.list(self.actions.len());
+        for action in &self.actions {
+            action.encode(enc);
+        }
+
+        enc.list(self.explain.len());
         for block in &self.explain {
-            hasher.update(block.level.to_string());
-            hasher.update(format!("{:?}", block.category));
-            hasher.update(&block.content);
+            enc.u8(block.level);
+            enc.tag(block.category.tag());
+            enc.str(&block.content);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> RepairPlan {
+        RepairPlan::new(
+            "nginx.service failed",
+            "restart-nginx.service",
+            RiskLevel::Low,
+            vec![RepairAction::RestartUnit { unit: "nginx.service".into() }],
+        )
+        .with_confidence(true)
+        .with_verify(SystemState::ServiceActive("nginx.service".into()))
+    }
+
+    fn evidence() -> Evidence {
+        Evidence {
+            unit: "nginx.service".into(),
+            result: Some("exit-code".into()),
+            exit_code: Some("exited".into()),
+            exit_status: Some(1),
+            journal: vec!["bind() failed".into()],
+        }
+    }
+
+    /// Plan sealed with the hash of `version`
+    fn sealed_as(mut plan: RepairPlan, version: u32) -> RepairPlan {
+        plan.id = plan.stable_id();
+        plan.schema_version = version;
+        plan.integrity_hash = plan.compute_hash().unwrap();
+        plan
+    }
+
+    #[test]
+    fn sealed_plan_verifies() {
+        let plan = plan().sealed();
+
+        assert_eq!(plan.schema_version, PLAN_SCHEMA_VERSION);
+        assert_eq!(plan.id, plan.stable_id());
+        assert_eq!(plan.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn every_supported_version_verifies() {
+        for &version in SUPPORTED_SCHEMA_VERSIONS {
+            assert_eq!(sealed_as(plan(), version).verify_integrity(), Ok(()), "v{}", version);
+        }
+    }
+
+    #[test]
+    fn unsupported_version_is_rejected() {
+        for version in [0, PLAN_SCHEMA_VERSION + 1] {
+            let mut plan = plan().sealed();
+            plan.schema_version = version;
+
+            assert_eq!(plan.verify_integrity(), Err(IntegrityError::UnsupportedSchema(version)));
+        }
+    }
+
+    #[test]
+    fn versions_cover_their_fields() {
+        // Schema version that added the field, and an edit of it
+        type Edit = (u32, fn(&mut RepairPlan));
+
+        let changes: [Edit; 3] = [
+            (2, |p| p.evidence.push(evidence())),
+            (3, |p| p.fingerprint.push_str("-other")),
+            (4, |p| p.verify.clear()),
+        ];
+
+        for (since, change) in changes {
+            for &version in SUPPORTED_SCHEMA_VERSIONS {
+                let mut plan = sealed_as(plan(), version);
+                change(&mut plan);
+
+                assert_eq!(plan.verify_integrity().is_err(), version >= since, "v{} since v{}", version, since);
+            }
         }
+    }
+
+    #[test]
+    fn lifecycle_fields_are_not_hashed() {
+        let mut plan = plan().sealed();
+
+        plan.transition(PlanStatus::Approved, "test", None).unwrap();
+        plan.superseded_by = Some("other".into());
+
+        assert_eq!(plan.verify_integrity(), Ok(()));
+    }
+
+    #[test]
+    fn stable_id_follows_the_actions() {
+        let a = plan();
+        let b = plan().with_explain(1, ExplainCategory::WhatHappened, "more text");
+
+        let mut c = plan();
+        c.actions.push(RepairAction::ResetFailed { unit: "nginx.service".into() });
+
+        assert_eq!(a.stable_id(), b.stable_id());
+        assert_ne!(a.stable_id(), c.stable_id());
+        assert!(a.stable_id().starts_with("restart-nginx.service-"));
+    }
+
+    #[test]
+    fn recurrence_ids() {
+        let plan = plan();
+        let stable = plan.stable_id();
+
+        assert_eq!(plan.recurrence_id(1), stable);
+        assert_eq!(plan.recurrence_id(3), format!("{}.3", stable));
+
+        assert_eq!(plan.generation_of(&stable), Some(1));
+        assert_eq!(plan.generation_of(&plan.recurrence_id(3)), Some(3));
+        assert_eq!(plan.generation_of(&format!("{}.1", stable)), None);
+        assert_eq!(plan.generation_of(&format!("{}.x", stable)), None);
+        assert_eq!(plan.generation_of(&format!("{}0", stable)), None);
+        assert_eq!(plan.generation_of("restart-sshd.service-00000000"), None);
+    }
+
+    #[test]
+    fn apply_blockers() {
+        let plan = plan().sealed();
+        assert_eq!(plan.apply_blocker(), None);
+
+        let mut failed = plan.clone();
+        failed.status = PlanStatus::Failed;
+        assert_eq!(failed.apply_blocker(), None);
+
+        let mut superseded = plan.clone();
+        superseded.superseded_by = Some("newer".into());
+        assert_eq!(superseded.apply_blocker(), Some("superseded by newer".into()));
+
+        let mut running = plan.clone();
+        running.status = PlanStatus::Running;
+        assert_eq!(running.apply_blocker(), Some("plan is running".into()));
+
+        let mut claims_reversible = plan.clone();
+        claims_reversible.actions.push(RepairAction::RunPackageSync);
+        assert!(claims_reversible.apply_blocker().unwrap().contains("cannot be undone"));
+
+        claims_reversible.reversible = false;
+        assert_eq!(claims_reversible.apply_blocker(), None);
+    }
+
+    #[test]
+    fn transition_records_history() {
+        let mut plan = plan().sealed();
+
+        plan.transition(PlanStatus::Approved, "operator", None).unwrap();
+        assert_eq!(
+            plan.transition(PlanStatus::Succeeded, "voxlinuxd", None),
+            Err(TransitionError { from: PlanStatus::Approved, to: PlanStatus::Succeeded })
+        );
 
-        let result = hasher.finalize();
-        hex::encode(result)
+        assert_eq!(plan.status, PlanStatus::Approved);
+        assert_eq!(plan.status_history.len(), 1);
+        assert_eq!(plan.status_history[0].from, PlanStatus::Proposed);
+        assert_eq!(plan.status_history[0].by, "operator");
     }
 }
@@ -0,0 +1,140 @@
+use crate::paths;
+use crate::repair_plan::RepairPlan;
+use ed25519_dalek::{Signature, Signer, Verifier};
+use std::fs;
+
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Valid,
+    Missing,
+    Invalid,
+    NoTrustedKey,
+}
+
+impl SignatureStatus {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SignatureStatus::Valid => "valid",
+            SignatureStatus::Missing => "missing (unsigned plan)",
+            SignatureStatus::Invalid => "INVALID (plan was modified or signed by another key)",
+            SignatureStatus::NoTrustedKey => "unverifiable (no trusted public key)",
+        }
+    }
+}
+
+/// Seal the plan and store a signature over its content hash
+pub fn sign_plan(plan: &mut RepairPlan, key: &SigningKey) {
+    plan.seal();
+
+    let signature = key.sign(plan.integrity_hash.as_bytes());
+    plan.signature = hex::encode(signature.to_bytes());
+}
+
+/// Check the plan content and signature against a trusted key
+pub fn verify_plan(plan: &RepairPlan, key: &VerifyingKey) -> SignatureStatus {
+    if plan.signature.is_empty() {
+        return SignatureStatus::Missing;
+    }
+
+    if plan.verify_integrity().is_err() {
+        return SignatureStatus::Invalid;
+    }
+
+    let signature = match hex::decode(&plan.signature)
+    .ok()
+    .and_then(|bytes| Signature::from_slice(&bytes).ok())
+    {
+        Some(s) => s,
+        None => return SignatureStatus::Invalid,
+    };
+
+    match key.verify(plan.integrity_hash.as_bytes(), &signature) {
+        Ok(()) => SignatureStatus::Valid,
+        Err(_) => SignatureStatus::Invalid,
+    }
+}
+
+/// Load the trusted public key written by voxlinuxd
+pub fn load_trusted_key() -> Option<VerifyingKey> {
+    let data = fs::read_to_string(paths::public_key()).ok()?;
+    let bytes: [u8; 32] = hex::decode(data.trim()).ok()?.try_into().ok()?;
+
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+/// Verify a plan against the host public key (`paths::public_key()`)
+pub fn check_plan(plan: &RepairPlan) -> SignatureStatus {
+    match load_trusted_key() {
+        Some(key) => verify_plan(plan, &key),
+        None => SignatureStatus::NoTrustedKey,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repair_action::RepairAction;
+    use crate::repair_plan::RiskLevel;
+
+    fn plan() -> RepairPlan {
+        RepairPlan::new(
+            "nginx.service failed",
+            "restart-nginx.service",
+            RiskLevel::Low,
+            vec![RepairAction::RestartUnit { unit: "nginx.service".into() }],
+        )
+    }
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn signed_plan_verifies() {
+        let mut plan = plan();
+        sign_plan(&mut plan, &key(1));
+
+        assert!(!plan.id.is_empty());
+        assert_eq!(verify_plan(&plan, &key(1).verifying_key()), SignatureStatus::Valid);
+    }
+
+    #[test]
+    fn unsigned_plan_is_missing() {
+        let plan = plan().sealed();
+        assert_eq!(verify_plan(&plan, &key(1).verifying_key()), SignatureStatus::Missing);
+    }
+
+    #[test]
+    fn other_key_is_invalid() {
+        let mut plan = plan();
+        sign_plan(&mut plan, &key(1));
+
+        assert_eq!(verify_plan(&plan, &key(2).verifying_key()), SignatureStatus::Invalid);
+    }
+
+    #[test]
+    fn edited_plan_is_invalid() {
+        let mut plan = plan();
+        sign_plan(&mut plan, &key(1));
+
+        plan.actions = vec![RepairAction::DisableUnit { unit: "nginx.service".into() }];
+        assert_eq!(verify_plan(&plan, &key(1).verifying_key()), SignatureStatus::Invalid);
+
+        // Re-hashing the edit does not help without the key
+        plan.seal();
+        assert_eq!(verify_plan(&plan, &key(1).verifying_key()), SignatureStatus::Invalid);
+    }
+
+    #[test]
+    fn lifecycle_fields_are_not_signed() {
+        let mut plan = plan();
+        sign_plan(&mut plan, &key(1));
+
+        plan.transition(crate::plan_status::PlanStatus::Approved, "test", None).unwrap();
+        plan.superseded_by = Some("other".into());
+
+        assert_eq!(verify_plan(&plan, &key(1).verifying_key()), SignatureStatus::Valid);
+    }
+}
@@ -0,0 +1,54 @@
+// system_state.rs
+//
+// Conditions the daemon can check on the running system. Probes report
+// the ones that are broken; repair plans list the ones that must hold
+// after the repair (their verification targets).
+
+use crate::canonical::CanonicalEncoder;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SystemState {
+    PackageConsistent,
+    ServiceActive(String),
+    NetworkReachable,
+    FilesystemWritable(String),
+
+    /// Mount point below the critical disk usage thresholds
+    DiskSpaceAvailable(String),
+}
+
+impl SystemState {
+    /// Canonical encoding used by the plan hash (schema v4)
+    pub(crate) fn encode(&self, enc: &mut CanonicalEncoder) {
+        match self {
+            SystemState::PackageConsistent => enc.tag("package_consistent"),
+            SystemState::ServiceActive(unit) => {
+                enc.tag("service_active");
+                enc.str(unit);
+            }
+            SystemState::NetworkReachable => enc.tag("network_reachable"),
+            SystemState::FilesystemWritable(path) => {
+                enc.tag("filesystem_writable");
+                enc.str(path);
+            }
+            SystemState::DiskSpaceAvailable(path) => {
+                enc.tag("disk_space_available");
+                enc.str(path);
+            }
+        }
+    }
+}
+
+impl fmt::Display for SystemState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemState::PackageConsistent => write!(f, "package database consistent"),
+            SystemState::ServiceActive(unit) => write!(f, "{} active", unit),
+            SystemState::NetworkReachable => write!(f, "network reachable"),
+            SystemState::FilesystemWritable(path) => write!(f, "{} writable", path),
+            SystemState::DiskSpaceAvailable(path) => write!(f, "{} below critical usage", path),
+        }
+    }
+}
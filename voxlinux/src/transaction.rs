@@ -0,0 +1,554 @@
+// transaction.rs
+//
+// Runs the actions of a plan as one unit. Before the first action, every
+// file the plan may touch (`RepairAction::backup_paths`) is copied to
+// `paths::backup_dir()/<plan id>/`, next to a journal (transaction.json)
+// that lists the copies and each action as it completes. If an action
+// fails, the files it may have written are put back and the completed
+// ones are undone in reverse order. The journal of a finished plan is
+// kept so the whole plan can be rolled back later; a plan whose journal
+// still lists applied actions cannot run again until it is rolled back.
+// Units the plan enables or disables have their prior state recorded, so
+// an undo only flips back what the plan actually changed.
+
+use crate::command_runner::CommandRunner;
+use crate::paths;
+use crate::repair_action::{RepairAction, Undo};
+use crate::repair_plan::RepairPlan;
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const JOURNAL: &str = "transaction.json";
+
+/// One file as it was before the plan ran
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileBackup {
+    pub path: String,
+
+    /// Name of the copy in the backup directory; None if the file did not exist
+    pub copy: Option<String>,
+}
+
+/// `systemctl is-enabled` of a unit before the plan ran
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UnitEnablement {
+    pub unit: String,
+
+    /// "enabled", "disabled", "static", …; empty if systemd did not know the unit
+    pub state: String,
+}
+
+impl UnitEnablement {
+    pub fn enabled(&self) -> bool {
+        self.state.starts_with("enabled")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Transaction {
+    pub plan_id: String,
+    pub started: u64,
+    pub backups: Vec<FileBackup>,
+
+    /// Units the plan enables or disables; absent in older journals
+    #[serde(default)]
+    pub enablement: Vec<UnitEnablement>,
+
+    /// Actions that ran successfully and have not been undone, in order
+    pub completed: Vec<RepairAction>,
+}
+
+/// Why `run` stopped
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// None if the backup failed and nothing ran
+    pub action: Option<RepairAction>,
+    pub error: String,
+
+    /// Completed actions that were undone
+    pub undone: usize,
+
+    /// Undo steps that failed or were impossible
+    pub undo_errors: Vec<String>,
+}
+
+impl Failure {
+    /// "failed; rolled back 2 action(s)" or what could not be undone
+    pub fn describe(&self) -> String {
+        let what = match &self.action {
+            Some(action) => format!("{}: {}", action, self.error),
+            None => self.error.clone(),
+        };
+
+        if self.undo_errors.is_empty() {
+            format!("{} (rolled back {} action(s))", what, self.undone)
+        } else {
+            format!("{} (rollback incomplete: {})", what, self.undo_errors.join("; "))
+        }
+    }
+}
+
+fn plan_dir(plan_id: &str) -> PathBuf {
+    paths::backup_dir().join(plan_id)
+}
+
+fn now_ts() -> u64 {
+    SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
+}
+
+impl Transaction {
+    /// Back up every file the plan may touch, note the enablement of the
+    /// units it enables or disables and write the journal. A previous
+    /// transaction of the same plan is replaced, unless it still has
+    /// applied actions: those would lose their only record.
+    pub fn begin(plan: &RepairPlan, runner: &dyn CommandRunner) -> Result<Self, String> {
+        if let Some(previous) = Self::load(&plan.id)? {
+            if !previous.completed.is_empty() {
+                return Err(format!(
+                    "plan {} still has {} applied action(s) from an earlier run; roll it back first",
+                    plan.id,
+                    previous.completed.len()
+                ));
+            }
+        }
+
+        let dir = plan_dir(&plan.id);
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)
+        .map_err(|e| format!("cannot create backup dir {}: {}", dir.display(), e))?;
+
+        let mut tx = Transaction {
+            plan_id: plan.id.clone(),
+            started: now_ts(),
+            backups: Vec::new(),
+            enablement: Vec::new(),
+            completed: Vec::new(),
+        };
+
+        for path in plan.actions.iter().flat_map(|a| a.backup_paths()) {
+            if tx.backups.iter().any(|b| b.path == path) {
+                continue;
+            }
+
+            let copy = match fs::symlink_metadata(&path) {
+                Ok(meta) if meta.is_file() => {
+                    let name = tx.backups.len().to_string();
+
+                    fs::copy(&path, dir.join(&name))
+                    .map_err(|e| format!("cannot back up {}: {}", path, e))?;
+
+                    Some(name)
+                }
+                Ok(_) => return Err(format!("cannot back up {}: not a regular file", path)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => return Err(format!("cannot back up {}: {}", path, e)),
+            };
+
+            tx.backups.push(FileBackup { path, copy });
+        }
+
+        for action in &plan.actions {
+            let unit = match action {
+                RepairAction::EnableUnit { unit } | RepairAction::DisableUnit { unit } => unit,
+                _ => continue,
+            };
+
+            if tx.enablement.iter().any(|e| &e.unit == unit) {
+                continue;
+            }
+
+            // Exits non-zero for anything but enabled; the state is on stdout
+            let output = runner
+            .run("systemctl", &["is-enabled", "--", unit])
+            .map_err(|e| format!("cannot read the enablement of {}: {}", unit, e))?;
+
+            tx.enablement.push(UnitEnablement {
+                unit: unit.clone(),
+                state: output.stdout.trim().to_string(),
+            });
+        }
+
+        tx.save()?;
+        Ok(tx)
+    }
+
+    /// Journal of the last run of `plan_id`, if it is still kept
+    pub fn load(plan_id: &str) -> Result<Option<Self>, String> {
+        let path = plan_dir(plan_id).join(JOURNAL);
+
+        match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|e| format!("unreadable journal {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("cannot read {}: {}", path.display(), e)),
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let dir = plan_dir(&self.plan_id);
+        let tmp_path = dir.join(format!("{}.tmp", JOURNAL));
+
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+
+        fs::File::create(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, dir.join(JOURNAL)))
+        .map_err(|e| format!("cannot write transaction journal for {}: {}", self.plan_id, e))
+    }
+
+    /// Note that `action` ran successfully
+    pub fn record(&mut self, action: &RepairAction) -> Result<(), String> {
+        self.completed.push(action.clone());
+        self.save()
+    }
+
+    /// First completed action that cannot be undone, if any
+    pub fn irreversible(&self) -> Option<&RepairAction> {
+        self.completed.iter().find(|a| a.undo() == Undo::Irreversible)
+    }
+
+    /// Undo the completed actions, newest first. Steps that fail are
+    /// reported and skipped; the rest still run. The skipped actions stay
+    /// in `completed` and in the journal, so the rollback can be retried.
+    pub fn rollback(&mut self, runner: &dyn CommandRunner) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut kept = Vec::new();
+
+        while let Some(action) = self.completed.pop() {
+            if let Err(e) = self.undo(&action, runner) {
+                errors.push(format!("undo of '{}': {}", action, e));
+                kept.push(action);
+            }
+        }
+
+        kept.reverse();
+        self.completed = kept;
+
+        if let Err(e) = self.save() {
+            errors.push(e);
+        }
+
+        errors
+    }
+
+    /// Put back the files of an action that failed part way. Its reverse
+    /// command is not run: the action's own command failed, so there is
+    /// nothing for it to reverse.
+    fn revert_failed(&self, action: &RepairAction) -> Result<(), String> {
+        if let Undo::RestoreFiles { .. } = action.undo() {
+            for path in action.backup_paths() {
+                self.restore(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// False for an enable or disable that found the unit already in the
+    /// state it asked for, so its undo must not flip it. Journals without
+    /// a recorded state are undone by the plain reverse action.
+    fn changed_enablement(&self, action: &RepairAction) -> bool {
+        let before = action
+        .unit()
+        .and_then(|unit| self.enablement.iter().find(|e| e.unit == unit));
+
+        match (action, before) {
+            (RepairAction::DisableUnit { .. }, Some(before)) => before.enabled(),
+            (RepairAction::EnableUnit { .. }, Some(before)) => before.state == "disabled",
+            _ => true,
+        }
+    }
+
+    fn undo(&self, action: &RepairAction, runner: &dyn CommandRunner) -> Result<(), String> {
+        match action.undo() {
+            Undo::Nothing => Ok(()),
+            Undo::Run(_) if !self.changed_enablement(action) => Ok(()),
+            Undo::Run(reverse) => reverse.execute(runner),
+            Undo::RestoreFiles { then } => {
+                for path in action.backup_paths() {
+                    self.restore(&path)?;
+                }
+
+                match then {
+                    Some(reverse) => reverse.execute(runner),
+                    None => Ok(()),
+                }
+            }
+            Undo::Irreversible => Err("cannot be undone".into()),
+        }
+    }
+
+    /// Put `path` back as it was when the transaction began
+    fn restore(&self, path: &str) -> Result<(), String> {
+        let backup = self
+        .backups
+        .iter()
+        .find(|b| b.path == path)
+        .ok_or_else(|| format!("no backup of {}", path))?;
+
+        match &backup.copy {
+            Some(copy) => {
+                if let Some(parent) = Path::new(path).parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+
+                fs::copy(plan_dir(&self.plan_id).join(copy), path)
+                .map(|_| ())
+                .map_err(|e| format!("cannot restore {}: {}", path, e))
+            }
+            None => match fs::remove_file(path) {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(format!("cannot remove {}: {}", path, e)),
+            },
+        }
+    }
+
+    /// Drop the journal and the backups
+    pub fn discard(self) {
+        let _ = fs::remove_dir_all(plan_dir(&self.plan_id));
+    }
+}
+
+/// Run every action of `plan` inside a transaction, calling `observe`
+/// after each one. Stops at the first failure, puts back the files the
+/// failed action may have written and undoes the actions before it.
+pub fn run(
+    plan: &RepairPlan,
+    runner: &dyn CommandRunner,
+    observe: &mut dyn FnMut(&RepairAction, &Result<(), String>),
+) -> Result<Transaction, Failure> {
+    let mut tx = Transaction::begin(plan, runner).map_err(|error| Failure {
+        action: None,
+        error,
+        undone: 0,
+        undo_errors: Vec::new(),
+    })?;
+
+    for action in &plan.actions {
+        let result = action.execute(runner);
+        observe(action, &result);
+
+        let mut undo_errors = Vec::new();
+
+        // An unrecorded action would be skipped by a later rollback; one
+        // whose journal write failed is still in `completed` and undone
+        let result = match result {
+            Ok(()) => tx.record(action),
+            Err(error) => {
+                if let Err(e) = tx.revert_failed(action) {
+                    undo_errors.push(format!("revert of '{}': {}", action, e));
+                }
+                Err(error)
+            }
+        };
+
+        if let Err(error) = result {
+            let ran = tx.completed.len();
+            undo_errors.extend(tx.rollback(runner));
+            let undone = ran - tx.completed.len();
+
+            return Err(Failure {
+                action: Some(action.clone()),
+                error,
+                undone,
+                undo_errors,
+            });
+        }
+    }
+
+    Ok(tx)
+}
+
+/// Remove journals and backups of transactions started more than
+/// `retain_secs` ago, except those of the plans in `keep`; returns
+/// their plan IDs
+pub fn gc(retain_secs: u64, keep: &[String]) -> Vec<String> {
+    let cutoff = now_ts().saturating_sub(retain_secs);
+    let mut removed = Vec::new();
+
+    let Ok(entries) = fs::read_dir(paths::backup_dir()) else {
+        return removed;
+    };
+
+    for entry in entries.flatten() {
+        let Some(plan_id) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+
+        if keep.contains(&plan_id) {
+            continue;
+        }
+
+        let started = Transaction::load(&plan_id)
+        .ok()
+        .flatten()
+        .map_or(0, |tx| tx.started);
+
+        if started <= cutoff && fs::remove_dir_all(entry.path()).is_ok() {
+            removed.push(plan_id);
+        }
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::{CommandOutput, FakeRunner};
+    use crate::repair_plan::RiskLevel;
+
+    fn plan(name: &str, actions: Vec<RepairAction>) -> RepairPlan {
+        paths::test_root();
+        RepairPlan::new(name, name, RiskLevel::Low, actions).sealed()
+    }
+
+    /// A call as `FakeRunner::calls` records it
+    fn systemctl(verb: &str, unit: &str) -> (String, Vec<String>) {
+        ("systemctl".into(), vec![verb.into(), "--".into(), unit.into()])
+    }
+
+    #[test]
+    fn completed_run_keeps_the_journal() {
+        let plan = plan("tx-ok", vec![
+            RepairAction::ResetFailed { unit: "a.service".into() },
+            RepairAction::RestartUnit { unit: "a.service".into() },
+        ]);
+
+        let runner = FakeRunner::new()
+        .respond("systemctl", &["reset-failed", "--", "a.service"], CommandOutput::ok(""))
+        .respond("systemctl", &["restart", "--", "a.service"], CommandOutput::ok(""));
+
+        let mut observed = Vec::new();
+        let tx = run(&plan, &runner, &mut |action, result| observed.push((action.clone(), result.is_ok())))
+        .unwrap();
+
+        assert_eq!(observed.len(), 2);
+        assert_eq!(tx.completed, plan.actions);
+        assert_eq!(Transaction::load(&plan.id).unwrap(), Some(tx.clone()));
+
+        tx.discard();
+        assert_eq!(Transaction::load(&plan.id).unwrap(), None);
+    }
+
+    #[test]
+    fn failed_action_undoes_the_earlier_ones() {
+        let plan = plan("tx-fail", vec![
+            RepairAction::DisableUnit { unit: "b.service".into() },
+            RepairAction::RestartUnit { unit: "c.service".into() },
+        ]);
+
+        let runner = FakeRunner::new()
+        .respond("systemctl", &["is-enabled", "--", "b.service"], CommandOutput::ok("enabled\n"))
+        .respond("systemctl", &["disable", "--", "b.service"], CommandOutput::ok(""))
+        .respond("systemctl", &["restart", "--", "c.service"], CommandOutput::failed(1))
+        .respond("systemctl", &["enable", "--", "b.service"], CommandOutput::ok(""));
+
+        let failure = run(&plan, &runner, &mut |_, _| {}).unwrap_err();
+
+        assert_eq!(failure.action, Some(plan.actions[1].clone()));
+        assert_eq!(failure.undone, 1);
+        assert!(failure.undo_errors.is_empty());
+
+        assert_eq!(runner.calls().last(), Some(&systemctl("enable", "b.service")));
+        assert!(Transaction::load(&plan.id).unwrap().unwrap().completed.is_empty());
+    }
+
+    #[test]
+    fn failed_undo_stays_in_the_journal() {
+        let plan = plan("tx-partial", vec![
+            RepairAction::DisableUnit { unit: "d.service".into() },
+            RepairAction::DisableUnit { unit: "e.service".into() },
+        ]);
+
+        let runner = FakeRunner::new()
+        .respond("systemctl", &["is-enabled", "--", "d.service"], CommandOutput::ok("enabled\n"))
+        .respond("systemctl", &["is-enabled", "--", "e.service"], CommandOutput::ok("enabled\n"))
+        .respond("systemctl", &["disable", "--", "d.service"], CommandOutput::ok(""))
+        .respond("systemctl", &["disable", "--", "e.service"], CommandOutput::ok(""))
+        .respond("systemctl", &["enable", "--", "d.service"], CommandOutput::failed(1))
+        .respond("systemctl", &["enable", "--", "e.service"], CommandOutput::ok(""));
+
+        let mut tx = run(&plan, &runner, &mut |_, _| {}).unwrap();
+
+        let errors = tx.rollback(&runner);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("d.service"));
+
+        // Only the step that failed is left to retry
+        assert_eq!(tx.completed, [plan.actions[0].clone()]);
+        assert_eq!(Transaction::load(&plan.id).unwrap().unwrap().completed, tx.completed);
+    }
+
+    #[test]
+    fn undo_keeps_the_enablement_it_found() {
+        let plan = plan("tx-enablement", vec![
+            RepairAction::DisableUnit { unit: "f.service".into() },
+            RepairAction::EnableUnit { unit: "g.service".into() },
+        ]);
+
+        // Both units already were as the plan wants them
+        let runner = FakeRunner::new()
+        .respond("systemctl", &["is-enabled", "--", "f.service"], CommandOutput { code: Some(1), ..CommandOutput::ok("disabled\n") })
+        .respond("systemctl", &["is-enabled", "--", "g.service"], CommandOutput::ok("enabled\n"))
+        .respond("systemctl", &["disable", "--", "f.service"], CommandOutput::ok(""))
+        .respond("systemctl", &["enable", "--", "g.service"], CommandOutput::ok(""));
+
+        let mut tx = run(&plan, &runner, &mut |_, _| {}).unwrap();
+        let before = runner.calls().len();
+
+        assert!(tx.rollback(&runner).is_empty());
+        assert_eq!(runner.calls().len(), before, "nothing to flip back");
+        assert!(tx.completed.is_empty());
+    }
+
+    #[test]
+    fn begin_refuses_a_journal_with_applied_actions() {
+        let plan = plan("tx-begin", vec![RepairAction::DisableUnit { unit: "h.service".into() }]);
+
+        let runner = FakeRunner::new()
+        .respond("systemctl", &["is-enabled", "--", "h.service"], CommandOutput::ok("enabled\n"))
+        .respond("systemctl", &["disable", "--", "h.service"], CommandOutput::ok(""));
+
+        let tx = run(&plan, &runner, &mut |_, _| {}).unwrap();
+
+        let again = run(&plan, &runner, &mut |_, _| {}).unwrap_err();
+        assert_eq!(again.action, None);
+        assert!(again.error.contains("roll it back first"));
+        assert_eq!(Transaction::load(&plan.id).unwrap(), Some(tx));
+    }
+
+    #[test]
+    fn failed_action_gets_its_files_back() {
+        let dir = paths::test_root().join("tx-files");
+        fs::create_dir_all(&dir).unwrap();
+
+        let file = dir.join("stale.lock");
+        fs::write(&file, "pid 42").unwrap();
+        let path = file.to_string_lossy().into_owned();
+
+        let plan = plan("tx-files", vec![RepairAction::RemoveFile { path: path.clone() }]);
+        let runner = FakeRunner::new().respond("rm", &["-f", "--", &path], CommandOutput::failed(1));
+
+        // rm got part way before failing
+        let failure = run(&plan, &runner, &mut |_, _| {
+            let _ = fs::remove_file(&file);
+        })
+        .unwrap_err();
+
+        assert!(failure.undo_errors.is_empty());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "pid 42");
+    }
+}
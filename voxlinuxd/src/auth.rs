@@ -0,0 +1,193 @@
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+
+use crate::config;
+use voxlinux::protocol::Request;
+use voxlinux::repair_plan::RiskLevel;
+
+/// Identity of the process on the other end of the socket (SO_PEERCRED)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: i32,
+}
+
+impl std::fmt::Display for PeerCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "uid={} gid={} pid={}", self.uid, self.gid, self.pid)
+    }
+}
+
+impl PeerCredentials {
+    /// "alice (uid=1000 gid=1000 pid=4242)", used to attribute changes
+    pub fn describe(&self) -> String {
+        match lookup_user(self.uid) {
+            Some((name, _)) => format!("{} ({})", name, self),
+            None => self.to_string(),
+        }
+    }
+}
+
+pub fn peer_credentials(stream: &UnixStream) -> io::Result<PeerCredentials> {
+    let mut cred = libc::ucred { pid: 0, uid: 0, gid: 0 };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+    let rc = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(PeerCredentials {
+        uid: cred.uid,
+        gid: cred.gid,
+        pid: cred.pid,
+    })
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthPolicy {
+    pub admin_group: String,
+
+    /// Highest plan risk a non-root admin may apply; root may apply any
+    pub admin_max_risk: RiskLevel,
+}
+
+impl Default for AuthPolicy {
+    fn default() -> Self {
+        let auth = &config::get().auth;
+
+        AuthPolicy {
+            admin_group: auth.admin_group.clone(),
+            admin_max_risk: auth.admin_max_risk.clone(),
+        }
+    }
+}
+
+impl AuthPolicy {
+    /// Decide whether `creds` may issue `request`.
+    /// `plan_risk` is the risk of the targeted plan for Apply and Rollback requests.
+    pub fn authorize(
+        &self,
+        creds: &PeerCredentials,
+        request: &Request,
+        plan_risk: Option<&RiskLevel>,
+    ) -> Result<(), String> {
+        match request {
+            // Read-only calls are open to every local user
+            Request::ListPlans
+            | Request::GetPlan { .. }
+            | Request::DryRun { .. }
+            | Request::GetStatus
+            | Request::GetHistory { .. }
+            | Request::Subscribe => Ok(()),
+
+            Request::SetHealingLevel { .. } | Request::Pause { .. } | Request::Resume => {
+                if self.is_admin(creds) {
+                    Ok(())
+                } else {
+                    Err(format!("changing the healing level requires root or group '{}'", self.admin_group))
+                }
+            }
+
+            Request::Dismiss { .. } => {
+                if self.is_admin(creds) {
+                    Ok(())
+                } else {
+                    Err(format!("dismissing plans requires root or group '{}'", self.admin_group))
+                }
+            }
+
+            Request::Apply { .. } | Request::Rollback { .. } => {
+                if creds.uid == 0 {
+                    return Ok(());
+                }
+
+                if !self.is_admin(creds) {
+                    return Err(format!("applying plans requires root or group '{}'", self.admin_group));
+                }
+
+                match plan_risk {
+                    Some(risk) if *risk <= self.admin_max_risk => Ok(()),
+                    Some(risk) => Err(format!("{:?}-risk plans can only be applied by root", risk)),
+                    None => Err("unknown plan risk".into()),
+                }
+            }
+        }
+    }
+
+    fn is_admin(&self, creds: &PeerCredentials) -> bool {
+        creds.uid == 0 || in_group(creds, &self.admin_group)
+    }
+}
+
+/// Primary gid of the peer, the user's primary group, or supplementary membership
+fn in_group(creds: &PeerCredentials, group: &str) -> bool {
+    let Some((gid, members)) = lookup_group(group) else {
+        return false;
+    };
+
+    if creds.gid == gid {
+        return true;
+    }
+
+    match lookup_user(creds.uid) {
+        Some((name, primary_gid)) => primary_gid == gid || members.contains(&name),
+        None => false,
+    }
+}
+
+fn lookup_group(name: &str) -> Option<(u32, Vec<String>)> {
+    let cname = CString::new(name).ok()?;
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+
+    let rc = unsafe {
+        libc::getgrnam_r(cname.as_ptr(), &mut grp, buf.as_mut_ptr(), buf.len(), &mut result)
+    };
+
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+
+    let mut members = Vec::new();
+    let mut member = grp.gr_mem;
+
+    unsafe {
+        while !member.is_null() && !(*member).is_null() {
+            members.push(CStr::from_ptr(*member).to_string_lossy().into_owned());
+            member = member.add(1);
+        }
+    }
+
+    Some((grp.gr_gid, members))
+}
+
+fn lookup_user(uid: u32) -> Option<(String, u32)> {
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+
+    let rc = unsafe {
+        libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result)
+    };
+
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+
+    let name = unsafe { CStr::from_ptr(pwd.pw_name) }.to_string_lossy().into_owned();
+    Some((name, pwd.pw_gid))
+}
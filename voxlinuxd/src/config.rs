@@ -0,0 +1,668 @@
+// config.rs
+//
+// Daemon policy and tunables.
+//
+// Read once at startup from /etc/voxlinux/config.toml followed by every
+// *.toml fragment in /etc/voxlinux/config.d/ (in file-name order), both
+// under the root prefix (see `voxlinux::paths`). Later
+// files override earlier ones key by key; arrays are replaced, not appended.
+// Every file is optional: with no config at all the built-in defaults apply.
+//
+//     [daemon]
+//     scan_interval_secs = 300
+//     healing_level = "AssistedRepair"
+//
+//     [policy]
+//     ignore_patterns = ["getty@", "cups.service"]
+//     allow_list = ["NetworkManager.service"]
+//
+//     [units]
+//     core = ["dbus.service", "systemd-journald.service"]
+//
+//     [backoff]
+//     max_retries = 3
+//     base_delay_secs = 10
+//
+//     [auth]
+//     admin_group = "voxlinux-admins"
+//     admin_max_risk = "Medium"
+//
+//     [systemd]
+//     bus_address = "unix:path=/run/user/1000/bus"   # default: system bus
+//     job_timeout_secs = 90
+//
+//     [evidence]
+//     journal_lines = 20
+//
+//     [predictive]
+//     watched = ["NetworkManager.service", "sshd.service"]
+//     window_secs = 3600
+//     score_threshold = 30
+//
+//     [disk]
+//     paths = ["/", "/var", "/home", "/boot"]
+//     warn_percent = 85
+//     critical_percent = 95
+//     inode_warn_percent = 85
+//     inode_critical_percent = 95
+//     package_cache_keep = 3
+//     journal_max_mb = 500
+//     coredump_max_age_days = 7
+//
+//     [pressure]
+//     some_warn_percent = 20.0
+//     full_critical_percent = 10.0
+//     swap_warn_percent = 80
+//     swap_critical_percent = 95
+//
+//     [network]
+//     check_gateway = true
+//     dns_names = ["router.lan"]                       # default: none
+//     http_url = "http://192.168.1.10/health"         # default: none
+//     upstream_hosts = ["1.1.1.1"]                     # default: none
+//     networkmanager = true
+//     timeout_secs = 2
+//
+//     [memory]
+//     oom_limit_percent = 50
+//     high_ratio_percent = 90
+//
+//     [plans]
+//     retention_secs = 86400
+//
+//     [verify]
+//     stability_window_secs = 30
+//     poll_interval_secs = 5
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+use voxlinux::healing_level::HealingLevel;
+use voxlinux::paths;
+use voxlinux::repair_plan::RiskLevel;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    pub daemon: DaemonConfig,
+    pub policy: PolicyConfig,
+    pub units: UnitsConfig,
+    pub backoff: BackoffConfig,
+    pub auth: AuthConfig,
+    pub systemd: SystemdConfig,
+    pub evidence: EvidenceConfig,
+    pub predictive: PredictiveConfig,
+    pub disk: DiskConfig,
+    pub pressure: PressureConfig,
+    pub memory: MemoryConfig,
+    pub network: NetworkConfig,
+    pub plans: PlansConfig,
+    pub verify: VerifyConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct DaemonConfig {
+    /// Seconds between two full reconciliation scans; unit failures are
+    /// picked up immediately from systemd signals in between
+    pub scan_interval_secs: u64,
+
+    /// Healing level used until one is set at runtime (which then persists)
+    pub healing_level: HealingLevel,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct PolicyConfig {
+    /// Units (exact name or prefix) whose failures are only informational
+    pub ignore_patterns: Vec<String>,
+
+    /// Units whose failures keep their detected severity
+    pub allow_list: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct UnitsConfig {
+    /// Core units: a failure marks systemd as broken, and they are never
+    /// restarted or turned into repair plans automatically
+    pub core: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct BackoffConfig {
+    /// Retry attempts per escalation level
+    pub max_retries: u8,
+
+    /// First retry delay; doubles on every further attempt
+    pub base_delay_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct AuthConfig {
+    /// Group whose members may change daemon state
+    pub admin_group: String,
+
+    /// Highest plan risk a non-root admin may apply
+    pub admin_max_risk: RiskLevel,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct SystemdConfig {
+    /// D-Bus address of systemd; unset means the system bus
+    pub bus_address: Option<String>,
+
+    /// How long to wait for a restart job to finish
+    pub job_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct EvidenceConfig {
+    /// Journal lines of a failed unit attached to its repair plan
+    pub journal_lines: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct PredictiveConfig {
+    /// Services whose automatic restarts are tracked for drift
+    pub watched: Vec<String>,
+
+    /// Sliding window the restart trend is scored over
+    pub window_secs: u64,
+
+    /// Drift score (10 per restart inside the window) that triggers a preventive plan
+    pub score_threshold: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct DiskConfig {
+    /// Mount points checked; paths on the same filesystem are reported once
+    pub paths: Vec<String>,
+
+    /// Space usage (percent) reported as degraded / broken
+    pub warn_percent: u8,
+    pub critical_percent: u8,
+
+    /// Inode usage (percent) reported as degraded / broken
+    pub inode_warn_percent: u8,
+    pub inode_critical_percent: u8,
+
+    /// Cached versions of each package kept by the cache trim plan
+    pub package_cache_keep: u32,
+
+    /// Journal size the vacuum plan shrinks to
+    pub journal_max_mb: u32,
+
+    /// Core dumps older than this are pruned
+    pub coredump_max_age_days: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct PressureConfig {
+    /// PSI "some" share (percent) over 1 and 5 minutes reported as degraded
+    pub some_warn_percent: f64,
+
+    /// PSI "full" share (percent) over 1 and 5 minutes reported as broken
+    pub full_critical_percent: f64,
+
+    /// Swap usage (percent) reported as degraded / broken
+    pub swap_warn_percent: u8,
+    pub swap_critical_percent: u8,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct MemoryConfig {
+    /// MemoryMax proposed for an OOM-killed unit without a limit, as a share of RAM
+    pub oom_limit_percent: u8,
+
+    /// MemoryHigh as a share of the proposed MemoryMax, so the unit is
+    /// throttled and reclaimed before it is killed
+    pub high_ratio_percent: u8,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct NetworkConfig {
+    /// Ping the default gateway (some routers drop ICMP)
+    pub check_gateway: bool,
+
+    /// Names that must resolve; empty only checks that a nameserver is set
+    pub dns_names: Vec<String>,
+
+    /// Endpoint that must answer an HTTP GET (a local service or a captive-portal check)
+    pub http_url: Option<String>,
+
+    /// Hosts beyond the gateway that must answer ping; empty skips the check
+    pub upstream_hosts: Vec<String>,
+
+    /// Ask NetworkManager for its connectivity state
+    pub networkmanager: bool,
+
+    /// Per-check timeout
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct PlansConfig {
+    /// How long finished plans (succeeded, expired, …) stay listed
+    pub retention_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct VerifyConfig {
+    /// How long a repaired system must stay healthy to count as fixed
+    pub stability_window_secs: u64,
+
+    /// Seconds between checks of the verification targets
+    pub poll_interval_secs: u64,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        DaemonConfig {
+            scan_interval_secs: 300,
+            healing_level: HealingLevel::AssistedRepair,
+        }
+    }
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        PolicyConfig {
+            ignore_patterns: strings(&[
+                "getty@",
+                "serial-getty@",
+                "bluetooth.service",
+                "cups.service",
+                "modemmanager.service",
+                "avahi-daemon.service",
+                "power-profiles-daemon.service",
+            ]),
+            allow_list: strings(&[
+                "NetworkManager.service",
+                "pipewire.service",
+                "wireplumber.service",
+                "sddm.service",
+                "systemd-logind.service",
+                "dbus.service",
+            ]),
+        }
+    }
+}
+
+impl Default for UnitsConfig {
+    fn default() -> Self {
+        UnitsConfig {
+            core: strings(&[
+                "basic.target",
+                "sysinit.target",
+                "multi-user.target",
+                "graphical.target",
+                "systemd-journald.service",
+                "systemd-logind.service",
+                "systemd-udevd.service",
+                "dbus.service",
+            ]),
+        }
+    }
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        BackoffConfig {
+            max_retries: 3,
+            base_delay_secs: 10,
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            admin_group: "voxlinux-admins".into(),
+            admin_max_risk: RiskLevel::Medium,
+        }
+    }
+}
+
+impl Default for SystemdConfig {
+    fn default() -> Self {
+        SystemdConfig {
+            bus_address: None,
+            job_timeout_secs: 90,
+        }
+    }
+}
+
+impl Default for EvidenceConfig {
+    fn default() -> Self {
+        EvidenceConfig { journal_lines: 20 }
+    }
+}
+
+impl Default for PredictiveConfig {
+    fn default() -> Self {
+        PredictiveConfig {
+            watched: strings(&["NetworkManager.service", "sshd.service"]),
+            window_secs: 3600,
+            score_threshold: 30,
+        }
+    }
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        DiskConfig {
+            paths: strings(&["/", "/var", "/home", "/boot"]),
+            warn_percent: 85,
+            critical_percent: 95,
+            inode_warn_percent: 85,
+            inode_critical_percent: 95,
+            package_cache_keep: 3,
+            journal_max_mb: 500,
+            coredump_max_age_days: 7,
+        }
+    }
+}
+
+impl Default for PressureConfig {
+    fn default() -> Self {
+        PressureConfig {
+            some_warn_percent: 20.0,
+            full_critical_percent: 10.0,
+            swap_warn_percent: 80,
+            swap_critical_percent: 95,
+        }
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig {
+            oom_limit_percent: 50,
+            high_ratio_percent: 90,
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        NetworkConfig {
+            check_gateway: true,
+            dns_names: Vec::new(),
+            http_url: None,
+            upstream_hosts: Vec::new(),
+            networkmanager: true,
+            timeout_secs: 2,
+        }
+    }
+}
+
+impl Default for PlansConfig {
+    fn default() -> Self {
+        PlansConfig { retention_secs: 86400 }
+    }
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        VerifyConfig {
+            stability_window_secs: 30,
+            poll_interval_secs: 5,
+        }
+    }
+}
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+impl UnitsConfig {
+    pub fn is_core(&self, unit: &str) -> bool {
+        self.core.iter().any(|u| u == unit)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: PathBuf, error: std::io::Error },
+    Parse { path: PathBuf, error: String },
+    Schema { path: PathBuf, error: String },
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, error } => {
+                write!(f, "{}: cannot read: {}", path.display(), error)
+            }
+            ConfigError::Parse { path, error } => {
+                write!(f, "{}: invalid TOML: {}", path.display(), error)
+            }
+            ConfigError::Schema { path, error } => {
+                write!(f, "{}: {}", path.display(), error)
+            }
+            ConfigError::Invalid(e) => write!(f, "invalid configuration: {}", e),
+        }
+    }
+}
+
+/// Load the configuration and make it available through `get()`.
+/// Must be called before anything reads the config.
+pub fn init() -> Result<&'static Config, ConfigError> {
+    let config = load(&paths::config_file(), &paths::config_dir())?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// Active configuration (defaults if `init()` was never called)
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Merge the main file and its drop-ins, then check the result
+pub fn load(main: &Path, dropin_dir: &Path) -> Result<Config, ConfigError> {
+    let mut files = Vec::new();
+
+    if main.exists() {
+        files.push(main.to_path_buf());
+    }
+
+    if let Ok(entries) = fs::read_dir(dropin_dir) {
+        let mut fragments: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|e| e == "toml").unwrap_or(false))
+        .collect();
+
+        fragments.sort();
+        files.extend(fragments);
+    }
+
+    let mut merged = toml::Value::Table(toml::map::Map::new());
+
+    for path in files {
+        let contents = fs::read_to_string(&path).map_err(|error| ConfigError::Io {
+            path: path.clone(),
+            error,
+        })?;
+
+        let fragment: toml::Value = toml::from_str(&contents).map_err(|e| ConfigError::Parse {
+            path: path.clone(),
+            error: e.to_string(),
+        })?;
+
+        // Check each file on its own so schema errors name the file at fault
+        fragment
+        .clone()
+        .try_into::<Config>()
+        .map_err(|e| ConfigError::Schema {
+            path: path.clone(),
+            error: e.to_string(),
+        })?;
+
+        println!("[CONFIG] Loaded {}", path.display());
+        merge(&mut merged, fragment);
+    }
+
+    let config: Config = merged
+    .try_into()
+    .map_err(|e| ConfigError::Invalid(e.to_string()))?;
+
+    config.validate()?;
+    Ok(config)
+}
+
+/// Overlay `fragment` onto `base`: tables merge recursively, anything else is replaced
+fn merge(base: &mut toml::Value, fragment: toml::Value) {
+    match (base, fragment) {
+        (toml::Value::Table(base), toml::Value::Table(fragment)) => {
+            for (key, value) in fragment {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, fragment) => *base = fragment,
+    }
+}
+
+impl Config {
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.daemon.scan_interval_secs == 0 {
+            return Err(ConfigError::Invalid("daemon.scan_interval_secs must be at least 1".into()));
+        }
+
+        if self.backoff.max_retries == 0 {
+            return Err(ConfigError::Invalid("backoff.max_retries must be at least 1".into()));
+        }
+
+        // Delay doubles per retry; keep the shift in range
+        if self.backoff.max_retries > 16 {
+            return Err(ConfigError::Invalid("backoff.max_retries must be at most 16".into()));
+        }
+
+        if self.systemd.job_timeout_secs == 0 {
+            return Err(ConfigError::Invalid("systemd.job_timeout_secs must be at least 1".into()));
+        }
+
+        // Evidence is hashed, signed and sent over IPC; keep plans small
+        if self.evidence.journal_lines > 500 {
+            return Err(ConfigError::Invalid("evidence.journal_lines must be at most 500".into()));
+        }
+
+        if self.predictive.window_secs == 0 {
+            return Err(ConfigError::Invalid("predictive.window_secs must be at least 1".into()));
+        }
+
+        if self.predictive.score_threshold == 0 {
+            return Err(ConfigError::Invalid("predictive.score_threshold must be at least 1".into()));
+        }
+
+        let disk = &self.disk;
+
+        for (name, warn, critical) in [
+            ("", disk.warn_percent, disk.critical_percent),
+            ("inode_", disk.inode_warn_percent, disk.inode_critical_percent),
+        ] {
+            if warn == 0 || warn >= critical || critical > 100 {
+                return Err(ConfigError::Invalid(format!(
+                    "disk.{0}warn_percent must be at least 1 and below disk.{0}critical_percent, which must be at most 100",
+                    name
+                )));
+            }
+        }
+
+        let pressure = &self.pressure;
+
+        for (name, value) in [
+            ("some_warn_percent", pressure.some_warn_percent),
+            ("full_critical_percent", pressure.full_critical_percent),
+        ] {
+            if !(value > 0.0 && value <= 100.0) {
+                return Err(ConfigError::Invalid(format!("pressure.{} must be above 0 and at most 100", name)));
+            }
+        }
+
+        if pressure.swap_warn_percent == 0
+            || pressure.swap_warn_percent >= pressure.swap_critical_percent
+            || pressure.swap_critical_percent > 100
+        {
+            return Err(ConfigError::Invalid(
+                "pressure.swap_warn_percent must be at least 1 and below pressure.swap_critical_percent, which must be at most 100".into(),
+            ));
+        }
+
+        for (name, value) in [
+            ("oom_limit_percent", self.memory.oom_limit_percent),
+            ("high_ratio_percent", self.memory.high_ratio_percent),
+        ] {
+            if value == 0 || value > 100 {
+                return Err(ConfigError::Invalid(format!("memory.{} must be between 1 and 100", name)));
+            }
+        }
+
+        if self.network.timeout_secs == 0 {
+            return Err(ConfigError::Invalid("network.timeout_secs must be at least 1".into()));
+        }
+
+        if let Some(url) = &self.network.http_url
+        && !(url.starts_with("http://") || url.starts_with("https://"))
+        {
+            return Err(ConfigError::Invalid(format!("network.http_url {:?} must be an http(s) URL", url)));
+        }
+
+        if self.verify.poll_interval_secs == 0 {
+            return Err(ConfigError::Invalid("verify.poll_interval_secs must be at least 1".into()));
+        }
+
+        // A plan stays Running, and its issue unrepaired by the autonomous
+        // loop, until the window ends
+        if self.verify.stability_window_secs > 600 {
+            return Err(ConfigError::Invalid("verify.stability_window_secs must be at most 600".into()));
+        }
+
+        if disk.package_cache_keep == 0 {
+            return Err(ConfigError::Invalid("disk.package_cache_keep must be at least 1".into()));
+        }
+
+        if disk.journal_max_mb == 0 {
+            return Err(ConfigError::Invalid("disk.journal_max_mb must be at least 1".into()));
+        }
+
+        if let Some(bad) = disk.paths.iter().find(|p| !p.starts_with('/')) {
+            return Err(ConfigError::Invalid(format!("disk.paths entry {:?} is not absolute", bad)));
+        }
+
+        if self.auth.admin_group.is_empty() {
+            return Err(ConfigError::Invalid("auth.admin_group must not be empty".into()));
+        }
+
+        if let Some(empty) = self.policy.ignore_patterns.iter().find(|p| p.is_empty()) {
+            return Err(ConfigError::Invalid(format!(
+                "policy.ignore_patterns contains an empty pattern ({:?}) which would match every unit",
+                empty
+            )));
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,12 @@
 use crate::repair_plan::{RepairPlan, RiskLevel};
 use crate::core::confidence::Confidence;
+use voxlinux::command_runner::CommandRunner;
 
 pub fn attempt_autonomous_execution(
     plans: &[RepairPlan],
     confidence: Confidence,
     boot_safe: bool,
+    runner: &dyn CommandRunner,
 ) {
     if confidence != Confidence::High || !boot_safe {
         println!("[AUTO] Autonomous healing blocked by policy.");
@@ -14,7 +16,7 @@ pub fn attempt_autonomous_execution(
     for plan in plans {
         if should_execute(plan) {
             println!("[AUTO] Executing plan: {}", plan.id);
-            execute(plan);
+            execute(plan, runner);
         }
     }
 }
@@ -25,13 +27,12 @@ fn should_execute(plan: &RepairPlan) -> bool {
     && plan.risk != RiskLevel::High
 }
 
-fn execute(plan: &RepairPlan) {
+fn execute(plan: &RepairPlan, runner: &dyn CommandRunner) {
     for action in &plan.actions {
         println!("[AUTO] Running: {}", action);
 
-        let _ = std::process::Command::new("sh")
-        .arg("-c")
-        .arg(action)
-        .status();
+        if let Err(e) = action.execute(runner) {
+            println!("[AUTO] Action failed: {}", e);
+        }
     }
 }
@@ -1,5 +1,5 @@
 use super::detector::RawDetection;
-use crate::system_state::SystemState;
+use voxlinux::system_state::SystemState;
 use crate::core::opinion::Opinion;
 use crate::core::reporter::ObserverReport;
 
@@ -10,10 +10,16 @@ pub enum Severity {
     Critical,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum FailureClass {
-    RuntimeFailure,
-    CoreIntegrityFailure,
+pub use voxlinux::failure_class::FailureClass;
+
+/// What made the unit fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailureReason {
+    /// Whatever systemd reported (exit code, signal, timeout, …)
+    Reported,
+
+    /// The kernel OOM killer killed the unit's processes
+    OomKilled,
 }
 
 #[derive(Debug)]
@@ -21,21 +27,27 @@ pub struct Detection {
     pub unit: String,
     pub severity: Severity,
     pub reason: String,
+    pub cause: FailureReason,
 }
 
 pub fn classify(raw: RawDetection) -> Detection {
-    let severity = if raw.unit.contains("systemd-logind")
-        || raw.unit.contains("dbus")
-    {
-        Severity::Critical
-    } else {
-        Severity::Info
-    };
+    let critical = raw.unit.contains("systemd-logind")
+        || raw.unit.contains("dbus");
+
+    if let Some(oom) = raw.oom_kill {
+        return Detection {
+            unit: raw.unit,
+            severity: if critical { Severity::Critical } else { Severity::Warn },
+            reason: format!("killed by the OOM killer ({})", oom),
+            cause: FailureReason::OomKilled,
+        };
+    }
 
     Detection {
         unit: raw.unit,
-        severity,
+        severity: if critical { Severity::Critical } else { Severity::Info },
         reason: format!("systemd reported status: {}", raw.status),
+        cause: FailureReason::Reported,
     }
 }
 
@@ -0,0 +1,126 @@
+// core/cleanup.rs
+//
+// Space estimates for the disk cleanup plans. Each estimate mirrors what the
+// corresponding action will delete, so a plan can say up front how much it
+// is expected to free:
+//
+//   package cache  → paccache -rk N (all but the newest N versions per package)
+//   journal        → journalctl --vacuum-size (everything above the limit)
+//   core dumps     → files older than the age limit
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Whether `dir` exists and lives on the filesystem with st_dev `device`
+pub fn on_device(dir: &Path, device: u64) -> bool {
+    fs::metadata(dir).map(|m| m.dev() == device).unwrap_or(false)
+}
+
+/// Bytes held by cached packages beyond the newest `keep` versions of each
+pub fn package_cache_excess(dir: &Path, keep: u32) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    // (name, arch) → [(mtime, size incl. signature)]
+    let mut packages: HashMap<(String, String), Vec<(SystemTime, u64)>> = HashMap::new();
+
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+
+        let Some((name, arch)) = package_key(&file_name) else {
+            continue;
+        };
+
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+
+        let sig_size = fs::metadata(dir.join(format!("{}.sig", file_name)))
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+        packages
+        .entry((name, arch))
+        .or_default()
+        .push((meta.modified().unwrap_or(SystemTime::UNIX_EPOCH), meta.len() + sig_size));
+    }
+
+    packages
+    .into_values()
+    .map(|mut versions| {
+        versions.sort_by_key(|&(modified, _)| Reverse(modified));
+        versions.iter().skip(keep as usize).map(|(_, size)| size).sum::<u64>()
+    })
+    .sum()
+}
+
+/// "linux-6.9.1.arch1-1-x86_64.pkg.tar.zst" → ("linux", "x86_64")
+fn package_key(file_name: &str) -> Option<(String, String)> {
+    let (stem, ext) = file_name.split_once(".pkg.tar")?;
+
+    // Signatures are counted with their package; skip partial downloads
+    if ext.ends_with(".sig") || ext.ends_with(".part") {
+        return None;
+    }
+
+    let mut parts = stem.rsplitn(4, '-');
+    let arch = parts.next()?;
+    let _pkgrel = parts.next()?;
+    let _pkgver = parts.next()?;
+    let name = parts.next()?;
+
+    Some((name.to_string(), arch.to_string()))
+}
+
+/// Bytes of journal above `max_size_mb`
+pub fn journal_excess(dir: &Path, max_size_mb: u32) -> u64 {
+    dir_size(dir).saturating_sub(max_size_mb as u64 * 1024 * 1024)
+}
+
+/// Bytes of core dumps older than `older_than_days`
+pub fn coredump_excess(dir: &Path, older_than_days: u32) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    // find -mtime +N matches files at least N+1 whole days old
+    let min_age = Duration::from_secs((older_than_days as u64 + 1) * 86400);
+
+    entries
+    .flatten()
+    .filter_map(|e| e.metadata().ok())
+    .filter(|m| m.is_file())
+    .filter(|m| {
+        m.modified()
+        .ok()
+        .and_then(|t| t.elapsed().ok())
+        .map(|age| age >= min_age)
+        .unwrap_or(false)
+    })
+    .map(|m| m.len())
+    .sum()
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+
+    entries
+    .flatten()
+    .filter_map(|e| {
+        let meta = e.metadata().ok()?;
+
+        Some(if meta.is_dir() {
+            dir_size(&e.path())
+        } else {
+            meta.len()
+        })
+    })
+    .sum()
+}
@@ -1,6 +1,6 @@
 use crate::state::BootContext;
 use crate::core::confidence::Confidence;
-use crate::core::classifier::Detection;
+use crate::core::classifier::{Detection, FailureReason};
 
 use std::time::Instant;
 
@@ -22,6 +22,12 @@ impl DeferredHealQueue {
             return; // already queued
         }
 
+        // Restarting into the same memory limit just gets it killed again
+        if detection.cause == FailureReason::OomKilled {
+            println!("[DEFER] {} was OOM-killed → no blind restart", detection.unit);
+            return;
+        }
+
         self.actions.push(DeferredHealAction {
             unit: detection.unit.clone(),
                           reason: detection.reason.clone(),
@@ -1,6 +1,8 @@
-use std::process::Command;
 use std::fs;
 use crate::state::BootContext;
+use voxlinux::paths;
+use crate::probe;
+use crate::systemd_backend::{SystemdBackend, UnitEvent};
 
 
 
@@ -9,115 +11,97 @@ use crate::state::BootContext;
 pub struct RawDetection {
     pub unit: String,
     pub status: String,
+    /// Set when the kernel OOM killer is behind the failure
+    pub oom_kill: Option<String>,
 }
 
-pub fn detect_boot_context() -> BootContext {
+pub fn detect_boot_context(systemd: &dyn SystemdBackend) -> BootContext {
     // Check actual system running state
-    if let Ok(out) = Command::new("systemctl")
-        .arg("is-system-running")
-        .output()
-        {
-            let status = String::from_utf8_lossy(&out.stdout).trim().to_string();
-
-            match status.as_str() {
-                "running" => {
-                    // Now check target
-                    if let Ok(target_out) = Command::new("systemctl")
-                        .arg("get-default")
-                        .output()
-                        {
-                            let target =
-                            String::from_utf8_lossy(&target_out.stdout);
-
-                            if target.contains("graphical.target") {
-                                return BootContext::Graphical;
-                            }
-
-                            if target.contains("multi-user.target") {
-                                return BootContext::MultiUser;
-                            }
-                        }
-
+    if let Ok(state) = systemd.system_state() {
+        match state.as_str() {
+            "running" => {
+                // Now check target
+                if let Ok(target) = systemd.default_target() {
+                    if target == "graphical.target" {
+                        return BootContext::Graphical;
+                    }
+
+                    if target == "multi-user.target" {
                         return BootContext::MultiUser;
+                    }
                 }
 
-                "starting" => return BootContext::EarlyBoot,
-                "degraded" => return BootContext::MultiUser,
-                "maintenance" => return BootContext::Rescue,
-                _ => {}
+                return BootContext::MultiUser;
             }
+
+            "initializing" | "starting" => return BootContext::EarlyBoot,
+            "degraded" => return BootContext::MultiUser,
+            "maintenance" => return BootContext::Rescue,
+            _ => {}
         }
+    }
 
-        BootContext::Unknown
+    BootContext::Unknown
 }
 
 /* --- helpers --- */
 
 fn in_initramfs() -> bool {
-    fs::metadata("/run/initramfs").is_ok()
+    fs::metadata(paths::resolve("/run/initramfs")).is_ok()
 }
 
 fn pid1_is_systemd() -> bool {
-    fs::read_to_string("/proc/1/comm")
+    fs::read_to_string(paths::resolve("/proc/1/comm"))
     .map(|s| s.trim() == "systemd")
     .unwrap_or(false)
 }
 
-fn systemd_state() -> Option<String> {
-    let out = Command::new("systemctl")
-    .arg("is-system-running")
-    .output()
-    .ok()?;
-
-    if !out.status.success() {
-        return None;
-    }
-
-    Some(String::from_utf8_lossy(&out.stdout).trim().to_string())
+fn systemd_state(systemd: &dyn SystemdBackend) -> Option<String> {
+    systemd.system_state().ok()
 }
 
-fn rescue_target_active() -> bool {
-    Command::new("systemctl")
-    .args(["is-active", "rescue.target"])
-    .output()
-    .map(|o| o.status.success())
+fn rescue_target_active(systemd: &dyn SystemdBackend) -> bool {
+    systemd
+    .unit_status("rescue.target")
+    .map(|u| u.is_active())
     .unwrap_or(false)
 }
 
-fn graphical_target_active() -> bool {
-    Command::new("systemctl")
-    .args(["is-active", "graphical.target"])
-    .output()
-    .map(|o| o.status.success())
+fn graphical_target_active(systemd: &dyn SystemdBackend) -> bool {
+    systemd
+    .unit_status("graphical.target")
+    .map(|u| u.is_active())
     .unwrap_or(false)
 }
 
-pub fn scan() -> Vec<RawDetection> {
-    let output = Command::new("systemctl")
-    .args(["list-units", "--failed", "--no-legend"])
-    .output()
-    .expect("failed to run systemctl");
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    stdout
-    .lines()
-    .filter_map(|line| {
-        // Expected format:
-        // ● UNIT LOAD ACTIVE SUB DESCRIPTION
-        let mut parts = line.split_whitespace();
-
-        let marker = parts.next()?; // ●
-        let unit = parts.next()?;   // getty@tty1.service
-        let load = parts.next()?;   // loaded
-        let active = parts.next()?; // failed
-        let sub = parts.next()?;    // failed
-
-        Some(RawDetection {
-            unit: unit.to_string(),
-             status: format!("{}/{}/{}", load, active, sub),
+pub fn scan(systemd: &dyn SystemdBackend) -> Vec<RawDetection> {
+    match systemd.failed_units() {
+        Ok(units) => units
+        .into_iter()
+        .map(|u| RawDetection {
+            status: format!("{}/{}/{}", u.load_state, u.active_state, u.sub_state),
+            oom_kill: probe::oom_kill_cause(&u.name, systemd),
+            unit: u.name,
         })
-    })
-    .collect()
+        .collect(),
+
+        Err(e) => {
+            println!("[DETECT] Failed to list units: {}", e);
+            Vec::new()
+        }
+    }
 }
 
+/// Detection for a unit failure pushed by systemd, without a full scan
+pub fn from_event(event: &UnitEvent, systemd: &dyn SystemdBackend) -> RawDetection {
+    let status = match event {
+        UnitEvent::JobFailed { result, .. } => format!("job {}", result),
+        UnitEvent::EnteredFailed { .. } => "failed".to_string(),
+    };
+
+    RawDetection {
+        unit: event.unit().to_string(),
+        status,
+        oom_kill: probe::oom_kill_cause(event.unit(), systemd),
+    }
+}
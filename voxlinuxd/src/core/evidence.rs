@@ -0,0 +1,172 @@
+// core/evidence.rs
+//
+// Gathers what systemd and the journal recorded about a failed unit, so a
+// repair plan carries the facts it was built from: the unit's `Result=`,
+// how its main process exited, and the tail of its journal.
+
+use crate::config;
+use crate::mountinfo::MountInfo;
+use crate::systemd_backend::SystemdBackend;
+use voxlinux::command_runner::CommandRunner;
+use voxlinux::evidence::Evidence;
+
+pub fn collect(unit: &str, runner: &dyn CommandRunner, systemd: &dyn SystemdBackend) -> Evidence {
+    // Non-service units (mounts, sockets, …) have no main process
+    let exit = systemd.service_exit(unit).ok();
+
+    Evidence {
+        unit: unit.to_string(),
+        result: exit.as_ref().map(|e| e.result.clone()),
+        exit_code: exit.as_ref().and_then(|e| e.code.clone()),
+        exit_status: exit.as_ref().filter(|e| e.code.is_some()).map(|e| e.status),
+        journal: journal_tail(unit, runner),
+    }
+}
+
+/// Kernel errors of the current boot that concern `mount`'s device, or
+/// read-only remounts in general
+pub fn kernel_errors(mount: &MountInfo, runner: &dyn CommandRunner) -> Evidence {
+    let device = mount.source.rsplit('/').next().unwrap_or(&mount.source).to_string();
+    let lines = config::get().evidence.journal_lines;
+
+    let journal = match runner.run("journalctl", &["-k", "-b", "-p", "err", "--no-pager", "--quiet", "-o", "short-iso"]) {
+        Ok(output) if output.success() => {
+            let matching: Vec<String> = output
+            .stdout
+            .lines()
+            .filter(|l| l.contains(&device) || l.contains("read-only") || l.contains("I/O error"))
+            .map(|l| l.to_string())
+            .collect();
+
+            let skip = matching.len().saturating_sub(lines);
+            matching.into_iter().skip(skip).collect()
+        }
+
+        Ok(output) => {
+            println!("[EVIDENCE] journalctl -k exited with {:?}", output.code);
+            Vec::new()
+        }
+
+        Err(e) => {
+            println!("[EVIDENCE] journalctl -k failed: {}", e);
+            Vec::new()
+        }
+    };
+
+    Evidence {
+        unit: mount.unit_name(),
+        result: None,
+        exit_code: None,
+        exit_status: None,
+        journal,
+    }
+}
+
+fn journal_tail(unit: &str, runner: &dyn CommandRunner) -> Vec<String> {
+    let lines = config::get().evidence.journal_lines;
+
+    if lines == 0 {
+        return Vec::new();
+    }
+
+    let count = lines.to_string();
+
+    match runner.run(
+        "journalctl",
+        &["-u", unit, "-n", &count, "--no-pager", "--quiet", "-o", "short-iso"],
+    ) {
+        Ok(output) if output.success() => output
+        .stdout
+        .lines()
+        .map(|l| l.to_string())
+        .collect(),
+
+        Ok(output) => {
+            println!("[EVIDENCE] journalctl for {} exited with {:?}", unit, output.code);
+            Vec::new()
+        }
+
+        Err(e) => {
+            println!("[EVIDENCE] journalctl for {} failed: {}", unit, e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mountinfo;
+    use crate::systemd_backend::{FakeSystemd, ServiceExit};
+    use voxlinux::command_runner::{CommandOutput, FakeRunner};
+
+    const UNIT_JOURNAL: &[&str] = &[
+        "-u", "nginx.service", "-n", "20", "--no-pager", "--quiet", "-o", "short-iso",
+    ];
+
+    const KERNEL_JOURNAL: &[&str] = &[
+        "-k", "-b", "-p", "err", "--no-pager", "--quiet", "-o", "short-iso",
+    ];
+
+    #[test]
+    fn collect_combines_exit_and_journal() {
+        let systemd = FakeSystemd::default().exit("nginx.service", ServiceExit {
+            result: "exit-code".into(),
+            code: Some("exited".into()),
+            status: 1,
+        });
+
+        let runner = FakeRunner::new().respond("journalctl", UNIT_JOURNAL, CommandOutput::ok(
+            "2026-10-18T06:01:02+0000 host nginx[812]: [emerg] bind() to 0.0.0.0:80 failed (98: Address in use)\n\
+             2026-10-18T06:01:02+0000 host systemd[1]: nginx.service: Failed with result 'exit-code'.\n",
+        ));
+
+        let evidence = collect("nginx.service", &runner, &systemd);
+
+        assert_eq!(evidence.result.as_deref(), Some("exit-code"));
+        assert_eq!(evidence.exit_code.as_deref(), Some("exited"));
+        assert_eq!(evidence.exit_status, Some(1));
+        assert_eq!(evidence.journal.len(), 2);
+        assert!(evidence.journal[0].contains("bind()"));
+    }
+
+    #[test]
+    fn collect_without_service_or_journal() {
+        let runner = FakeRunner::new().respond("journalctl", UNIT_JOURNAL, CommandOutput::failed(1));
+
+        let evidence = collect("nginx.service", &runner, &FakeSystemd::default());
+
+        assert_eq!(evidence.result, None);
+        assert_eq!(evidence.exit_status, None);
+        assert!(evidence.journal.is_empty());
+    }
+
+    #[test]
+    fn kernel_errors_keep_lines_about_the_device() {
+        let mount = mountinfo::parse(
+            "36 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 ro,errors=remount-ro\n",
+        )
+        .remove(0);
+
+        let runner = FakeRunner::new().respond("journalctl", KERNEL_JOURNAL, CommandOutput::ok(
+            "2026-10-18T05:59:00+0000 host kernel: usb 1-2: device descriptor read/64, error -71\n\
+             2026-10-18T06:00:01+0000 host kernel: EXT4-fs error (device sda2): ext4_find_entry:1455: inode #2: comm ls: reading directory lblock 0\n\
+             2026-10-18T06:00:01+0000 host kernel: EXT4-fs (sda2): Remounting filesystem read-only\n\
+             2026-10-18T06:00:02+0000 host kernel: blk_update_request: I/O error, dev sdb, sector 2048\n",
+        ));
+
+        let evidence = kernel_errors(&mount, &runner);
+
+        assert_eq!(evidence.unit, "-.mount");
+        assert_eq!(evidence.journal.len(), 3);
+        assert!(evidence.journal.iter().all(|l| !l.contains("usb 1-2")));
+    }
+
+    #[test]
+    fn kernel_errors_survive_journalctl_failure() {
+        let mount = mountinfo::parse("36 1 8:2 / / ro - ext4 /dev/sda2 ro\n").remove(0);
+        let runner = FakeRunner::new();
+
+        assert!(kernel_errors(&mount, &runner).journal.is_empty());
+    }
+}
@@ -1,20 +1,9 @@
 use std::collections::HashSet;
-use std::process::Command;
 
+use crate::config;
 use crate::core::confidence::Confidence;
 use crate::state::BootContext;
-
-/// Units that must NEVER be restarted automatically
-const DENYLIST: &[&str] = &[
-    "basic.target",
-"sysinit.target",
-"multi-user.target",
-"graphical.target",
-"systemd-journald.service",
-"systemd-logind.service",
-"systemd-udevd.service",
-"dbus.service",
-];
+use crate::systemd_backend::backend;
 
 #[derive(Default)]
 pub struct HealingSession {
@@ -40,8 +29,9 @@ impl HealingSession {
             return Err("confidence not high".into());
         }
 
-        if DENYLIST.contains(&unit) {
-            return Err("unit is denylisted".into());
+        // Core units must NEVER be restarted automatically
+        if config::get().units.is_core(unit) {
+            return Err("unit is a protected core unit".into());
         }
 
         if self.attempted_units.contains(unit) {
@@ -53,15 +43,8 @@ impl HealingSession {
         // ─────────────────────────────
         self.attempted_units.insert(unit.to_string());
 
-        let status = Command::new("systemctl")
-        .args(["restart", unit])
-        .status()
-        .map_err(|e| format!("failed to invoke systemctl: {}", e))?;
-
-        if !status.success() {
-            return Err("systemctl restart failed".into());
-        }
-
-        Ok(())
+        backend()
+        .restart_unit(unit)
+        .map_err(|e| e.to_string())
     }
 }
@@ -12,3 +12,5 @@ pub mod deferred;
 pub mod healer;
 pub mod repair_builder;
 pub mod ai_advisor;
+pub mod evidence;
+pub mod cleanup;
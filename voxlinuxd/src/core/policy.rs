@@ -1,31 +1,15 @@
 use super::classifier::{Detection, Severity};
-
-const IGNORE_PATTERNS: &[&str] = &[
-    "getty@",
-    "serial-getty@",
-    "bluetooth.service",
-    "cups.service",
-    "modemmanager.service",
-    "avahi-daemon.service",
-    "power-profiles-daemon.service",
-];
-
-const ALLOW_LIST: &[&str] = &[
-    "NetworkManager.service",
-    "pipewire.service",
-    "wireplumber.service",
-    "sddm.service",
-    "systemd-logind.service",
-    "dbus.service",
-];
+use crate::config;
 
 fn matches_pattern(unit: &str, pattern: &str) -> bool {
     unit == pattern || unit.starts_with(pattern)
 }
 
 pub fn apply_policy(mut d: Detection) -> Detection {
+    let policy = &config::get().policy;
+
     // 1. Ignore list → force INFO
-    for pattern in IGNORE_PATTERNS {
+    for pattern in &policy.ignore_patterns {
         if matches_pattern(&d.unit, pattern) {
             d.severity = Severity::Info;
             d.reason.push_str(" (ignored by policy)");
@@ -34,7 +18,7 @@ pub fn apply_policy(mut d: Detection) -> Detection {
     }
 
     // 2. Allow list → allow severity
-    for allowed in ALLOW_LIST {
+    for allowed in &policy.allow_list {
         if d.unit == *allowed {
             return d;
         }
@@ -1,30 +1,580 @@
+use crate::config;
 use crate::core::reporter::ObserverReport;
 use crate::core::opinion::Opinion;
 use crate::core::confidence::Confidence;
+use crate::core::cleanup;
+use crate::probe::{format_bytes, DiskPressure, DiskUsage, UnitMemory};
+use crate::pressure::{self, PressureReport};
+use crate::mountinfo::MountInfo;
+use crate::network::{NetworkLayer, NetworkReport};
+use crate::systemd;
+use crate::predictive::DriftResult;
 use voxlinux::repair_plan::{RepairPlan, RiskLevel};
-use voxlinux::explain::{ExplainBlock, ExplainCategory};
-
-use std::time::{SystemTime, UNIX_EPOCH};
-
-const DENYLIST: &[&str] = &[
-    "basic.target",
-"sysinit.target",
-"multi-user.target",
-"graphical.target",
-"systemd-journald.service",
-"systemd-logind.service",
-"systemd-udevd.service",
-"dbus.service",
-"getty@tt1.service",
-];
-
-fn generate_plan_id(prefix: &str) -> String {
-    let ts = SystemTime::now()
-    .duration_since(UNIX_EPOCH)
-    .unwrap()
-    .as_secs();
-
-    format!("{}-{}", prefix, ts)
+use voxlinux::evidence::Evidence;
+use voxlinux::system_state::SystemState;
+use voxlinux::paths;
+use voxlinux::repair_action::RepairAction;
+use voxlinux::explain::ExplainCategory;
+
+/// Restart plan for one failed unit; core units never get one
+pub fn build_unit_plan(
+    unit: &str,
+    confidence_high: bool,
+    evidence: Option<&Evidence>,
+) -> Option<RepairPlan> {
+    if config::get().units.is_core(unit) {
+        return None;
+    }
+
+    let why_detected = match evidence {
+        Some(e) => format!("systemd lists the service among its failed units ({}).", e.summary()),
+        None => "systemd lists the service among its failed units.".into(),
+    };
+
+    let plan = RepairPlan::new(
+        format!("systemd unit '{}' failed", unit),
+        format!("restart-{}", unit),
+        RiskLevel::Low,
+        vec![
+            RepairAction::RestartUnit { unit: unit.to_string() },
+        ],
+    )
+    .with_confidence(confidence_high)
+    .with_explain(
+        1,
+        ExplainCategory::WhatHappened,
+        "Systemd reports that this service is currently in a failed state.",
+    )
+    .with_explain(2, ExplainCategory::WhyDetected, why_detected)
+    .with_explain(
+        3,
+        ExplainCategory::WhySafe,
+        "Restarting a failed service is generally safe when confidence is high and the system is stable.",
+    )
+    .with_explain(
+        4,
+        ExplainCategory::WhatWillExecute,
+        "VoxLinux will attempt to restart the service using systemctl restart.",
+    )
+    .with_evidence(evidence.cloned())
+    .with_verify(SystemState::ServiceActive(unit.to_string()))
+    .sealed();
+
+    Some(plan)
+}
+
+/// Plan for a failed unit: a memory limit when the OOM killer took it,
+/// a restart otherwise
+pub fn build_failed_unit_plan(report: &ObserverReport, unit: &str) -> Option<RepairPlan> {
+    let confidence_high = report.confidence == Confidence::High;
+    let evidence = report.evidence.get(unit);
+
+    if report.oom_killed(unit)
+    && let Some(plan) = build_memory_limit_plan(unit, report.memory.get(unit), confidence_high, evidence)
+    {
+        return Some(plan);
+    }
+
+    build_unit_plan(unit, confidence_high, evidence)
+}
+
+/// Runtime MemoryHigh/MemoryMax drop-in plus a restart for an OOM-killed unit.
+/// A unit that hit its own memory.max keeps that limit and gets MemoryHigh
+/// below it; a unit without a limit (killed by system-wide OOM) is capped
+/// at a share of RAM so it cannot take the machine down again.
+/// Confidence is high only when the report is and the cgroup's oom_kill
+/// counter confirms the kill; a bare "oom-kill" result stays low.
+pub fn build_memory_limit_plan(
+    unit: &str,
+    memory: Option<&UnitMemory>,
+    confidence_high: bool,
+    evidence: Option<&Evidence>,
+) -> Option<RepairPlan> {
+    if config::get().units.is_core(unit) {
+        return None;
+    }
+
+    let settings = &config::get().memory;
+    const MIB: u64 = 1024 * 1024;
+
+    let own_limit = memory.and_then(|m| m.max);
+
+    let memory_max = match own_limit {
+        Some(max) => max,
+        None => pressure::mem_total_bytes()? / 100 * settings.oom_limit_percent as u64,
+    };
+
+    let memory_max = (memory_max / MIB).max(1) * MIB;
+    let memory_high = ((memory_max / 100 * settings.high_ratio_percent as u64) / MIB).max(1) * MIB;
+
+    let why = match (own_limit, memory) {
+        (Some(max), Some(m)) => format!(
+            "The kernel OOM killer ended {} process(es) of the unit after it reached its memory.max of {}.",
+            m.oom_kill,
+            format_bytes(max)
+        ),
+        (None, Some(m)) => format!(
+            "The kernel OOM killer ended {} process(es) of the unit; it had no memory limit and was using {}.",
+            m.oom_kill,
+            format_bytes(m.current)
+        ),
+        _ => "systemd reports the service result as oom-kill.".to_string(),
+    };
+
+    let killed_by_cgroup = memory.map(|m| m.oom_kill > 0).unwrap_or(false);
+
+    let plan = RepairPlan::new(
+        format!("systemd unit '{}' was killed by the OOM killer", unit),
+        format!("memlimit-{}", unit),
+        RiskLevel::Low,
+        vec![
+            RepairAction::SetMemoryLimit {
+                unit: unit.to_string(),
+                memory_high,
+                memory_max,
+            },
+            RepairAction::RestartUnit { unit: unit.to_string() },
+        ],
+    )
+    .with_confidence(confidence_high && killed_by_cgroup)
+    .with_explain(
+        1,
+        ExplainCategory::WhatHappened,
+        "The service ran out of memory and was killed by the kernel, not by a bug in its own exit path.",
+    )
+    .with_explain(2, ExplainCategory::WhyDetected, why)
+    .with_explain(
+        3,
+        ExplainCategory::WhySafe,
+        format!(
+            "Restarting alone would run into the same kill. The limits are set with --runtime, so they disappear on reboot or with `systemctl revert {}`.",
+            unit
+        ),
+    )
+    .with_explain(
+        4,
+        ExplainCategory::WhatWillExecute,
+        format!(
+            "Set MemoryHigh={} (throttling starts) and MemoryMax={} (hard cap) for the unit, then restart it.",
+            format_bytes(memory_high),
+            format_bytes(memory_max)
+        ),
+    )
+    .with_evidence(evidence.cloned())
+    .with_verify(SystemState::ServiceActive(unit.to_string()))
+    .sealed();
+
+    Some(plan)
+}
+
+/// Preventive plan for a service that keeps being restarted automatically.
+/// Core units never get one.
+pub fn build_preventive_plan(
+    drift: &DriftResult,
+    confidence_high: bool,
+    evidence: Option<&Evidence>,
+) -> Option<RepairPlan> {
+    let unit = &drift.service;
+
+    if config::get().units.is_core(unit) {
+        return None;
+    }
+
+    let predictive = &config::get().predictive;
+
+    let plan = RepairPlan::new(
+        format!("'{}' is restarting repeatedly", unit),
+        format!("drift-{}", unit),
+        RiskLevel::Low,
+        vec![
+            RepairAction::RestartUnit { unit: unit.to_string() },
+        ],
+    )
+    .with_confidence(confidence_high)
+    .with_explain(
+        1,
+        ExplainCategory::WhatHappened,
+        format!(
+            "systemd restarted this service {} time(s) in the last {} minute(s). It is still running, but the trend suggests it will fail.",
+            drift.restarts,
+            drift.observed_secs.div_ceil(60).max(1)
+        ),
+    )
+    .with_explain(
+        2,
+        ExplainCategory::WhyDetected,
+        format!(
+            "NRestarts grew by {} inside the {}s window: drift score {} (10 per restart) reached the threshold of {}.",
+            drift.restarts,
+            predictive.window_secs,
+            drift.score,
+            predictive.score_threshold
+        ),
+    )
+    .with_explain(
+        3,
+        ExplainCategory::WhySafe,
+        "A controlled restart resets the service before systemd's start limit stops it for good; the service is already restarting on its own.",
+    )
+    .with_explain(
+        4,
+        ExplainCategory::WhatWillExecute,
+        "VoxLinux will restart the service once; the journal should be checked for the underlying cause.",
+    )
+    .with_explain(
+        4,
+        ExplainCategory::Preconditions,
+        format!("Inspect journalctl -u {} for the cause of the restarts; a restart does not remove it.", unit),
+    )
+    .with_evidence(evidence.cloned())
+    .with_verify(SystemState::ServiceActive(unit.to_string()))
+    .sealed();
+
+    Some(plan)
+}
+
+/// Cleanup plans for a filling filesystem: one per cleanup that would free
+/// space on it, each stating the expected gain
+pub fn build_cleanup_plans(disk: &DiskUsage) -> Vec<RepairPlan> {
+    let settings = &config::get().disk;
+    let mut plans = Vec::new();
+
+    let cache_dir = paths::pacman_cache_dir();
+    if cleanup::on_device(&cache_dir, disk.device) {
+        let freed = cleanup::package_cache_excess(&cache_dir, settings.package_cache_keep);
+
+        if freed > 0 {
+            plans.push(cleanup_plan(
+                disk,
+                "cleanup-pkgcache",
+                "package cache",
+                freed,
+                RepairAction::TrimPackageCache {
+                    dir: cache_dir.to_string_lossy().into_owned(),
+                    keep: settings.package_cache_keep,
+                },
+                format!(
+                    "Cached packages older than the newest {} version(s) of each package will be removed. Downgrading to a removed version will need a download.",
+                    settings.package_cache_keep
+                ),
+            ));
+        }
+    }
+
+    let journal_dir = paths::journal_dir();
+    if cleanup::on_device(&journal_dir, disk.device) {
+        let freed = cleanup::journal_excess(&journal_dir, settings.journal_max_mb);
+
+        if freed > 0 {
+            plans.push(cleanup_plan(
+                disk,
+                "cleanup-journal",
+                "journal",
+                freed,
+                RepairAction::VacuumJournal {
+                    dir: journal_dir.to_string_lossy().into_owned(),
+                    max_size_mb: settings.journal_max_mb,
+                },
+                format!(
+                    "The oldest archived journal files will be deleted until the journal is at most {} MiB. Active journal files are never removed, so the gain can be smaller.",
+                    settings.journal_max_mb
+                ),
+            ));
+        }
+    }
+
+    let coredump_dir = paths::coredump_dir();
+    if cleanup::on_device(&coredump_dir, disk.device) {
+        let freed = cleanup::coredump_excess(&coredump_dir, settings.coredump_max_age_days);
+
+        if freed > 0 {
+            plans.push(cleanup_plan(
+                disk,
+                "cleanup-coredumps",
+                "core dumps",
+                freed,
+                RepairAction::PruneCoredumps {
+                    dir: coredump_dir.to_string_lossy().into_owned(),
+                    older_than_days: settings.coredump_max_age_days,
+                },
+                format!(
+                    "Core dumps older than {} day(s) will be deleted; they can no longer be inspected with coredumpctl afterwards.",
+                    settings.coredump_max_age_days
+                ),
+            ));
+        }
+    }
+
+    plans
+}
+
+fn cleanup_plan(
+    disk: &DiskUsage,
+    prefix: &str,
+    what: &str,
+    freed: u64,
+    action: RepairAction,
+    consequences: String,
+) -> RepairPlan {
+    RepairPlan::new(
+        format!(
+            "{} is {}% full: trimming the {} frees ~{}",
+            disk.mount,
+            disk.used_percent(),
+            what,
+            format_bytes(freed)
+        ),
+        prefix,
+        RiskLevel::Medium,
+        vec![action],
+    )
+    .with_confidence(true)
+    .with_reversible(false)
+    .with_explain(
+        1,
+        ExplainCategory::WhatHappened,
+        format!("The filesystem holding {} is running out of space or inodes.", disk.mount),
+    )
+    .with_explain(2, ExplainCategory::WhyDetected, format!("statvfs reports {}.", disk.describe()))
+    .with_explain(3, ExplainCategory::RiskAnalysis, consequences)
+    .with_explain(
+        4,
+        ExplainCategory::WhatWillExecute,
+        format!(
+            "Expected to free about {} of {} on {}.",
+            format_bytes(freed),
+            what,
+            disk.mount
+        ),
+    )
+    .with_verify(SystemState::DiskSpaceAvailable(disk.mount.clone()))
+    .sealed()
+}
+
+/// Manual plan naming the services that hold the most memory
+pub fn build_memory_pressure_plan(report: &PressureReport) -> Option<RepairPlan> {
+    let top = pressure::top_memory_units(5);
+
+    if top.is_empty() {
+        return None;
+    }
+
+    let listing: Vec<String> = top
+    .iter()
+    .map(|(unit, bytes)| format!("{} ({})", unit, format_bytes(*bytes)))
+    .collect();
+
+    let mut stall = Vec::new();
+
+    if let Some(memory) = report.memory {
+        stall.push(format!(
+            "memory PSI some avg60={:.1}% avg300={:.1}%",
+            memory.some.avg60, memory.some.avg300
+        ));
+    }
+
+    if let Some(swap) = report.swap.filter(|s| s.total_bytes > 0) {
+        stall.push(format!("swap {}% used", swap.used_percent()));
+    }
+
+    // The actions decide the plan ID, so they leave out the live sizes
+    // and the ranking: the same services keep the same plan while their
+    // usage moves
+    let mut units: Vec<&str> = top.iter().map(|(unit, _)| unit.as_str()).collect();
+    units.sort_unstable();
+
+    let mut actions: Vec<RepairAction> = units
+    .into_iter()
+    .map(|unit| RepairAction::Manual {
+        instruction: format!("Check whether {} needs the memory it uses", unit),
+    })
+    .collect();
+
+    actions.push(RepairAction::Manual {
+        instruction: "Watch live usage with systemd-cgtop -m".into(),
+    });
+
+    let plan = RepairPlan::new(
+        format!("memory pressure; largest services: {}", listing.join(", ")),
+        "memory-pressure",
+        RiskLevel::Medium,
+        actions,
+    )
+    .with_explain(
+        1,
+        ExplainCategory::WhatHappened,
+        "Tasks are repeatedly waiting for memory; the system is swapping or reclaiming instead of working.",
+    )
+    .with_explain(
+        2,
+        ExplainCategory::WhyDetected,
+        format!("The kernel reports sustained pressure: {}.", stall.join(", ")),
+    )
+    .with_explain(
+        3,
+        ExplainCategory::RiskAnalysis,
+        format!(
+            "These services hold the most memory and are the first candidates to restart or limit: {}.",
+            listing.join(", ")
+        ),
+    )
+    .with_explain(
+        4,
+        ExplainCategory::WhatWillExecute,
+        "Nothing is executed automatically; this plan only identifies where the memory is.",
+    )
+    .sealed();
+
+    Some(plan)
+}
+
+/// Manual plan for a filesystem the kernel (or someone) remounted read-only.
+/// Nothing is remounted automatically: the usual cause is an I/O or
+/// consistency error, and writing again before fsck can make it worse.
+pub fn build_read_only_plan(mount: &MountInfo, evidence: Option<&Evidence>) -> RepairPlan {
+    let kernel_lines = evidence.map(|e| e.journal.len()).unwrap_or(0);
+    let is_root = mount.mount_point == "/";
+
+    let why_detected = if mount.super_options.iter().any(|o| o == "ro") {
+        format!(
+            "/proc/self/mountinfo lists the {} superblock of {} as read-only, which is what the kernel does after an error (errors=remount-ro); /etc/fstab does not ask for it.",
+            mount.fs_type, mount.source
+        )
+    } else {
+        format!(
+            "/proc/self/mountinfo lists the mount as read-only ({}) although /etc/fstab does not ask for it.",
+            mount.mount_options.join(",")
+        )
+    };
+
+    let cause = if kernel_lines > 0 {
+        format!("{} kernel error line(s) from this boot are attached as evidence (intentctl repair explain --level 2).", kernel_lines)
+    } else {
+        "No matching kernel errors were found in this boot's journal; the remount may have been manual.".to_string()
+    };
+
+    RepairPlan::new(
+        format!("{} ({}) is mounted read-only unexpectedly", mount.mount_point, mount.source),
+        format!("readonly-{}", mount.unit_name().trim_end_matches(".mount")),
+        RiskLevel::High,
+        vec![
+            RepairAction::Manual {
+                instruction: format!("Read the kernel errors for {}: journalctl -k -b -p err", mount.source),
+            },
+            RepairAction::Manual {
+                instruction: format!(
+                    "Check the disk (smartctl -a) and run fsck on {} while it is unmounted{}",
+                    mount.source,
+                    if is_root { ", from a rescue system" } else { "" }
+                ),
+            },
+            RepairAction::Manual {
+                instruction: format!("Once repaired: mount -o remount,rw {}", mount.mount_point),
+            },
+        ],
+    )
+    .with_reversible(false)
+    .with_reboot(is_root)
+    .with_explain(
+        1,
+        ExplainCategory::WhatHappened,
+        format!("{} can no longer be written to; programs that save data there will fail.", mount.mount_point),
+    )
+    .with_explain(2, ExplainCategory::WhyDetected, why_detected)
+    .with_explain(2, ExplainCategory::RiskAnalysis, cause)
+    .with_explain(
+        3,
+        ExplainCategory::WhyBlocked,
+        "Remounting read-write before the filesystem is checked can turn a detected error into data loss, so VoxLinux does not do it automatically.",
+    )
+    .with_evidence(evidence.cloned())
+    .with_verify(SystemState::FilesystemWritable(mount.mount_point.clone()))
+    .sealed()
+}
+
+/// Plan for the lowest failing network layer; higher layers depend on it
+pub fn build_network_plan(report: &NetworkReport) -> Option<RepairPlan> {
+    let layer = report.failed_layer()?;
+    let failures = report.failures(layer);
+
+    let nm = "NetworkManager.service";
+    let resolved = "systemd-resolved.service";
+
+    let (actions, why_safe): (Vec<RepairAction>, String) = match layer {
+        NetworkLayer::Link => (
+            vec![RepairAction::Manual {
+                instruction: "Check the cable, Wi-Fi or VPN connection (ip link)".into(),
+            }],
+            "A missing link is usually physical; nothing is changed automatically.".into(),
+        ),
+
+        NetworkLayer::Route if systemd::is_active(nm) => (
+            vec![RepairAction::RestartUnit { unit: nm.into() }],
+            "Restarting NetworkManager renews DHCP leases and re-adds the default route; connections come back within seconds.".into(),
+        ),
+
+        NetworkLayer::Route => (
+            vec![RepairAction::Manual {
+                instruction: "Renew the DHCP lease or check the static gateway (ip route)".into(),
+            }],
+            "No network manager is running that VoxLinux could restart.".into(),
+        ),
+
+        NetworkLayer::Dns if systemd::is_active(resolved) => (
+            vec![RepairAction::RestartUnit { unit: resolved.into() }],
+            "Restarting systemd-resolved flushes its caches and re-reads the link DNS servers.".into(),
+        ),
+
+        NetworkLayer::Dns => (
+            vec![RepairAction::Manual {
+                instruction: "Check the nameservers in /etc/resolv.conf".into(),
+            }],
+            "The resolver configuration is not managed by a service VoxLinux can restart.".into(),
+        ),
+
+        NetworkLayer::Upstream => {
+            let instruction = if report.nm_connectivity.as_deref() == Some("portal") {
+                "Log in to the captive portal in a browser"
+            } else {
+                "The local network works; check the router or the internet provider"
+            };
+
+            (
+                vec![RepairAction::Manual { instruction: instruction.into() }],
+                "The fault is beyond this machine; nothing local is changed.".into(),
+            )
+        }
+    };
+
+    // Never Low: the autonomous loop would run it, and its quarantine
+    // disables the first unit of a plan that keeps failing, which must not
+    // be NetworkManager or the resolver
+    let plan = RepairPlan::new(
+        format!("network {} layer failed: {}", layer, failures),
+        "network",
+        RiskLevel::Medium,
+        actions,
+    )
+    .with_explain(
+        1,
+        ExplainCategory::WhatHappened,
+        format!("Network connectivity fails at the {} layer.", layer),
+    )
+    .with_explain(
+        2,
+        ExplainCategory::WhyDetected,
+        report
+        .checks
+        .iter()
+        .map(|c| format!("[{}] {} {}", c.layer, if c.ok { "ok" } else { "FAILED" }, c.detail))
+        .collect::<Vec<_>>()
+        .join("\n"),
+    )
+    .with_explain(3, ExplainCategory::WhySafe, why_safe)
+    .with_verify(SystemState::NetworkReachable)
+    .sealed();
+
+    Some(plan)
 }
 
 pub fn build_repair_plans(
@@ -38,43 +588,42 @@ pub fn build_repair_plans(
     // 1️⃣ PACMAN LOCK
     // ─────────────────────────────
     if report.pacman.locked && report.pacman.no_active_process {
-        let mut plan = RepairPlan {
-            id: generate_plan_id("pacman-lock"),
-            issue: "pacman database is locked".into(),
-            risk: RiskLevel::Medium,
-            confidence_high: report.confidence == Confidence::High,
-            reversible: true,
-            requires_reboot: false,
-            actions: vec![
-                "rm -f /var/lib/pacman/db.lck".into(),
-                "pacman -Sy".into(),
-            ],
-            explain: vec![
-                ExplainBlock {
-                    level: 1,
-                    category: ExplainCategory::WhatHappened,
-                    content: "A pacman database lock file exists, preventing package operations.".into(),
-                },
-                ExplainBlock {
-                    level: 2,
-                    category: ExplainCategory::WhyDetected,
-                    content: "VoxLinux verified that the lock file exists and no pacman process is currently running.".into(),
-                },
-                ExplainBlock {
-                    level: 3,
-                    category: ExplainCategory::WhySafe,
-                    content: "Since no package transaction is active, removing the stale lock file is considered safe.".into(),
-                },
-                ExplainBlock {
-                    level: 4,
-                    category: ExplainCategory::WhatWillExecute,
-                    content: "The system will remove the lock file and re-synchronize package databases.".into(),
+        let plan = RepairPlan::new(
+            "pacman database is locked",
+            "pacman-lock",
+            RiskLevel::Medium,
+            vec![
+                RepairAction::RemoveFile {
+                    path: paths::pacman_lock().to_string_lossy().into_owned(),
                 },
+                RepairAction::RunPackageSync,
             ],
-            integrity_hash: String::new(),
-        };
+        )
+        .with_confidence(report.confidence == Confidence::High)
+        .with_reversible(false) // pacman -Sy replaces the sync databases
+        .with_explain(
+            1,
+            ExplainCategory::WhatHappened,
+            "A pacman database lock file exists, preventing package operations.",
+        )
+        .with_explain(
+            2,
+            ExplainCategory::WhyDetected,
+            "VoxLinux verified that the lock file exists and no pacman process is currently running.",
+        )
+        .with_explain(
+            3,
+            ExplainCategory::WhySafe,
+            "Since no package transaction is active, removing the stale lock file is considered safe.",
+        )
+        .with_explain(
+            4,
+            ExplainCategory::WhatWillExecute,
+            "The system will remove the lock file and re-synchronize package databases.",
+        )
+        .with_verify(SystemState::PackageConsistent)
+        .sealed();
 
-        plan.integrity_hash = plan.compute_hash();
         plans.push(plan);
     }
 
@@ -82,46 +631,40 @@ pub fn build_repair_plans(
     // 2️⃣ FAILED SYSTEMD UNITS
     // ─────────────────────────────
     for unit in &report.failed_units {
-        if DENYLIST.contains(&unit.as_str()) {
-            continue;
+        if let Some(plan) = build_failed_unit_plan(report, unit) {
+            plans.push(plan);
         }
+    }
 
-        let mut plan = RepairPlan {
-            id: generate_plan_id(&format!("restart-{}", unit)),
-            issue: format!("systemd unit '{}' failed", unit),
-            risk: RiskLevel::Low,
-            confidence_high: report.confidence == Confidence::High,
-            reversible: true,
-            requires_reboot: false,
-            actions: vec![
-                format!("systemctl restart {}", unit),
-            ],
-            explain: vec![
-                ExplainBlock {
-                    level: 1,
-                    category: ExplainCategory::WhatHappened,
-                    content: "Systemd reports that this service is currently in a failed state.".into(),
-                },
-                ExplainBlock {
-                    level: 2,
-                    category: ExplainCategory::WhyDetected,
-                    content: "The service appears in the output of 'systemctl list-units --failed'.".into(),
-                },
-                ExplainBlock {
-                    level: 3,
-                    category: ExplainCategory::WhySafe,
-                    content: "Restarting a failed service is generally safe when confidence is high and the system is stable.".into(),
-                },
-                ExplainBlock {
-                    level: 4,
-                    category: ExplainCategory::WhatWillExecute,
-                    content: "VoxLinux will attempt to restart the service using systemctl restart.".into(),
-                },
-            ],
-            integrity_hash: String::new(),
-        };
+    // ─────────────────────────────
+    // 2️⃣.1 DISK SPACE
+    // ─────────────────────────────
+    for disk in &report.disks {
+        if disk.pressure() != DiskPressure::Ok {
+            plans.extend(build_cleanup_plans(disk));
+        }
+    }
+
+    // ─────────────────────────────
+    // 2️⃣.2 MEMORY PRESSURE
+    // ─────────────────────────────
+    if report.pressure.memory_stalled()
+    && let Some(plan) = build_memory_pressure_plan(&report.pressure)
+    {
+        plans.push(plan);
+    }
+
+    // ─────────────────────────────
+    // 2️⃣.3 READ-ONLY FILESYSTEMS
+    // ─────────────────────────────
+    for (mount, evidence) in &report.read_only_mounts {
+        plans.push(build_read_only_plan(mount, Some(evidence)));
+    }
 
-        plan.integrity_hash = plan.compute_hash();
+    // ─────────────────────────────
+    // 2️⃣.4 NETWORK
+    // ─────────────────────────────
+    if let Some(plan) = build_network_plan(&report.network) {
         plans.push(plan);
     }
 
@@ -129,38 +672,38 @@ pub fn build_repair_plans(
     // 3️⃣ HEALTH BROKEN
     // ─────────────────────────────
     if let Opinion::Broken { reason } = health {
-        let mut plan = RepairPlan {
-            id: generate_plan_id("health-broken"),
-            issue: format!("system health broken: {}", reason),
-            risk: RiskLevel::High,
-            confidence_high: false,
-            reversible: false,
-            requires_reboot: true,
-            actions: vec![
-                "Investigate system logs".into(),
-                "Consider rebooting".into(),
-            ],
-            explain: vec![
-                ExplainBlock {
-                    level: 1,
-                    category: ExplainCategory::WhatHappened,
-                    content: "The health module detected a critical system condition.".into(),
+        let plan = RepairPlan::new(
+            format!("system health broken: {}", reason),
+            "health-broken",
+            RiskLevel::High,
+            vec![
+                RepairAction::Manual {
+                    instruction: "Investigate system logs".into(),
                 },
-                ExplainBlock {
-                    level: 2,
-                    category: ExplainCategory::RiskAnalysis,
-                    content: "This condition may affect system stability or integrity.".into(),
-                },
-                ExplainBlock {
-                    level: 3,
-                    category: ExplainCategory::Preconditions,
-                    content: "Further manual inspection is required before automated repair is attempted.".into(),
+                RepairAction::Manual {
+                    instruction: "Consider rebooting".into(),
                 },
             ],
-            integrity_hash: String::new(),
-        };
+        )
+        .with_reversible(false)
+        .with_reboot(true)
+        .with_explain(
+            1,
+            ExplainCategory::WhatHappened,
+            "The health module detected a critical system condition.",
+        )
+        .with_explain(
+            2,
+            ExplainCategory::RiskAnalysis,
+            "This condition may affect system stability or integrity.",
+        )
+        .with_explain(
+            3,
+            ExplainCategory::Preconditions,
+            "Further manual inspection is required before automated repair is attempted.",
+        )
+        .sealed();
 
-        plan.integrity_hash = plan.compute_hash();
         plans.push(plan);
     }
 
@@ -168,38 +711,38 @@ pub fn build_repair_plans(
     // 4️⃣ SYSTEMD BROKEN
     // ─────────────────────────────
     if let Opinion::Broken { reason } = systemd {
-        let mut plan = RepairPlan {
-            id: generate_plan_id("systemd-broken"),
-            issue: format!("systemd integrity issue: {}", reason),
-            risk: RiskLevel::High,
-            confidence_high: false,
-            reversible: false,
-            requires_reboot: true,
-            actions: vec![
-                "Check journalctl -xe".into(),
-                "Reboot system".into(),
-            ],
-            explain: vec![
-                ExplainBlock {
-                    level: 1,
-                    category: ExplainCategory::WhatHappened,
-                    content: "The systemd subsystem reported a critical integrity issue.".into(),
-                },
-                ExplainBlock {
-                    level: 2,
-                    category: ExplainCategory::RiskAnalysis,
-                    content: "Core service management instability may impact the entire operating system.".into(),
+        let plan = RepairPlan::new(
+            format!("systemd integrity issue: {}", reason),
+            "systemd-broken",
+            RiskLevel::High,
+            vec![
+                RepairAction::Manual {
+                    instruction: "Check journalctl -xe".into(),
                 },
-                ExplainBlock {
-                    level: 3,
-                    category: ExplainCategory::Preconditions,
-                    content: "Manual inspection of logs is recommended before attempting corrective action.".into(),
+                RepairAction::Manual {
+                    instruction: "Reboot system".into(),
                 },
             ],
-            integrity_hash: String::new(),
-        };
+        )
+        .with_reversible(false)
+        .with_reboot(true)
+        .with_explain(
+            1,
+            ExplainCategory::WhatHappened,
+            "The systemd subsystem reported a critical integrity issue.",
+        )
+        .with_explain(
+            2,
+            ExplainCategory::RiskAnalysis,
+            "Core service management instability may impact the entire operating system.",
+        )
+        .with_explain(
+            3,
+            ExplainCategory::Preconditions,
+            "Manual inspection of logs is recommended before attempting corrective action.",
+        )
+        .sealed();
 
-        plan.integrity_hash = plan.compute_hash();
         plans.push(plan);
     }
 
@@ -2,19 +2,41 @@ use super::classifier::{Detection, Severity};
 use crate::state::BootContext;
 use crate::core::detector::detect_boot_context;
 use crate::core::confidence::Confidence;
+use crate::core::evidence;
+use crate::probe::{self, DiskUsage, UnitMemory};
+use crate::pressure::{self, PressureReport};
+use crate::mountinfo::MountInfo;
+use crate::network::{self, NetworkReport};
 use voxlinux::repair_plan::RepairPlan;
+use voxlinux::plan_status::PlanStatus;
+use voxlinux::plan_store::{PlanStore, StoreError};
+use voxlinux::signing::sign_plan;
+use crate::{events, keystore};
+use crate::systemd_backend::SystemdBackend;
+use voxlinux::protocol::Event;
+use voxlinux::paths;
+use voxlinux::command_runner::CommandRunner;
+use voxlinux::evidence::Evidence;
 use voxlinux::explain::{ExplainBlock, ExplainCategory};
 
-use std::process::Command;
-use std::fs;
-use std::io::Write;
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct ObserverReport {
     pub boot_context: BootContext,
     pub failed_units: Vec<String>,
+    /// Journal and exit details per failed unit
+    pub evidence: HashMap<String, Evidence>,
+    /// cgroup memory of failed units whose cgroup still exists
+    pub memory: HashMap<String, UnitMemory>,
     pub confidence: Confidence,
     pub pacman: PacmanState,
+    /// One entry per checked filesystem
+    pub disks: Vec<DiskUsage>,
+    pub pressure: PressureReport,
+    /// Mounts that went read-only unexpectedly, with their kernel errors
+    pub read_only_mounts: Vec<(MountInfo, Evidence)>,
+    pub network: NetworkReport,
 }
 
 #[derive(Debug)]
@@ -34,104 +56,257 @@ pub fn emit(d: &Detection) {
 }
 
 impl ObserverReport {
-    pub fn collect() -> Self {
-        let boot_context = detect_boot_context();
-        let failed_units = collect_failed_units();
+    pub fn collect(runner: &dyn CommandRunner, systemd: &dyn SystemdBackend) -> Self {
+        let boot_context = detect_boot_context(systemd);
+        let failed_units = collect_failed_units(systemd);
         let confidence = derive_confidence(boot_context, &failed_units);
 
-        let lock_exists = std::path::Path::new("/var/lib/pacman/db.lck").exists();
+        let memory = failed_units
+        .iter()
+        .filter_map(|unit| probe::unit_memory(unit, systemd).map(|m| (unit.clone(), m)))
+        .collect();
 
-        let pacman_running = Command::new("pgrep")
-        .arg("pacman")
-        .output()
+        let evidence = failed_units
+        .iter()
+        .map(|unit| (unit.clone(), evidence::collect(unit, runner, systemd)))
+        .collect();
+
+        let lock_exists = paths::pacman_lock().exists();
+
+        let pacman_running = runner
+        .run("pgrep", &["pacman"])
         .map(|o| !o.stdout.is_empty())
         .unwrap_or(false);
 
         Self {
             boot_context,
             failed_units,
+            evidence,
+            memory,
             confidence,
             pacman: PacmanState {
                 locked: lock_exists,
                 no_active_process: !pacman_running,
             },
+            disks: probe::check_disks(),
+            pressure: pressure::collect(),
+            read_only_mounts: probe::unexpected_read_only()
+            .into_iter()
+            .map(|m| {
+                let evidence = evidence::kernel_errors(&m, runner);
+                (m, evidence)
+            })
+            .collect(),
+            network: network::probe(runner),
         }
     }
 }
 
-fn collect_failed_units() -> Vec<String> {
-    let out = Command::new("systemctl")
-    .args(["list-units", "--failed", "--no-legend"])
-    .output();
+impl ObserverReport {
+    /// Whether the kernel OOM killer is behind the failure of `unit`
+    pub fn oom_killed(&self, unit: &str) -> bool {
+        let by_cgroup = self.memory.get(unit).map(|m| m.oom_kill > 0).unwrap_or(false);
+
+        let by_result = self
+        .evidence
+        .get(unit)
+        .and_then(|e| e.result.as_deref())
+        .map(|r| r == "oom-kill")
+        .unwrap_or(false);
+
+        by_cgroup || by_result
+    }
+}
+
+fn collect_failed_units(systemd: &dyn SystemdBackend) -> Vec<String> {
+    systemd
+    .failed_units()
+    .map(|units| units.into_iter().map(|u| u.name).collect())
+    .unwrap_or_default()
+}
+
+/// Store freshly built plans, keyed by their stable ID.
+///
+/// An open plan (proposed, approved or failed) with the same ID is
+/// rewritten in place, keeping its status, without an event. Running,
+/// dismissed and rolled-back plans are left alone. If the issue comes
+/// back after its plan succeeded, expired or was superseded, the plan is
+/// stored as the next generation (`RepairPlan::recurrence_id`) so the old
+/// record, its history and its rollback journal stay as they were. A new
+/// plan supersedes open plans that share its fingerprint but not its
+/// actions; they are marked with `superseded_by` and expired. The IDs in
+/// `plans` are updated to the stored ones.
+pub fn emit_repair_plans(store: &dyn PlanStore, plans: &mut [RepairPlan]) {
+    let mut stored = match store.list() {
+        Ok(stored) => stored,
+        Err(e) => {
+            eprintln!("[ERROR] cannot list stored plans: {}", e);
+            return;
+        }
+    };
+
+    for built in plans.iter_mut() {
 
-    match out {
-        Ok(o) if o.status.success() => {
-            String::from_utf8_lossy(&o.stdout)
-            .lines()
-            .filter_map(|line| {
-                let mut parts = line.split_whitespace();
+        // Newest stored generation of this plan
+        let latest = stored
+        .iter()
+        .filter_map(|p| built.generation_of(&p.id).map(|generation| (generation, p)))
+        .max_by_key(|(generation, _)| *generation);
 
-                let first = parts.next()?;
+        let mut plan = built.clone();
 
-                if first == "●" {
-                    parts.next().map(|s| s.to_string())
-                } else {
-                    Some(first.to_string())
+        let known = match latest {
+            Some((_, old)) if matches!(old.status, PlanStatus::Running | PlanStatus::Unverified | PlanStatus::Dismissed | PlanStatus::RolledBack) => {
+                built.id = old.id.clone();
+                continue;
+            }
+            Some((_, old)) if old.superseded_by.is_none() && old.status.is_open() => {
+                plan.id = old.id.clone();
+                plan.status = old.status;
+                plan.status_history = old.status_history.clone();
+                true
+            }
+            // Finished, or superseded and now the actions changed back
+            Some((generation, _)) => {
+                plan.id = plan.recurrence_id(generation + 1);
+                false
+            }
+            None => false,
+        };
+
+        sign_plan(&mut plan, keystore::signing_key());
+        built.id = plan.id.clone();
+
+        // The listing may be stale by now: an open plan is only replaced
+        // if it is still open, and it keeps the status it has in the store
+        let saved = if known {
+            store.update_with(&plan.id, &mut |old| {
+                if old.superseded_by.is_some() || !old.status.is_open() {
+                    return Err(StoreError::Conflict {
+                        id: old.id.clone(),
+                        reason: format!("no longer open ({})", old.status),
+                    });
                 }
+
+                let mut fresh = plan.clone();
+                fresh.status = old.status;
+                fresh.status_history = std::mem::take(&mut old.status_history);
+                *old = fresh;
+                Ok(())
             })
-            .collect()
+        } else {
+            store.put(&plan).map(|_| plan.clone())
+        };
+
+        let plan = match saved {
+            Ok(plan) => plan,
+            Err(e) => {
+                eprintln!("[ERROR] {}", e);
+                continue;
+            }
+        };
+
+        if known {
+            stored.retain(|p| p.id != plan.id);
+            stored.push(plan);
+            continue;
         }
-        _ => Vec::new(),
-    }
-}
 
-pub fn emit_repair_plans(plans: &[RepairPlan]) {
-    let dir = "/run/voxlinux/plans";
+        println!("[PLAN] saved → {}", plan.id);
+
+        events::publish(Event::PlanCreated {
+            plan_id: plan.id.clone(),
+            issue: plan.issue.clone(),
+            risk: plan.risk.clone(),
+        });
+
+        for old in stored.iter_mut().filter(|p| {
+            p.fingerprint == plan.fingerprint
+            && p.id != plan.id
+            && p.superseded_by.is_none()
+            && p.status.is_open()
+        }) {
+            let superseded = store.update_with(&old.id, &mut |stored| {
+                if stored.superseded_by.is_some() || !stored.status.is_open() {
+                    return Err(StoreError::Conflict {
+                        id: stored.id.clone(),
+                        reason: format!("no longer open ({})", stored.status),
+                    });
+                }
 
-    if let Err(e) = fs::create_dir_all(dir) {
-        eprintln!("[ERROR] failed to create plan directory: {}", e);
-        return;
-    }
+                // Expired, so the superseded plan falls under gc like
+                // any other plan nobody acted on
+                stored.superseded_by = Some(plan.id.clone());
 
-    for plan in plans {
+                stored
+                .transition(PlanStatus::Expired, "voxlinuxd", Some(format!("superseded by {}", plan.id)))
+                .map_err(StoreError::Transition)
+            });
 
-        let tmp_path = format!("{}/{}.tmp", dir, plan.id);
-        let final_path = format!("{}/{}.json", dir, plan.id);
+            match superseded {
+                Ok(updated) => *old = updated,
+                Err(e) => {
+                    eprintln!("[ERROR] {}", e);
+                    continue;
+                }
+            }
 
-        match serde_json::to_string_pretty(plan) {
-            Ok(json) => {
+            println!("[PLAN] {} superseded by {}", old.id, plan.id);
 
-                match fs::File::create(&tmp_path) {
-                    Ok(mut file) => {
+            events::publish(Event::PlanSuperseded {
+                old_id: old.id.clone(),
+                new_id: plan.id.clone(),
+            });
+        }
 
-                        if let Err(e) = file.write_all(json.as_bytes()) {
-                            eprintln!("[ERROR] failed writing tmp plan {}: {}", plan.id, e);
-                            continue;
-                        }
+        stored.retain(|p| p.id != plan.id);
+        stored.push(plan);
+    }
+}
 
-                        if let Err(e) = file.sync_all() {
-                            eprintln!("[ERROR] sync failed for {}: {}", plan.id, e);
-                            continue;
-                        }
+/// Expire open plans whose issue is gone, i.e. whose fingerprint no
+/// longer appears among the plans built in this scan
+pub fn expire_plans(store: &dyn PlanStore, current: &[RepairPlan]) {
+    let stored = match store.list() {
+        Ok(stored) => stored,
+        Err(e) => {
+            eprintln!("[ERROR] cannot list stored plans: {}", e);
+            return;
+        }
+    };
 
-                        if let Err(e) = fs::rename(&tmp_path, &final_path) {
-                            eprintln!("[ERROR] rename failed for {}: {}", plan.id, e);
-                            continue;
-                        }
+    for plan in stored {
 
-                        println!("[PLAN] saved → {}", final_path);
-                    }
+        if !plan.status.is_open() || current.iter().any(|p| p.fingerprint == plan.fingerprint) {
+            continue;
+        }
 
-                    Err(e) => {
-                        eprintln!("[ERROR] failed creating tmp plan {}: {}", plan.id, e);
-                    }
-                }
+        // Re-checked under the store lock: the plan may have been
+        // approved and started since it was listed
+        let expired = store.update_with(&plan.id, &mut |stored| {
+            if !stored.status.is_open() {
+                return Err(StoreError::Conflict {
+                    id: stored.id.clone(),
+                    reason: format!("no longer open ({})", stored.status),
+                });
             }
 
+            stored
+            .transition(PlanStatus::Expired, "voxlinuxd", Some("issue no longer detected".into()))
+            .map_err(StoreError::Transition)
+        });
+
+        let plan = match expired {
+            Ok(plan) => plan,
             Err(e) => {
-                eprintln!("[ERROR] failed to serialize plan {}: {}", plan.id, e);
+                eprintln!("[ERROR] cannot expire plan {}: {}", plan.id, e);
+                continue;
             }
-        }
+        };
+
+        println!("[PLAN] expired → {}", plan.id);
+
+        events::publish(Event::PlanExpired { plan_id: plan.id });
     }
 }
 
@@ -0,0 +1,37 @@
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Mutex, OnceLock};
+
+use voxlinux::protocol::Event;
+
+/// Events a subscriber may fall behind by before it is dropped
+const SUBSCRIBER_BACKLOG: usize = 256;
+
+/// One sender per connected subscriber
+static SUBSCRIBERS: OnceLock<Mutex<Vec<SyncSender<Event>>>> = OnceLock::new();
+
+fn subscribers() -> &'static Mutex<Vec<SyncSender<Event>>> {
+    SUBSCRIBERS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+pub fn subscribe() -> Receiver<Event> {
+    let (tx, rx) = sync_channel(SUBSCRIBER_BACKLOG);
+    subscribers().lock().unwrap().push(tx);
+    rx
+}
+
+/// Deliver an event to every live subscriber without blocking.
+/// Disconnected subscribers are dropped, and so are ones whose backlog
+/// is full: a client that stopped reading must not grow the daemon's
+/// memory. Dropping the sender ends its stream, so it can reconnect.
+pub fn publish(event: Event) {
+    let mut subs = subscribers().lock().unwrap();
+
+    subs.retain(|tx| match tx.try_send(event.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            println!("[EVENTS] Dropping a subscriber {} events behind", SUBSCRIBER_BACKLOG);
+            false
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    });
+}
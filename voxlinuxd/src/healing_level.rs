@@ -1,8 +0,0 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum HealingLevel {
-    ObserveOnly,      // Stage 0
-    RuntimeSafe,      // Stage 1
-    AssistedRepair,
-    AutonomousRepair,// Stage 2
-}
-
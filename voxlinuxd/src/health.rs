@@ -1,19 +1,38 @@
-use crate::probe;
+use crate::probe::{self, DiskPressure};
+use crate::pressure;
 use crate::core::opinion::Opinion;
+use voxlinux::command_runner::CommandRunner;
 
-pub fn assess() -> Opinion {
-    if probe::disk_full() {
+pub fn assess(runner: &dyn CommandRunner) -> Opinion {
+    let disks = probe::check_disks();
+
+    if let Some(disk) = disks.iter().find(|d| d.pressure() == DiskPressure::Critical) {
         return Opinion::Broken {
-            reason: "Root filesystem usage is critically high".to_string(),
+            reason: format!("Filesystem usage is critically high ({})", disk.describe()),
         };
     }
 
-    if probe::boot_degraded() {
+    let pressure = pressure::assess(&pressure::collect());
+
+    if let Opinion::Broken { .. } = pressure {
+        return pressure;
+    }
+
+    if let Some(boot) = probe::boot_degraded(runner) {
         return Opinion::Degraded {
-            reason: "Boot completed with degraded systemd state".to_string(),
+            reason: format!("systemd state is degraded: {}", boot.describe()),
+        };
+    }
+
+    if let Opinion::Degraded { .. } = pressure {
+        return pressure;
+    }
+
+    if let Some(disk) = disks.iter().find(|d| d.pressure() == DiskPressure::Warning) {
+        return Opinion::Degraded {
+            reason: format!("Filesystem is filling up ({})", disk.describe()),
         };
     }
 
     Opinion::Ok
 }
-
@@ -0,0 +1,69 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use voxlinux::paths;
+use voxlinux::protocol::{HistoryEntry, HistoryOutcome};
+
+fn history_file() -> PathBuf {
+    paths::state_dir().join("history.json")
+}
+
+/// Oldest entries are dropped beyond this many
+const MAX_ENTRIES: usize = 500;
+
+static HISTORY: OnceLock<Mutex<Vec<HistoryEntry>>> = OnceLock::new();
+
+fn with_history<F, R>(f: F) -> R
+where
+F: FnOnce(&mut Vec<HistoryEntry>) -> R,
+{
+    let mutex = HISTORY.get_or_init(|| {
+        let entries = fs::read_to_string(history_file())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+
+        Mutex::new(entries)
+    });
+
+    let mut guard = mutex.lock().unwrap();
+    f(&mut guard)
+}
+
+/// Append an execution outcome and persist the history
+pub fn record(plan_id: &str, issue: &str, outcome: HistoryOutcome) {
+    let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap()
+    .as_secs();
+
+    with_history(|entries| {
+        entries.push(HistoryEntry {
+            timestamp,
+            plan_id: plan_id.to_string(),
+            issue: issue.to_string(),
+            outcome,
+        });
+
+        if entries.len() > MAX_ENTRIES {
+            let excess = entries.len() - MAX_ENTRIES;
+            entries.drain(..excess);
+        }
+
+        let _ = fs::create_dir_all(paths::state_dir());
+
+        if let Ok(json) = serde_json::to_string_pretty(entries) {
+            let _ = fs::write(history_file(), json);
+        }
+    });
+}
+
+/// Most recent `limit` entries, newest last
+pub fn recent(limit: usize) -> Vec<HistoryEntry> {
+    with_history(|entries| {
+        let start = entries.len().saturating_sub(limit);
+        entries[start..].to_vec()
+    })
+}
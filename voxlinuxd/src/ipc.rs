@@ -1,36 +1,48 @@
 use std::os::unix::net::{UnixListener, UnixStream};
-use std::io::{Read, Write};
 use std::fs;
 use std::thread;
 use std::os::unix::fs::PermissionsExt;
 
-use crate::repair_executor;
+use crate::auth::{self, AuthPolicy, PeerCredentials};
+use crate::{events, history, repair_executor, status};
+use voxlinux::command_runner::CommandRunner;
+use voxlinux::paths;
+use voxlinux::protocol::{self, DaemonError, FrameError, Request, Response};
 use voxlinux::repair_plan::RepairPlan;
+use voxlinux::plan_status::PlanStatus;
+use voxlinux::plan_store::{PlanStore, StoreError};
 
-const SOCKET_PATH: &str = "/run/voxlinux/voxlinux.sock";
+pub fn start_ipc_server(runner: &'static dyn CommandRunner, store: &'static dyn PlanStore) {
 
-pub fn start_ipc_server() {
+    let socket_path = paths::socket_path();
 
-    if let Some(parent) = std::path::Path::new(SOCKET_PATH).parent() {
+    if let Some(parent) = socket_path.parent() {
         fs::create_dir_all(parent).ok();
         fs::set_permissions(parent, fs::Permissions::from_mode(0o755)).ok();
     }
 
-    let _ = fs::remove_file(SOCKET_PATH);
+    let _ = fs::remove_file(&socket_path);
 
-    let listener = UnixListener::bind(SOCKET_PATH)
+    let listener = UnixListener::bind(&socket_path)
     .expect("Failed to bind socket");
 
-    fs::set_permissions(SOCKET_PATH, fs::Permissions::from_mode(0o666)).ok();
+    // Anyone may connect; each request is authorized from SO_PEERCRED
+    fs::set_permissions(&socket_path, fs::Permissions::from_mode(0o666)).ok();
 
-    println!("[IPC] Listening on {}", SOCKET_PATH);
+    let policy = AuthPolicy::default();
+
+    println!(
+        "[IPC] Listening on {} (admin group: {})",
+        socket_path.display(), policy.admin_group
+    );
 
     for stream in listener.incoming() {
 
         match stream {
 
             Ok(stream) => {
-                thread::spawn(|| handle_client(stream));
+                let policy = policy.clone();
+                thread::spawn(move || handle_client(stream, policy, runner, store));
             }
 
             Err(e) => {
@@ -40,60 +52,184 @@ pub fn start_ipc_server() {
     }
 }
 
-fn handle_client(mut stream: UnixStream) {
+/// Serve framed requests until the client hangs up
+fn handle_client(
+    mut stream: UnixStream,
+    policy: AuthPolicy,
+    runner: &'static dyn CommandRunner,
+    store: &'static dyn PlanStore,
+) {
+
+    let creds = match auth::peer_credentials(&stream) {
+        Ok(creds) => creds,
+        Err(e) => {
+            eprintln!("[IPC] cannot read peer credentials: {}", e);
+            return;
+        }
+    };
+
+    loop {
+
+        let response = match protocol::recv::<_, Request>(&mut stream) {
+
+            Ok(Request::Subscribe) => {
+                println!("[IPC] {} subscribed to events", creds);
+                stream_events(stream);
+                return;
+            }
+
+            Ok(request) => {
+                println!("[IPC] Received from {}: {:?}", creds, request);
+                dispatch(request, &creds, &policy, runner, store)
+            }
+
+            Err(e) if e.is_eof() => return,
+
+            Err(FrameError::UnsupportedVersion(got)) => {
+                Response::Error(DaemonError::UnsupportedVersion {
+                    got,
+                    supported: protocol::PROTOCOL_VERSION,
+                })
+            }
 
-    let mut buffer = [0; 512];
+            Err(FrameError::Malformed(e)) => {
+                Response::Error(DaemonError::MalformedRequest(e))
+            }
 
-    match stream.read(&mut buffer) {
+            Err(e) => {
+                eprintln!("[IPC] read error: {}", e);
+                return;
+            }
+        };
 
-        Ok(size) => {
+        if let Err(e) = protocol::send(&mut stream, &response) {
+            eprintln!("[IPC] write error: {}", e);
+            return;
+        }
+    }
+}
 
-            let request =
-            String::from_utf8_lossy(&buffer[..size])
-            .trim()
-            .to_string();
+/// Push every daemon event to the client until it disconnects
+fn stream_events(mut stream: UnixStream) {
 
-            println!("[IPC] Received: {}", request);
+    let rx = events::subscribe();
 
-            if let Some(plan_id) = request.strip_prefix("APPLY:") {
+    if protocol::send(&mut stream, &Response::Subscribed).is_err() {
+        return;
+    }
 
-                let plan_id = plan_id.trim();
+    for event in rx {
+        if protocol::send(&mut stream, &Response::Event(event)).is_err() {
+            return;
+        }
+    }
+}
 
-                match load_plan(plan_id) {
+fn dispatch(
+    request: Request,
+    creds: &PeerCredentials,
+    policy: &AuthPolicy,
+    runner: &'static dyn CommandRunner,
+    store: &'static dyn PlanStore,
+) -> Response {
+
+    // Apply and Rollback are authorized by the risk of the plan they target
+    let plan = match &request {
+        Request::Apply { id }
+        | Request::DryRun { id }
+        | Request::Dismiss { id, .. }
+        | Request::Rollback { id } => match load_plan(store, id) {
+            Some(plan) => Some(plan),
+            None => return Response::Error(DaemonError::PlanNotFound(id.clone())),
+        },
+        _ => None,
+    };
+
+    if let Err(reason) = policy.authorize(creds, &request, plan.as_ref().map(|p| &p.risk)) {
+        println!("[IPC] Denied {:?} for {}: {}", request, creds, reason);
+        repair_executor::log_event(&format!(
+            "DENIED {} request={:?} reason={}",
+            creds, request, reason
+        ));
+        return Response::Error(DaemonError::PermissionDenied(reason));
+    }
 
-                    Some(plan) => {
+    match (request, plan) {
 
-                        repair_executor::apply_plan(plan);
+        (Request::ListPlans, _) => Response::Plans(load_plans(store)),
 
-                        let _ = stream.write_all(b"OK");
-                    }
+        (Request::GetPlan { id }, _) => match load_plan(store, &id) {
+            Some(plan) => Response::Plan(plan),
+            None => Response::Error(DaemonError::PlanNotFound(id)),
+        },
 
-                    None => {
-                        let _ = stream.write_all(b"PLAN_NOT_FOUND");
-                    }
-                }
+        (Request::Apply { .. }, Some(plan)) => execute(plan, false, &creds.describe(), runner, store),
 
-            } else {
+        (Request::DryRun { .. }, Some(plan)) => execute(plan, true, &creds.describe(), runner, store),
 
-                let _ = stream.write_all(b"UNKNOWN_COMMAND");
+        (Request::Dismiss { reason, .. }, Some(plan)) => {
+            match store.update_status(&plan.id, PlanStatus::Dismissed, &creds.describe(), reason) {
+                Ok(plan) => Response::Plan(plan),
+                Err(StoreError::Transition(e)) => Response::Error(DaemonError::PolicyBlocked(e.to_string())),
+                Err(e) => Response::Error(DaemonError::Internal(e.to_string())),
             }
         }
 
-        Err(e) => {
-            eprintln!("[IPC] read error: {}", e);
+        (Request::Rollback { .. }, Some(plan)) => {
+            match repair_executor::rollback_plan(plan, &creds.describe(), runner, store) {
+                Ok(plan) => Response::Plan(plan),
+                Err(e) => Response::Error(e),
+            }
+        }
+
+        (Request::GetStatus, _) => Response::Status(status::snapshot()),
+
+        (Request::GetHistory { limit }, _) => Response::History(history::recent(limit)),
+
+        (Request::SetHealingLevel { level }, _) => {
+            status::set_healing_level(level, &creds.describe(), None);
+            Response::HealingLevel(status::healing_level())
+        }
+
+        (Request::Pause { duration_secs, reason }, _) => {
+            if duration_secs == 0 {
+                return Response::Error(DaemonError::MalformedRequest("pause duration must be positive".into()));
+            }
+
+            Response::Paused(status::pause(duration_secs, reason, &creds.describe()))
+        }
+
+        (Request::Resume, _) => Response::HealingLevel(status::resume(&creds.describe())),
+
+        (Request::Subscribe, _) => {
+            Response::Error(DaemonError::MalformedRequest("subscribe is handled per connection".into()))
+        }
+
+        (request, None) => {
+            Response::Error(DaemonError::Internal(format!("no plan loaded for {:?}", request)))
         }
     }
 }
 
-fn load_plan(plan_id: &str) -> Option<RepairPlan> {
+fn execute(
+    plan: RepairPlan,
+    dry_run: bool,
+    by: &str,
+    runner: &'static dyn CommandRunner,
+    store: &'static dyn PlanStore,
+) -> Response {
+
+    match repair_executor::apply_plan(plan, dry_run, by, runner, store) {
+        Ok(report) => Response::Executed(report),
+        Err(e) => Response::Error(e),
+    }
+}
 
-    let path = format!("/run/voxlinux/plans/{}.json", plan_id);
+fn load_plan(store: &dyn PlanStore, plan_id: &str) -> Option<RepairPlan> {
 
-    match fs::read_to_string(&path) {
+    match store.get(plan_id) {
 
-        Ok(data) => {
-            serde_json::from_str(&data).ok()
-        }
+        Ok(plan) => plan,
 
         Err(e) => {
 
@@ -103,3 +239,18 @@ fn load_plan(plan_id: &str) -> Option<RepairPlan> {
         }
     }
 }
+
+fn load_plans(store: &dyn PlanStore) -> Vec<RepairPlan> {
+
+    match store.list() {
+
+        Ok(plans) => plans,
+
+        Err(e) => {
+
+            println!("[IPC] Failed to list plans: {}", e);
+
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::sync::OnceLock;
+
+use voxlinux::paths;
+use voxlinux::signing::{SigningKey, VerifyingKey};
+
+/// Host signing key, loaded (or created) by `init`
+static KEY: OnceLock<SigningKey> = OnceLock::new();
+
+/// Load the signing key, or create it if there is none yet. A key file
+/// that exists but cannot be read or parsed is an error: replacing it
+/// would silently invalidate every stored plan.
+pub fn init() -> Result<(), String> {
+    let key = match load_key()? {
+        Some(key) => key,
+        None => generate()?,
+    };
+
+    write_public_key(&key);
+    let _ = KEY.set(key);
+    Ok(())
+}
+
+pub fn signing_key() -> &'static SigningKey {
+    KEY.get().expect("keystore::init runs at startup")
+}
+
+pub fn verifying_key() -> VerifyingKey {
+    signing_key().verifying_key()
+}
+
+fn generate() -> Result<SigningKey, String> {
+    let key_path = paths::signing_key();
+
+    println!("[KEYS] No plan signing key found → generating {}", key_path.display());
+
+    let mut seed = [0u8; 32];
+    fs::File::open("/dev/urandom")
+    .and_then(|mut f| f.read_exact(&mut seed))
+    .map_err(|e| format!("cannot read /dev/urandom: {}", e))?;
+
+    let key = SigningKey::from_bytes(&seed);
+
+    if let Some(parent) = key_path.parent() {
+        fs::create_dir_all(parent)
+        .map_err(|e| format!("cannot create {}: {}", parent.display(), e))?;
+    }
+
+    // Created 0600 before the secret is written; an existing file means
+    // another process got there first, and is not overwritten
+    let mut file = fs::OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .mode(0o600)
+    .open(&key_path)
+    .map_err(|e| format!("cannot create signing key {}: {}", key_path.display(), e))?;
+
+    file.write_all(hex::encode(key.to_bytes()).as_bytes())
+    .and_then(|_| file.sync_all())
+    .map_err(|e| format!("cannot write signing key {}: {}", key_path.display(), e))?;
+
+    Ok(key)
+}
+
+/// `Ok(None)` only if there is no key file
+fn load_key() -> Result<Option<SigningKey>, String> {
+    let key_path = paths::signing_key();
+
+    let data = match fs::read_to_string(&key_path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("cannot read signing key {}: {}", key_path.display(), e)),
+    };
+
+    let bytes: [u8; 32] = hex::decode(data.trim())
+    .ok()
+    .and_then(|bytes| bytes.try_into().ok())
+    .ok_or_else(|| format!("signing key {} is corrupt", key_path.display()))?;
+
+    Ok(Some(SigningKey::from_bytes(&bytes)))
+}
+
+/// Publish the public key so intentctl can verify plans
+fn write_public_key(key: &SigningKey) {
+    let public = hex::encode(key.verifying_key().to_bytes());
+    let public_path = paths::public_key();
+
+    if fs::read_to_string(&public_path).map(|p| p.trim() == public).unwrap_or(false) {
+        return;
+    }
+
+    if let Err(e) = fs::write(&public_path, format!("{}\n", public)) {
+        eprintln!("[KEYS] failed to write public key: {}", e);
+        return;
+    }
+
+    let _ = fs::set_permissions(&public_path, fs::Permissions::from_mode(0o644));
+}
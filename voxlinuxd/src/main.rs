@@ -3,22 +3,32 @@ mod systemd;
 mod health;
 mod state;
 mod predictive;
+mod pressure;
 
 mod pacman;
-mod system_state;
 mod probe;
-mod healing_level;
+mod mountinfo;
+mod network;
 mod verifier;
 
 mod ipc;
 mod repair_executor;
+mod keystore;
+mod status;
+mod history;
+mod auth;
+mod events;
+mod systemd_backend;
+mod config;
 
 
 use std::process::Command;
 use std::thread;
-use std::time::Duration;
+use std::collections::HashSet;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use voxlinux::explain::{ExplainBlock, ExplainCategory};
 use core::{detector, classifier, policy, reporter};
 use core::heal_gate::healing_allowed;
@@ -26,54 +36,295 @@ use core::confidence_eval::evaluate;
 use core::opinion::Opinion;
 use core::classifier::{Severity, FailureClass};
 use voxlinux::repair_plan::{RepairPlan, RiskLevel};
+use voxlinux::plan_store::{self, PlanStore};
+use voxlinux::plan_status::PlanStatus;
+use voxlinux::repair_action::RepairAction;
+use voxlinux::protocol::Event;
+use voxlinux::command_runner::{CommandRunner, SystemRunner};
+use voxlinux::transaction;
 use core::ai_advisor;
-use crate::core::repair_builder::build_repair_plans;
+use crate::core::repair_builder::{build_failed_unit_plan, build_repair_plans};
+use crate::systemd_backend::{SystemdBackend, UnitEvent};
 use crate::core::reporter::ObserverReport;
 use crate::core::deferred::DeferredHealQueue;
 use crate::core::healer::HealingSession;
-use crate::healing_level::HealingLevel;
+use voxlinux::healing_level::HealingLevel;
+use voxlinux::paths;
 
 fn init_runtime_dirs() {
-    let base = "/run/voxlinux";
-    let plans = "/run/voxlinux/plans";
+    let base = paths::runtime_dir();
+    let plans = paths::plan_dir();
+
+    fs::create_dir_all(&plans).expect("Failed to create runtime directory");
+    fs::create_dir_all(paths::backup_dir()).expect("Failed to create backup directory");
+
+    // Plan status, history and supersession are not signed, and journals
+    // decide what a rollback does: only the daemon may write either
+    for dir in [base, plans, paths::backup_dir()] {
+        if let Err(e) = make_private(&dir) {
+            eprintln!("voxlinuxd: {}: {}", dir.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
 
-    fs::create_dir_all(plans).expect("Failed to create runtime directory");
+/// Restrict `dir` to mode 0700 and refuse it unless it is a real
+/// directory owned by the daemon's user
+fn make_private(dir: &std::path::Path) -> Result<(), String> {
+    let meta = fs::symlink_metadata(dir).map_err(|e| e.to_string())?;
+
+    if !meta.is_dir() {
+        return Err("not a directory".into());
+    }
 
-    fs::set_permissions(base, fs::Permissions::from_mode(0o700))
-    .expect("Failed to set permissions on /run/voxlinux");
+    let euid = unsafe { libc::geteuid() };
+
+    if meta.uid() != euid {
+        return Err(format!("owned by uid {}, not {}", meta.uid(), euid));
+    }
+
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).map_err(|e| e.to_string())
 }
 
+/// `--root-dir DIR` / `--root-dir=DIR`; overrides $VOXLINUX_ROOT
+fn root_dir_arg() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--root-dir" {
+            return args.next();
+        }
+
+        if let Some(dir) = arg.strip_prefix("--root-dir=") {
+            return Some(dir.to_string());
+        }
+    }
+
+    None
+}
+
+/// Report one autonomous action to subscribers
+fn report_action(plan: &RepairPlan, action: &RepairAction, result: &Result<(), String>) {
+    println!("[AUTO] Ran: {}", action);
+
+    if let Err(e) = result {
+        println!("[AUTO] Action failed: {}", e);
+    }
+
+    events::publish(Event::ActionResult {
+        plan_id: plan.id.clone(),
+        action: action.clone(),
+        error: result.clone().err(),
+    });
+}
+
+/// Reset or escalate the issue of an autonomous plan once it ended;
+/// `repair_executor` already recorded and announced the outcome
+fn finish_autonomous(plan_id: &str, key: &str, success: bool) {
+    if success {
+        println!("[AUTO] Plan={} succeeded", plan_id);
+        state::reset_level(key);
+        state::bump_confidence(key);
+    } else {
+        println!("[AUTO] Plan={} failed → escalating", plan_id);
+        state::escalate_level(key);
+        state::drop_confidence(key);
+    }
+}
+
+/// Quiet period that ends a burst of unit events (restart loops, cascades)
+const EVENT_SETTLE: Duration = Duration::from_secs(2);
+
+/// A unit that flaps without pause must not hold detection off for ever
+const EVENT_BURST_MAX: usize = 64;
+const EVENT_BURST_MAX_WAIT: Duration = Duration::from_secs(10);
+
+/// Wait for the first unit event and everything that follows it closely,
+/// up to `EVENT_BURST_MAX` events or `EVENT_BURST_MAX_WAIT` after the first
+fn next_event_burst(events: &Receiver<UnitEvent>, timeout: Duration) -> Result<Vec<UnitEvent>, RecvTimeoutError> {
+    let mut burst = vec![events.recv_timeout(timeout)?];
+    let deadline = Instant::now() + EVENT_BURST_MAX_WAIT;
+
+    while burst.len() < EVENT_BURST_MAX {
+        let left = deadline.saturating_duration_since(Instant::now());
+
+        match events.recv_timeout(EVENT_SETTLE.min(left)) {
+            Ok(event) => burst.push(event),
+            Err(_) => break,
+        }
+    }
+
+    Ok(burst)
+}
+
+/// Fast path: detector → classifier → policy → plan for units systemd just reported
+fn handle_unit_events(
+    burst: &[UnitEvent],
+    deferred_queue: &mut DeferredHealQueue,
+    runner: &dyn CommandRunner,
+    systemd: &dyn SystemdBackend,
+    store: &dyn PlanStore,
+) {
+    let healing_level = status::healing_level();
+    let report = ObserverReport::collect(runner, systemd);
+    let mut seen = HashSet::new();
+
+    for event in burst {
+        println!("[EVENT] {}", event);
+
+        let unit = event.unit();
+        if !seen.insert(unit.to_string()) {
+            continue;
+        }
+
+        let classified = classifier::classify(detector::from_event(event, systemd));
+
+        if classified.severity == Severity::Critical {
+            deferred_queue.enqueue(&classified);
+        }
+
+        let filtered = policy::apply_policy(classified);
+        reporter::emit(&filtered);
+
+        if healing_level != HealingLevel::AssistedRepair {
+            continue;
+        }
+
+        // A unit that already came back (a flap) needs no plan
+        if !report.failed_units.iter().any(|u| u == unit) {
+            println!("[EVENT] {} is no longer failed", unit);
+            continue;
+        }
+
+        if let Some(mut plan) = build_failed_unit_plan(&report, unit) {
+            reporter::emit_repair_plans(store, std::slice::from_mut(&mut plan));
+            reporter::print_plan_summary(&plan);
+        }
+    }
+}
+
+/// Drop finished plans and transaction backups older than `retain_secs`.
+/// Backups of plans that are still stored and can be rolled back stay.
+fn collect_plan_garbage(store: &dyn PlanStore, retain_secs: u64) {
+    match store.gc(retain_secs) {
+        Ok(removed) if !removed.is_empty() => println!("[PLAN] removed finished plans: {}", removed.join(", ")),
+        Ok(_) => {}
+        Err(e) => println!("[PLAN] garbage collection failed: {}", e),
+    }
+
+    let keep: Vec<String> = match store.list() {
+        Ok(plans) => plans
+        .into_iter()
+        .filter(|p| p.status == PlanStatus::Running || p.status.can_become(PlanStatus::RolledBack))
+        .map(|p| p.id)
+        .collect(),
+        Err(e) => {
+            println!("[PLAN] keeping all transaction backups: {}", e);
+            return;
+        }
+    };
+
+    let removed = transaction::gc(retain_secs, &keep);
+
+    if !removed.is_empty() {
+        println!("[PLAN] removed transaction backups: {}", removed.join(", "));
+    }
+}
 
 fn main() {
 
 
-    let mut healing_level = HealingLevel::AssistedRepair;
     let mut last_notified_issue: Option<String> = None;
     let mut deferred_queue = DeferredHealQueue::default();
     let mut healing_session = HealingSession::default();
+    let mut gate_open: Option<bool> = None;
+
+
+    if let Some(dir) = root_dir_arg()
+    && let Err(e) = paths::set_root(std::path::Path::new(&dir))
+    {
+        eprintln!("voxlinuxd: {}", e);
+        std::process::exit(1);
+    }
+
+    if paths::root() != std::path::Path::new("/") {
+        println!("voxlinuxd: using root prefix {}", paths::root().display());
+    }
+
+    let config = match config::init() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("voxlinuxd: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if std::env::args().any(|a| a == "--check-config") {
+        println!("voxlinuxd: configuration OK");
+        return;
+    }
+
+    // Every external program the engine runs goes through this runner
+    let runner: &'static dyn CommandRunner = &SystemRunner;
 
+    // …and every systemd query and unit event through this backend
+    let systemd: &'static dyn SystemdBackend = systemd_backend::backend();
 
     init_runtime_dirs();   // FIRST create /run/voxlinux
 
-    let _ = std::fs::remove_dir_all("/run/voxlinux/plans");
-    let _ = std::fs::create_dir_all("/run/voxlinux/plans");
+    // Load or create the plan signing key
+    if let Err(e) = keystore::init() {
+        eprintln!("voxlinuxd: {}", e);
+        std::process::exit(1);
+    }
+
+    // Plans and their backups outlive a daemon restart, so a repair can
+    // still be rolled back; finished ones go after the retention period
+    let store: &'static dyn PlanStore = plan_store::system_store();
+    repair_executor::fail_interrupted_plans(store);
+    collect_plan_garbage(store, config.plans.retention_secs);
 
-    std::thread::spawn(|| {
-        ipc::start_ipc_server();
+    std::thread::spawn(move || {
+        ipc::start_ipc_server(runner, store);
     });
 
     println!("voxlinuxd: self-healing engine started");
 
+    let unit_events = systemd.unit_events();
+    let scan_interval = Duration::from_secs(config.daemon.scan_interval_secs);
+    let mut next_scan = Instant::now();
+
     loop {
+        // ─────────────────────────────
+        // Unit events between reconciliation scans
+        // ─────────────────────────────
+        let until_scan = next_scan.saturating_duration_since(Instant::now());
+
+        if !until_scan.is_zero() {
+            match next_event_burst(&unit_events, until_scan) {
+                Ok(burst) => {
+                    handle_unit_events(&burst, &mut deferred_queue, runner, systemd, store);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => thread::sleep(until_scan),
+            }
+        }
+
+        let healing_level = status::healing_level();
+
+        next_scan = Instant::now() + status::pause_left().map_or(scan_interval, |left| left.min(scan_interval));
+        let mut plan_count = 0;
+
         // ─────────────────────────────
         // 0️⃣ Observer snapshot
         // ─────────────────────────────
-        let report = ObserverReport::collect();
+        let report = ObserverReport::collect(runner, systemd);
 
         // ─────────────────────────────
         // 1️⃣ Detection & unit classification
         // ─────────────────────────────
-        let detections = detector::scan();
+        let detections = detector::scan(systemd);
 
         for d in detections {
             let classified = classifier::classify(d);
@@ -89,7 +340,7 @@ fn main() {
         // ─────────────────────────────
         // 2️⃣ Advisory opinions
         // ─────────────────────────────
-        let health_op = health::assess();
+        let health_op = health::assess(runner);
         let systemd_op = systemd::assess();
 
         // ─────────────────────────────
@@ -109,6 +360,14 @@ fn main() {
 
         println!("[FAILURE_CLASS] {:?}", failure_class);
 
+        let previous_class = status::snapshot().failure_class;
+        if previous_class != Some(failure_class) {
+            events::publish(Event::FailureClassChanged {
+                from: previous_class,
+                to: failure_class,
+            });
+        }
+
         if failure_class == FailureClass::CoreIntegrityFailure {
             println!("[POLICY] Integrity failure detected → autonomy restricted.");
         }
@@ -157,18 +416,36 @@ fn main() {
                      report.boot_context,
                      confidence
             );
+
+            // Announce only when the gate closes, not on every cycle
+            if gate_open != Some(false) {
+                events::publish(Event::HealingBlocked {
+                    plan_id: None,
+                    reason: format!(
+                        "boot={:?}, confidence={:?}",
+                        report.boot_context, confidence
+                    ),
+                });
+            }
         }
 
+        gate_open = Some(allowed);
+
+        // Built every cycle so stored plans can expire at any level
+        let mut plans = build_repair_plans(&report, &health_op, &systemd_op);
+
         // ─────────────────────────────
         // Stage-2 Assisted Repair
         // ─────────────────────────────
-        if healing_level == HealingLevel::AssistedRepair {
-            let plans = build_repair_plans(&report, &health_op, &systemd_op);
+        // Autonomous repair applies stored plans too, so they are listed,
+        // journaled and can be rolled back like the ones an operator applies
+        if matches!(healing_level, HealingLevel::AssistedRepair | HealingLevel::AutonomousRepair) {
+            plan_count = plans.len();
 
             if plans.is_empty() {
                 println!("[STAGE2] No repair plans generated.");
             } else {
-                reporter::emit_repair_plans(&plans);
+                reporter::emit_repair_plans(store, &mut plans);
 
                 for plan in &plans {
                     reporter::print_plan_summary(plan);
@@ -176,6 +453,31 @@ fn main() {
             }
         }
 
+        // ─────────────────────────────
+        // Predictive drift (sampled at every level)
+        // ─────────────────────────────
+        let mut preventive = predictive::observe(&report, runner, systemd);
+
+        if !preventive.is_empty() {
+            if healing_level == HealingLevel::AssistedRepair {
+                plan_count += preventive.len();
+                reporter::emit_repair_plans(store, &mut preventive);
+
+                for plan in &preventive {
+                    reporter::print_plan_summary(plan);
+                }
+            } else {
+                for plan in &preventive {
+                    println!("[PREDICT] {} (plan not offered at {:?})", plan.issue, healing_level);
+                }
+            }
+        }
+
+        let current: Vec<RepairPlan> = plans.iter().chain(&preventive).cloned().collect();
+        reporter::expire_plans(store, &current);
+        repair_executor::verify_unverified_plans(store, runner);
+        collect_plan_garbage(store, config.plans.retention_secs);
+
         // ─────────────────────────────
         // Stage-1 RuntimeSafe healing
         // ─────────────────────────────
@@ -214,20 +516,41 @@ fn main() {
             && allowed
             && failure_class != FailureClass::CoreIntegrityFailure
             {
-                let plans = build_repair_plans(&report, &health_op, &systemd_op);
+                for plan in &plans {
+                    if plan.risk != voxlinux::repair_plan::RiskLevel::Low || !plan.confidence_high {
+                        continue;
+                    }
 
-                for plan in plans {
-                    if plan.risk != voxlinux::repair_plan::RiskLevel::Low {
+                    if verifier::is_watching(&plan.id) {
+                        println!("[AUTO] Plan={} is still being verified", plan.id);
                         continue;
                     }
 
-                    let key = plan.issue.clone();
+                    let key = plan.fingerprint.clone();
 
                     if !state::should_retry(&key) {
                         println!("[AUTO] Backoff active for {}", key);
                         continue;
                     }
 
+                    // The stored copy carries the signature and the status
+                    let stored = match store.get(&plan.id) {
+                        Ok(Some(stored)) => stored,
+                        Ok(None) => {
+                            println!("[AUTO] Plan={} is not stored", plan.id);
+                            continue;
+                        }
+                        Err(e) => {
+                            println!("[AUTO] Cannot load plan={}: {}", plan.id, e);
+                            continue;
+                        }
+                    };
+
+                    if let Some(reason) = stored.apply_blocker() {
+                        println!("[AUTO] Plan={} skipped: {}", plan.id, reason);
+                        continue;
+                    }
+
                     let level = state::current_level(&key);
 
                     println!(
@@ -235,78 +558,13 @@ fn main() {
                              plan.id, level
                     );
 
-                    let mut success = true;
-
                     match level {
-                        1 => {
-                            for action in &plan.actions {
-                                println!("[AUTO] Running: {}", action);
-                                let status_ok = std::process::Command::new("sh")
-                                .arg("-c")
-                                .arg(&action)
-                                .status()
-                                .map(|s| s.success())
-                                .unwrap_or(false);
-
-                                if !status_ok {
-                                    success = false;
-                                } else {
-                                    // VERIFY service actually recovered
-                                    if let Some(unit) = action.split_whitespace().last() {
-                                        let is_active = std::process::Command::new("systemctl")
-                                        .arg("is-active")
-                                        .arg(unit)
-                                        .status()
-                                        .map(|s| s.success())
-                                        .unwrap_or(false);
-
-                                        if !is_active {
-                                            success = false;
-                                        }
-                                    }
-                                }
-                            }
-                        }
+                        1 => {}
 
                         2 => {
                             println!("[AUTO] Level 2 → reload + restart");
 
-                            let _ = std::process::Command::new("systemctl")
-                            .arg("daemon-reload")
-                            .status();
-
-                            for action in &plan.actions {
-                                println!("[AUTO] Running: {}", action);
-                                let status_ok = std::process::Command::new("sh")
-                                .arg("-c")
-                                .arg(&action)
-                                .status()
-                                .map(|s| s.success())
-                                .unwrap_or(false);
-
-                                if !status_ok {
-                                    success = false;
-                                } else {
-                                    // Wait briefly for service to stabilize
-                                    std::thread::sleep(std::time::Duration::from_secs(2));
-
-                                    if let Some(unit) = action.split_whitespace().last() {
-                                        let is_active = std::process::Command::new("systemctl")
-                                        .arg("is-active")
-                                        .arg(unit)
-                                        .output()
-                                        .map(|o| {
-                                            let out = String::from_utf8_lossy(&o.stdout);
-                                            out.trim() == "active"
-                                        })
-                                        .unwrap_or(false);
-
-                                        if !is_active {
-                                            success = false;
-                                        }
-                                    }
-                                }
-                            }
+                            let _ = RepairAction::DaemonReload.execute(runner);
                         }
 
                         3 => {
@@ -314,7 +572,15 @@ fn main() {
                                 "[AUTO] Level 3 reached for {} → switching to AssistedRepair",
                                 key
                             );
-                            healing_level = HealingLevel::AssistedRepair;
+                            status::set_healing_level(
+                                HealingLevel::AssistedRepair,
+                                "voxlinuxd",
+                                Some(format!("escalation level 3 reached for {}", key)),
+                            );
+                            events::publish(Event::HealingBlocked {
+                                plan_id: Some(plan.id.clone()),
+                                reason: "escalation level 3 reached → assisted repair only".into(),
+                            });
                             continue;
                         }
 
@@ -324,29 +590,63 @@ fn main() {
                                 key
                             );
 
-                            let _ = std::process::Command::new("systemctl")
-                            .arg("disable")
-                            .arg(&plan.actions[0]
-                            .split_whitespace()
-                            .last()
-                            .unwrap_or(""))
-                            .status();
+                            if let Some(unit) = plan.actions.iter().find_map(|a| a.unit()) {
+                                let quarantine = RepairAction::DisableUnit {
+                                    unit: unit.to_string(),
+                                };
+
+                                let result = quarantine.execute(runner);
+                                report_action(plan, &quarantine, &result);
+
+                                if result.is_err() {
+                                    println!("[AUTO] Quarantine of {} failed", unit);
+                                }
+                            }
                             continue;
                         }
-                    }
+                    };
+
+                    // Runs the actions as a transaction, then watches the
+                    // plan's targets for the stability window off the main loop
+                    let plan_id = plan.id.clone();
 
-                    if success {
-                        println!("[AUTO] Plan={} succeeded", plan.id);
-                        state::reset_level(&key);
-                        state::bump_confidence(&key);
-                    } else {
-                        println!("[AUTO] Plan={} failed → escalating", plan.id);
-                        state::escalate_level(&key);
-                        state::drop_confidence(&key);
+                    let applied = repair_executor::apply_plan_then(stored, false, "voxlinuxd", runner, store, move |success| {
+                        finish_autonomous(&plan_id, &key, success)
+                    });
+
+                    if let Err(e) = applied {
+                        println!("[AUTO] Plan={} not applied: {}", plan.id, e);
                     }
                 }
             }
 
-            thread::sleep(Duration::from_secs(60));
+            let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+            status::record_scan(failure_class, allowed, plan_count, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn event_burst_is_capped() {
+        let (tx, rx) = mpsc::channel();
+
+        for _ in 0..EVENT_BURST_MAX + 10 {
+            tx.send(UnitEvent::EnteredFailed { unit: "flappy.service".into() }).unwrap();
+        }
+
+        let burst = next_event_burst(&rx, Duration::ZERO).unwrap();
+        assert_eq!(burst.len(), EVENT_BURST_MAX);
+
+        // The rest is the start of the next burst
+        drop(tx);
+        assert_eq!(next_event_burst(&rx, Duration::ZERO).unwrap().len(), 10);
     }
 }
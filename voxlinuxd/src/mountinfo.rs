@@ -0,0 +1,257 @@
+// mountinfo.rs
+//
+// Parser for /proc/self/mountinfo (see proc(5)). Unlike `mount` output it
+// has one line per mount with separate per-mount and superblock options,
+// so read-only state is exact, and mount points are matched by path
+// component instead of by substring.
+//
+//   36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+//   (1)(2)(3)   (4)   (5)      (6)      (7)   (8) (9)   (10)         (11)
+
+use std::fs;
+use std::path::Path;
+
+use voxlinux::paths;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    pub mount_id: u32,
+    pub parent_id: u32,
+
+    /// "major:minor" of the backing device
+    pub device: String,
+
+    /// Root of the mount within its filesystem
+    pub root: String,
+    pub mount_point: String,
+
+    /// Per-mount options ("rw", "noatime", …)
+    pub mount_options: Vec<String>,
+
+    pub fs_type: String,
+
+    /// "/dev/sda2", "tmpfs", …
+    pub source: String,
+
+    /// Superblock options; a filesystem the kernel forced read-only after
+    /// an error shows "ro" here even if the mount itself still says "rw"
+    pub super_options: Vec<String>,
+}
+
+impl MountInfo {
+    pub fn is_read_only(&self) -> bool {
+        self.mount_options.iter().any(|o| o == "ro")
+        || self.super_options.iter().any(|o| o == "ro")
+    }
+
+    /// systemd mount unit for this mount point ("/" → "-.mount", "/var/lib" → "var-lib.mount")
+    pub fn unit_name(&self) -> String {
+        let trimmed = self.mount_point.trim_matches('/');
+
+        if trimmed.is_empty() {
+            return "-.mount".to_string();
+        }
+
+        format!("{}.mount", trimmed.replace('-', "\\x2d").replace('/', "-"))
+    }
+}
+
+/// Mounts of the current mount namespace, in mount order
+pub fn read() -> Vec<MountInfo> {
+    fs::read_to_string(paths::resolve("/proc/self/mountinfo"))
+    .map(|text| parse(&text))
+    .unwrap_or_default()
+}
+
+/// Parse mountinfo text, skipping malformed lines
+pub fn parse(text: &str) -> Vec<MountInfo> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<MountInfo> {
+    let (left, right) = line.split_once(" - ")?;
+
+    let mut fields = left.split(' ');
+    let mount_id = fields.next()?.parse().ok()?;
+    let parent_id = fields.next()?.parse().ok()?;
+    let device = fields.next()?.to_string();
+    let root = unescape(fields.next()?);
+    let mount_point = unescape(fields.next()?);
+    let mount_options = split_options(fields.next()?);
+    // remaining fields of the left half are optional tags (shared:N, master:N)
+
+    let mut fields = right.split(' ');
+    let fs_type = fields.next()?.to_string();
+    let source = unescape(fields.next()?);
+    let super_options = split_options(fields.next().unwrap_or(""));
+
+    Some(MountInfo {
+        mount_id,
+        parent_id,
+        device,
+        root,
+        mount_point,
+        mount_options,
+        fs_type,
+        source,
+        super_options,
+    })
+}
+
+fn split_options(field: &str) -> Vec<String> {
+    field
+    .split(',')
+    .filter(|o| !o.is_empty())
+    .map(|o| o.to_string())
+    .collect()
+}
+
+/// Undo the kernel's octal escapes (\040 space, \011 tab, \012 newline, \134 backslash)
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4).filter(|d| d.iter().all(|b| (b'0'..=b'7').contains(b)));
+
+        if let (b'\\', Some(digits)) = (bytes[i], octal) {
+            let value = digits.iter().fold(0u32, |acc, d| acc * 8 + (d - b'0') as u32);
+            out.push(value as u8);
+            i += 4;
+            continue;
+        }
+
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The mount that holds `path`: the deepest mount point that is a
+/// component-wise prefix of it, the last one mounted if stacked
+pub fn mount_for<'a>(mounts: &'a [MountInfo], path: &str) -> Option<&'a MountInfo> {
+    let path = Path::new(path);
+
+    mounts
+    .iter()
+    .filter(|m| path.starts_with(&m.mount_point))
+    .enumerate()
+    .max_by_key(|(order, m)| (Path::new(&m.mount_point).components().count(), *order))
+    .map(|(_, m)| m)
+}
+
+/// Filesystems that are read-only by design
+const READ_ONLY_TYPES: &[&str] = &["squashfs", "iso9660", "erofs", "udf", "cramfs"];
+
+/// Mounts that are read-only although nothing asked for it: the mount point
+/// is one of `watched` or in /etc/fstab, fstab does not say "ro", and the
+/// filesystem is a writable kind
+pub fn unexpected_read_only(mounts: &[MountInfo], watched: &[String]) -> Vec<MountInfo> {
+    let fstab = fs::read_to_string(paths::resolve("/etc/fstab")).unwrap_or_default();
+
+    mounts
+    .iter()
+    .filter(|m| m.is_read_only())
+    .filter(|m| m.source.starts_with("/dev/"))
+    .filter(|m| !READ_ONLY_TYPES.contains(&m.fs_type.as_str()))
+    .filter(|m| {
+        let options = fstab_options(&fstab, &m.mount_point);
+        let wanted_ro = options.as_ref().map(|o| o.iter().any(|x| x == "ro")).unwrap_or(false);
+
+        !wanted_ro && (options.is_some() || watched.contains(&m.mount_point))
+    })
+    .cloned()
+    .collect()
+}
+
+/// Options of the fstab entry for `mount_point`, if there is one
+fn fstab_options(fstab: &str, mount_point: &str) -> Option<Vec<String>> {
+    fstab
+    .lines()
+    .map(str::trim)
+    .filter(|l| !l.is_empty() && !l.starts_with('#'))
+    .find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let _spec = fields.next()?;
+        let file = unescape(fields.next()?);
+        let _vfstype = fields.next()?;
+        let options = fields.next().unwrap_or("defaults");
+
+        (file == mount_point).then(|| split_options(options))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 0:21 / /proc rw,nosuid,nodev,noexec,relatime shared:5 - proc proc rw
+25 1 8:2 / / rw,relatime shared:1 - ext4 /dev/sda2 ro,errors=remount-ro
+40 25 8:3 / /var rw,noatime shared:30 master:2 - xfs /dev/sda3 rw,attr2,inode64
+41 25 8:4 / /mnt/USB\\040Stick rw - vfat /dev/sdb1 rw,fmask=0022
+broken line without separator
+42 25 7:0 / /snap/core/1 ro,nodev,relatime shared:31 - squashfs /dev/loop0 ro
+";
+
+    #[test]
+    fn parses_fields_and_skips_malformed_lines() {
+        let mounts = parse(MOUNTINFO);
+        assert_eq!(mounts.len(), 5);
+
+        let root = &mounts[1];
+        assert_eq!(root.mount_id, 25);
+        assert_eq!(root.device, "8:2");
+        assert_eq!(root.fs_type, "ext4");
+        assert_eq!(root.source, "/dev/sda2");
+        assert_eq!(root.super_options, ["ro", "errors=remount-ro"]);
+        assert!(root.is_read_only());
+
+        // Optional tags do not shift the fields after the separator
+        assert_eq!(mounts[2].fs_type, "xfs");
+        assert!(!mounts[2].is_read_only());
+    }
+
+    #[test]
+    fn unescapes_mount_points() {
+        let mounts = parse(MOUNTINFO);
+
+        assert_eq!(mounts[3].mount_point, "/mnt/USB Stick");
+        assert_eq!(unescape("a\\011b\\134c"), "a\tb\\c");
+        assert_eq!(unescape("trailing\\04"), "trailing\\04");
+    }
+
+    #[test]
+    fn unit_names() {
+        let mounts = parse(MOUNTINFO);
+
+        assert_eq!(mounts[1].unit_name(), "-.mount");
+        assert_eq!(mounts[2].unit_name(), "var.mount");
+        assert_eq!(mounts[4].unit_name(), "snap-core-1.mount");
+    }
+
+    #[test]
+    fn deepest_mount_holds_path() {
+        let mounts = parse(MOUNTINFO);
+
+        assert_eq!(mount_for(&mounts, "/var/log/journal").unwrap().mount_point, "/var");
+        assert_eq!(mount_for(&mounts, "/variant").unwrap().mount_point, "/");
+    }
+
+    #[test]
+    fn fstab_entries() {
+        let fstab = "\
+# <file system> <dir> <type> <options> <dump> <pass>
+UUID=1234  /      ext4  defaults,noatime  0 1
+/dev/sda3  /var   xfs   ro                0 2
+/dev/sdb1  /mnt/USB\\040Stick vfat
+";
+
+        assert_eq!(fstab_options(fstab, "/").unwrap(), ["defaults", "noatime"]);
+        assert_eq!(fstab_options(fstab, "/var").unwrap(), ["ro"]);
+        assert_eq!(fstab_options(fstab, "/mnt/USB Stick").unwrap(), ["defaults"]);
+        assert_eq!(fstab_options(fstab, "/home"), None);
+    }
+}
@@ -0,0 +1,273 @@
+// network.rs
+//
+// Layered network reachability. Each layer is checked only as far as it is
+// configured, so a machine on a LAN without internet access is not reported
+// as "network broken" just because a public address does not answer:
+//
+//   link      an interface other than lo is up
+//   route     a default route exists (and its gateway answers a ping)
+//   dns       a nameserver is configured (and the configured names resolve)
+//   upstream  the configured HTTP endpoint / hosts answer, and
+//             NetworkManager reports full connectivity
+//
+// Files are read under the root prefix; programs run through the CommandRunner.
+
+use std::fmt;
+use std::fs;
+use std::net::Ipv4Addr;
+
+use crate::config;
+use voxlinux::command_runner::CommandRunner;
+use voxlinux::paths;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NetworkLayer {
+    Link,
+    Route,
+    Dns,
+    Upstream,
+}
+
+impl fmt::Display for NetworkLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NetworkLayer::Link => "link",
+            NetworkLayer::Route => "route",
+            NetworkLayer::Dns => "dns",
+            NetworkLayer::Upstream => "upstream",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone)]
+pub struct LayerCheck {
+    pub layer: NetworkLayer,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NetworkReport {
+    pub checks: Vec<LayerCheck>,
+
+    /// NetworkManager connectivity ("full", "limited", "portal", "none", …), if queried
+    pub nm_connectivity: Option<String>,
+}
+
+impl NetworkReport {
+    pub fn ok(&self) -> bool {
+        self.checks.iter().all(|c| c.ok)
+    }
+
+    /// Lowest layer with a failed check; everything above it is unreliable
+    pub fn failed_layer(&self) -> Option<NetworkLayer> {
+        self.checks.iter().filter(|c| !c.ok).map(|c| c.layer).min()
+    }
+
+    /// Failed checks of `layer`, as "detail; detail"
+    pub fn failures(&self, layer: NetworkLayer) -> String {
+        self.checks
+        .iter()
+        .filter(|c| c.layer == layer && !c.ok)
+        .map(|c| c.detail.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
+    }
+}
+
+pub fn probe(runner: &dyn CommandRunner) -> NetworkReport {
+    let settings = &config::get().network;
+    let timeout = settings.timeout_secs.to_string();
+    let mut report = NetworkReport::default();
+
+    // ─────────────────────────────
+    // Link
+    // ─────────────────────────────
+    let up = interfaces_up();
+
+    report.checks.push(LayerCheck {
+        layer: NetworkLayer::Link,
+        ok: !up.is_empty(),
+        detail: if up.is_empty() {
+            "no network interface is up".into()
+        } else {
+            format!("up: {}", up.join(", "))
+        },
+    });
+
+    // ─────────────────────────────
+    // Route
+    // ─────────────────────────────
+    match default_gateway() {
+        None => report.checks.push(LayerCheck {
+            layer: NetworkLayer::Route,
+            ok: false,
+            detail: "no default route".into(),
+        }),
+
+        Some(gateway) => {
+            let detail = format!("default gateway {}", gateway);
+
+            let ok = !settings.check_gateway
+            || gateway.is_unspecified()
+            || ping(runner, &gateway.to_string(), &timeout);
+
+            report.checks.push(LayerCheck {
+                layer: NetworkLayer::Route,
+                ok,
+                detail: if ok { detail } else { format!("{} does not answer ping", detail) },
+            });
+        }
+    }
+
+    // ─────────────────────────────
+    // DNS
+    // ─────────────────────────────
+    let nameservers = nameservers();
+
+    report.checks.push(LayerCheck {
+        layer: NetworkLayer::Dns,
+        ok: !nameservers.is_empty(),
+        detail: if nameservers.is_empty() {
+            "no nameserver in /etc/resolv.conf".into()
+        } else {
+            format!("nameservers: {}", nameservers.join(", "))
+        },
+    });
+
+    for name in &settings.dns_names {
+        let ok = runner
+        .run("getent", &["hosts", name])
+        .map(|o| o.success())
+        .unwrap_or(false);
+
+        report.checks.push(LayerCheck {
+            layer: NetworkLayer::Dns,
+            ok,
+            detail: format!("{} {}", name, if ok { "resolves" } else { "does not resolve" }),
+        });
+    }
+
+    // ─────────────────────────────
+    // Upstream
+    // ─────────────────────────────
+    if let Some(url) = &settings.http_url {
+        let ok = runner
+        .run("curl", &["-fsS", "-o", "/dev/null", "--max-time", &timeout, "--", url])
+        .map(|o| o.success())
+        .unwrap_or(false);
+
+        report.checks.push(LayerCheck {
+            layer: NetworkLayer::Upstream,
+            ok,
+            detail: format!("{} {}", url, if ok { "answers" } else { "does not answer" }),
+        });
+    }
+
+    for host in &settings.upstream_hosts {
+        let ok = ping(runner, host, &timeout);
+
+        report.checks.push(LayerCheck {
+            layer: NetworkLayer::Upstream,
+            ok,
+            detail: format!("{} {}", host, if ok { "answers ping" } else { "does not answer ping" }),
+        });
+    }
+
+    if settings.networkmanager {
+        report.nm_connectivity = nm_connectivity(runner);
+
+        if let Some(state) = &report.nm_connectivity {
+            report.checks.push(LayerCheck {
+                layer: NetworkLayer::Upstream,
+                ok: state == "full" || state == "unknown",
+                detail: format!("NetworkManager connectivity: {}", state),
+            });
+        }
+    }
+
+    report
+}
+
+fn ping(runner: &dyn CommandRunner, host: &str, timeout: &str) -> bool {
+    runner
+    .run("ping", &["-c", "1", "-W", timeout, "--", host])
+    .map(|o| o.success())
+    .unwrap_or(false)
+}
+
+/// Interfaces other than lo whose operstate is up (or unknown with carrier,
+/// as tun/ppp devices report)
+fn interfaces_up() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(paths::resolve("/sys/class/net")) else {
+        return Vec::new();
+    };
+
+    let mut up: Vec<String> = entries
+    .flatten()
+    .filter(|e| e.file_name() != "lo")
+    .filter(|e| {
+        let read = |name: &str| {
+            fs::read_to_string(e.path().join(name))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+        };
+
+        match read("operstate").as_str() {
+            "up" => true,
+            "unknown" => read("carrier") == "1",
+            _ => false,
+        }
+    })
+    .map(|e| e.file_name().to_string_lossy().into_owned())
+    .collect();
+
+    up.sort();
+    up
+}
+
+/// Gateway of the IPv4 default route from /proc/net/route
+fn default_gateway() -> Option<Ipv4Addr> {
+    let table = fs::read_to_string(paths::resolve("/proc/net/route")).ok()?;
+
+    table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        if fields.get(1) != Some(&"00000000") {
+            return None;
+        }
+
+        // Hex of the address in network byte order, read as a little-endian word
+        let raw = u32::from_str_radix(fields.get(2)?, 16).ok()?;
+        Some(Ipv4Addr::from(raw.to_le_bytes()))
+    })
+}
+
+fn nameservers() -> Vec<String> {
+    fs::read_to_string(paths::resolve("/etc/resolv.conf"))
+    .unwrap_or_default()
+    .lines()
+    .filter_map(|line| {
+        let mut fields = line.split_whitespace();
+
+        match fields.next() {
+            Some("nameserver") => fields.next().map(|s| s.to_string()),
+            _ => None,
+        }
+    })
+    .collect()
+}
+
+/// `nmcli networking connectivity`; None when NetworkManager is not running
+fn nm_connectivity(runner: &dyn CommandRunner) -> Option<String> {
+    let output = runner.run("nmcli", &["networking", "connectivity"]).ok()?;
+
+    if !output.success() {
+        return None;
+    }
+
+    Some(output.stdout.trim().to_string()).filter(|s| !s.is_empty())
+}
@@ -1,17 +1,19 @@
-use std::path::Path;
-use std::process::Command;
+use voxlinux::command_runner::CommandRunner;
+use voxlinux::paths;
 use voxlinux::repair_plan::{RepairPlan, RiskLevel};
-use voxlinux::explain::{ExplainBlock, ExplainCategory};
+use voxlinux::system_state::SystemState;
+use voxlinux::repair_action::RepairAction;
+use voxlinux::explain::ExplainCategory;
 
 pub fn pacman_broken() -> bool {
-    Path::new("/var/lib/pacman/db.lck").exists()
+    paths::pacman_lock().exists()
 }
 
-pub fn heal() -> String {
-    let _ = Command::new("rm")
-    .arg("-f")
-    .arg("/var/lib/pacman/db.lck")
-    .status();
+pub fn heal(runner: &dyn CommandRunner) -> String {
+    let _ = RepairAction::RemoveFile {
+        path: paths::pacman_lock().to_string_lossy().into_owned(),
+    }
+    .execute(runner);
 
     "removed stale pacman lock".to_string()
 }
@@ -32,38 +34,31 @@ pub fn pacman_lock_repair_plan(
         return None;
     }
 
-    let mut plan = RepairPlan {
-        id: "pacman-lock".into(), // or your existing ID logic
-        issue: "pacman database lock detected".into(),
-        risk: RiskLevel::Medium,
-        confidence_high: false, // or your logic
-        reversible: true,
-        requires_reboot: false,
-        actions: vec![
-            "rm -f /var/lib/pacman/db.lck".into(),
-            "pacman -Sy".into(),
-        ],
-        explain: vec![
-            ExplainBlock {
-                level: 1,
-                category: ExplainCategory::WhatHappened,
-                content: "A previous pacman process exited unexpectedly, leaving a stale lock file.".into(),
-            },
-            ExplainBlock {
-                level: 2,
-                category: ExplainCategory::WhySafe,
-                content: "No pacman process is currently running. Only the lock file will be removed.".into(),
-            },
-            ExplainBlock {
-                level: 3,
-                category: ExplainCategory::WhatWillExecute,
-                content: "rm -f /var/lib/pacman/db.lck\npacman -Sy".into(),
+    let plan = RepairPlan::new(
+        "pacman database lock detected",
+        "pacman-lock",
+        RiskLevel::Medium,
+        vec![
+            RepairAction::RemoveFile {
+                path: paths::pacman_lock().to_string_lossy().into_owned(),
             },
+            RepairAction::RunPackageSync,
         ],
-        integrity_hash: String::new(),
-    };
-
-    plan.integrity_hash = plan.compute_hash();
+    )
+    .with_reversible(false) // pacman -Sy replaces the sync databases
+    .with_explain(
+        1,
+        ExplainCategory::WhatHappened,
+        "A previous pacman process exited unexpectedly, leaving a stale lock file.",
+    )
+    .with_explain(
+        2,
+        ExplainCategory::WhySafe,
+        "No pacman process is currently running. Only the lock file will be removed.",
+    )
+    .with_explain(3, ExplainCategory::WhatWillExecute, "rm -f /var/lib/pacman/db.lck\npacman -Sy")
+    .with_verify(SystemState::PackageConsistent)
+    .sealed();
 
     Some(plan)
 }
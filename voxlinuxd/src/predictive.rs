@@ -2,16 +2,27 @@
 //
 // Predictive self-healing layer for VoxLinux
 // -----------------------------------------
-// This module detects early warning signals (drift)
-// and triggers EXISTING self-healing logic in systemd.rs.
+// This module detects early warning signals (drift) in the restart
+// history of watched services and turns them into preventive repair
+// plans, before systemd gives up on the service.
 //
 // It never executes systemctl directly.
+//
+// Every scan samples NRestarts of each watched service. The drift score is
+// 10 per automatic restart inside the sliding window (`predictive.window_secs`),
+// so a single burst and a slow trickle are scored the same way.
 
 use voxlinux::explain;
+use voxlinux::command_runner::CommandRunner;
+use voxlinux::repair_plan::RepairPlan;
+use crate::config;
+use crate::core::confidence::Confidence;
+use crate::core::evidence;
+use crate::core::reporter::ObserverReport;
+use crate::core::repair_builder::build_preventive_plan;
 use crate::state;
-use crate::systemd;
-use crate::system_state::SystemState;
-//use crate::healer;
+use crate::systemd_backend::SystemdBackend;
+
 /// Why a healing action was triggered
 #[derive(Debug)]
 pub enum TriggerReason {
@@ -19,35 +30,48 @@ pub enum TriggerReason {
 }
 
 /// Represents detected drift
-struct DriftResult {
-    service: String,
-    score: u32,
+#[derive(Debug, Clone)]
+pub struct DriftResult {
+    pub service: String,
+    pub score: u32,
+
+    /// Automatic restarts inside the window
+    pub restarts: u32,
+
+    /// Seconds between the oldest and newest sample in the window
+    pub observed_secs: u64,
 }
 
-/// Entry point called from main daemon loop
-pub fn observe() {
-    let drifts = detect_systemd_drift();
+/// Entry point called from main daemon loop.
+/// Samples every watched service and returns preventive plans for those
+/// whose drift score reached the threshold.
+pub fn observe(
+    report: &ObserverReport,
+    runner: &dyn CommandRunner,
+    systemd: &dyn SystemdBackend,
+) -> Vec<RepairPlan> {
+    let threshold = config::get().predictive.score_threshold;
+    let mut plans = Vec::new();
+
+    for drift in detect_systemd_drift(systemd) {
+        if drift.score < threshold {
+            continue;
+        }
 
-    for drift in drifts {
-        // Conservative threshold (safe default)
-        if drift.score >= 15 {
-            trigger_preventive_heal(&drift);
+        if let Some(plan) = trigger_preventive_heal(&drift, report, runner, systemd) {
+            plans.push(plan);
         }
     }
+
+    plans
 }
 
-/// Detect restart-pattern drift in selected services
-fn detect_systemd_drift() -> Vec<DriftResult> {
+/// Detect restart-pattern drift in the configured services
+fn detect_systemd_drift(systemd: &dyn SystemdBackend) -> Vec<DriftResult> {
     let mut results = Vec::new();
 
-    // Start with non-critical services only
-    let services = [
-        "NetworkManager.service",
-        "sshd.service",
-    ];
-
-    for service in services {
-        if let Some(drift) = check_restart_trend(service) {
+    for service in &config::get().predictive.watched {
+        if let Some(drift) = check_restart_trend(service, systemd) {
             results.push(drift);
         }
     }
@@ -55,44 +79,53 @@ fn detect_systemd_drift() -> Vec<DriftResult> {
     results
 }
 
-/// Compare current restart count with previous value
-fn check_restart_trend(service: &str) -> Option<DriftResult> {
-    let current = systemd::get_restart_count(service)?;
+/// Score the restarts of `service` inside the sliding window
+fn check_restart_trend(service: &str, systemd: &dyn SystemdBackend) -> Option<DriftResult> {
+    let current = systemd.restart_count(service).ok()?;
+    let window = config::get().predictive.window_secs;
 
-    let previous = state::get_last_restart_count(service)
-        .unwrap_or(current);
+    let samples = state::record_restart_sample(service, current, window);
 
-    // Persist for next observation cycle
-    state::set_last_restart_count(service, current);
+    let first = samples.first()?;
+    let last = samples.last()?;
 
-    let delta = current.saturating_sub(previous);
+    // Samples within the window never decrease (a reset clears them)
+    let restarts = last.count - first.count;
 
-    if delta == 0 {
+    if restarts == 0 {
         return None;
     }
 
     Some(DriftResult {
         service: service.to_string(),
-        score: delta * 10, // simple, explainable heuristic
+        score: restarts.saturating_mul(10), // simple, explainable heuristic
+        restarts,
+        observed_secs: last.at - first.at,
     })
 }
 
-/// Call existing self-healing logic early
-
-fn trigger_preventive_heal(drift: &DriftResult) {
+/// Build a preventive plan for the drifting service.
+/// It is rebuilt (and updated in place) while the window still holds the restarts.
+fn trigger_preventive_heal(
+    drift: &DriftResult,
+    report: &ObserverReport,
+    runner: &dyn CommandRunner,
+    systemd: &dyn SystemdBackend,
+) -> Option<RepairPlan> {
     explain::note(format!(
         "Predictive alert: restart trend detected for '{}' (drift score = {})",
         drift.service,
         drift.score
     ));
 
-    //healer::heal(
-        //&SystemState::ServiceActive(drift.service.clone())
-    //);
+    let evidence = evidence::collect(&drift.service, runner, systemd);
+    let plan = build_preventive_plan(drift, report.confidence == Confidence::High, Some(&evidence))?;
 
     explain::note(format!(
-        "Predictive healing triggered for '{}'",
+        "Predictive plan {} created for '{}'",
+        plan.id,
         drift.service
     ));
-}
 
+    Some(plan)
+}
@@ -0,0 +1,320 @@
+// pressure.rs
+//
+// Resource pressure from the kernel's PSI interface (/proc/pressure/*) and
+// swap usage from /proc/meminfo.
+//
+// PSI reports the share of wall time in which some (or all) runnable tasks
+// were stalled on a resource, averaged over 10s, 60s and 300s. A stall only
+// counts once both the 60s and the 300s average are above the threshold, so
+// a short burst (a compile, a backup) does not degrade the system opinion.
+//
+// All files are read under the root prefix (`voxlinux::paths`), so the
+// assessor runs unchanged against a fixture tree.
+
+use std::cmp::Reverse;
+use std::fs;
+use std::path::Path;
+
+use crate::config;
+use crate::core::opinion::Opinion;
+use crate::probe::format_bytes;
+use voxlinux::paths;
+
+/// One "some" or "full" line of a PSI file (percentages)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PsiAverages {
+    pub avg10: f64,
+    pub avg60: f64,
+    pub avg300: f64,
+}
+
+impl PsiAverages {
+    /// Both the 1 and the 5 minute average at or above `threshold`
+    pub fn sustained(&self, threshold: f64) -> bool {
+        self.avg60 >= threshold && self.avg300 >= threshold
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Psi {
+    pub some: PsiAverages,
+
+    /// Absent in /proc/pressure/cpu before Linux 5.13
+    pub full: Option<PsiAverages>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SwapUsage {
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+}
+
+impl SwapUsage {
+    pub fn used_percent(&self) -> u8 {
+        if self.total_bytes == 0 {
+            return 0;
+        }
+
+        ((self.total_bytes - self.free_bytes) * 100 / self.total_bytes) as u8
+    }
+}
+
+/// Snapshot of every pressure source; `None` where the kernel has no data
+#[derive(Debug, Clone, Default)]
+pub struct PressureReport {
+    pub memory: Option<Psi>,
+    pub cpu: Option<Psi>,
+    pub io: Option<Psi>,
+    pub swap: Option<SwapUsage>,
+}
+
+pub fn collect() -> PressureReport {
+    PressureReport {
+        memory: read_psi("memory"),
+        cpu: read_psi("cpu"),
+        io: read_psi("io"),
+        swap: read_swap(&paths::resolve("/proc/meminfo")),
+    }
+}
+
+fn read_psi(resource: &str) -> Option<Psi> {
+    let path = paths::resolve(&format!("/proc/pressure/{}", resource));
+    parse_psi(&fs::read_to_string(path).ok()?)
+}
+
+/// Parse a PSI file:
+///
+///     some avg10=0.00 avg60=1.52 avg300=0.84 total=1234567
+///     full avg10=0.00 avg60=0.31 avg300=0.12 total=234567
+pub fn parse_psi(text: &str) -> Option<Psi> {
+    let mut some = None;
+    let mut full = None;
+
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+
+        let kind = fields.next();
+        let mut averages = PsiAverages::default();
+
+        for field in fields {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+
+            let Ok(value) = value.parse::<f64>() else {
+                continue;
+            };
+
+            match key {
+                "avg10" => averages.avg10 = value,
+                "avg60" => averages.avg60 = value,
+                "avg300" => averages.avg300 = value,
+                _ => {}
+            }
+        }
+
+        match kind {
+            Some("some") => some = Some(averages),
+            Some("full") => full = Some(averages),
+            _ => {}
+        }
+    }
+
+    Some(Psi { some: some?, full })
+}
+
+fn read_swap(path: &Path) -> Option<SwapUsage> {
+    parse_meminfo_swap(&fs::read_to_string(path).ok()?)
+}
+
+/// SwapTotal / SwapFree from /proc/meminfo
+pub fn parse_meminfo_swap(text: &str) -> Option<SwapUsage> {
+    let total = meminfo_bytes(text, "SwapTotal")?;
+    let free = meminfo_bytes(text, "SwapFree")?;
+
+    Some(SwapUsage {
+        total_bytes: total,
+        free_bytes: free.min(total),
+    })
+}
+
+/// Physical memory (MemTotal)
+pub fn mem_total_bytes() -> Option<u64> {
+    let text = fs::read_to_string(paths::resolve("/proc/meminfo")).ok()?;
+    meminfo_bytes(&text, "MemTotal")
+}
+
+/// One /proc/meminfo field; the file gives kB
+fn meminfo_bytes(text: &str, field: &str) -> Option<u64> {
+    text.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+
+        if key != field {
+            return None;
+        }
+
+        value
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()
+        .map(|kb| kb * 1024)
+    })
+}
+
+impl PressureReport {
+    /// Memory is under sustained pressure (stalls or a nearly full swap)
+    pub fn memory_stalled(&self) -> bool {
+        let t = &config::get().pressure;
+
+        self.memory.map(|m| m.some.sustained(t.some_warn_percent)).unwrap_or(false)
+        || self.swap.map(|s| s.used_percent() >= t.swap_warn_percent).unwrap_or(false)
+    }
+}
+
+/// Degraded when some tasks are stalled on a resource (or swap is filling),
+/// Broken when all tasks are (or swap is exhausted)
+pub fn assess(report: &PressureReport) -> Opinion {
+    let t = &config::get().pressure;
+
+    let resources = [("memory", report.memory), ("cpu", report.cpu), ("io", report.io)];
+
+    let mut broken = Vec::new();
+    let mut degraded = Vec::new();
+
+    for (name, psi) in resources {
+        let Some(psi) = psi else {
+            continue;
+        };
+
+        if psi.full.map(|f| f.sustained(t.full_critical_percent)).unwrap_or(false) {
+            broken.push(format!("{} full avg60={:.1}%", name, psi.full.unwrap_or_default().avg60));
+        } else if psi.some.sustained(t.some_warn_percent) {
+            degraded.push(format!("{} some avg60={:.1}%", name, psi.some.avg60));
+        }
+    }
+
+    if let Some(swap) = report.swap {
+        let used = swap.used_percent();
+        let text = format!(
+            "swap {}% used ({} free)",
+            used,
+            format_bytes(swap.free_bytes)
+        );
+
+        if swap.total_bytes > 0 && used >= t.swap_critical_percent {
+            broken.push(text);
+        } else if swap.total_bytes > 0 && used >= t.swap_warn_percent {
+            degraded.push(text);
+        }
+    }
+
+    if !broken.is_empty() {
+        return Opinion::Broken {
+            reason: format!("Sustained resource pressure: {}", broken.join(", ")),
+        };
+    }
+
+    if !degraded.is_empty() {
+        return Opinion::Degraded {
+            reason: format!("Resource pressure: {}", degraded.join(", ")),
+        };
+    }
+
+    Opinion::Ok
+}
+
+/// Services using the most memory, from their cgroup's memory.current
+pub fn top_memory_units(limit: usize) -> Vec<(String, u64)> {
+    let mut units = Vec::new();
+    collect_memory_units(&paths::cgroup_root().join("system.slice"), &mut units);
+
+    units.sort_by_key(|&(_, bytes)| Reverse(bytes));
+    units.truncate(limit);
+    units
+}
+
+fn collect_memory_units(dir: &Path, units: &mut Vec<(String, u64)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+
+        if name.ends_with(".slice") {
+            collect_memory_units(&path, units);
+        } else if name.ends_with(".service") {
+            let current = fs::read_to_string(path.join("memory.current"))
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok());
+
+            if let Some(bytes) = current {
+                units.push((name, bytes));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMORY_PSI: &str = "\
+some avg10=12.50 avg60=8.31 avg300=4.02 total=98765432
+full avg10=3.10 avg60=1.25 avg300=0.40 total=12345678
+";
+
+    #[test]
+    fn psi_some_and_full() {
+        let psi = parse_psi(MEMORY_PSI).unwrap();
+
+        assert_eq!(psi.some, PsiAverages { avg10: 12.5, avg60: 8.31, avg300: 4.02 });
+        assert_eq!(psi.full.unwrap().avg60, 1.25);
+        assert!(psi.some.sustained(4.0));
+        assert!(!psi.some.sustained(5.0));
+    }
+
+    #[test]
+    fn psi_without_full_line() {
+        // /proc/pressure/cpu before Linux 5.13
+        let psi = parse_psi("some avg10=0.00 avg60=0.52 avg300=0.10 total=4242\n").unwrap();
+
+        assert_eq!(psi.full, None);
+        assert_eq!(psi.some.avg60, 0.52);
+    }
+
+    #[test]
+    fn psi_without_some_line_is_rejected() {
+        assert_eq!(parse_psi(""), None);
+        assert_eq!(parse_psi("full avg10=1.00 avg60=1.00 avg300=1.00 total=1\n"), None);
+    }
+
+    const MEMINFO: &str = "\
+MemTotal:       16303428 kB
+MemFree:         1043212 kB
+MemAvailable:    6021932 kB
+SwapCached:        12345 kB
+SwapTotal:       8388608 kB
+SwapFree:        2097152 kB
+";
+
+    #[test]
+    fn meminfo_swap() {
+        let swap = parse_meminfo_swap(MEMINFO).unwrap();
+
+        assert_eq!(swap.total_bytes, 8_388_608 * 1024);
+        assert_eq!(swap.free_bytes, 2_097_152 * 1024);
+        assert_eq!(swap.used_percent(), 75);
+        assert_eq!(meminfo_bytes(MEMINFO, "MemTotal"), Some(16_303_428 * 1024));
+    }
+
+    #[test]
+    fn meminfo_without_swap() {
+        assert_eq!(parse_meminfo_swap("MemTotal: 1024 kB\n"), None);
+
+        let none = parse_meminfo_swap("SwapTotal: 0 kB\nSwapFree: 0 kB\n").unwrap();
+        assert_eq!(none.used_percent(), 0);
+    }
+}
@@ -1,11 +1,18 @@
-use crate::{pacman, systemd};
-use crate::system_state::SystemState;
-use std::process::{Command, Stdio};
+use crate::{config, mountinfo, network, pacman, systemd};
+use voxlinux::system_state::SystemState;
+use voxlinux::command_runner::CommandRunner;
+use voxlinux::paths;
 use crate::state::BootContext;
+use crate::systemd_backend::{backend, SystemdBackend};
+
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
 
 
 /// Detect which system states are currently broken
-pub fn detect_broken_states() -> Vec<SystemState> {
+pub fn detect_broken_states(runner: &dyn CommandRunner) -> Vec<SystemState> {
     let mut broken = Vec::new();
 
     // ─────────────────────────────
@@ -25,99 +32,471 @@ pub fn detect_broken_states() -> Vec<SystemState> {
     // ─────────────────────────────
     // Network reachability
     // ─────────────────────────────
-    if !network_ok() {
+    if !network_ok(runner) {
         broken.push(SystemState::NetworkReachable);
     }
 
     // ─────────────────────────────
-    // Filesystem writable (root)
+    // Filesystem writable (root + unexpected read-only mounts)
     // ─────────────────────────────
     if !filesystem_rw("/") {
         broken.push(SystemState::FilesystemWritable("/".into()));
     }
 
+    for mount in unexpected_read_only() {
+        let state = SystemState::FilesystemWritable(mount.mount_point);
+
+        if !broken.contains(&state) {
+            broken.push(state);
+        }
+    }
+
     broken
 }
 
 
 
 pub fn detect_boot_context() -> BootContext {
-    // Check active systemd target
-    let output = Command::new("systemctl")
-    .arg("get-default")
-    .output();
-
-    if let Ok(out) = output {
-        let target = String::from_utf8_lossy(&out.stdout);
+    let systemd = backend();
 
-        if target.contains("graphical.target") {
-            return BootContext::Graphical;
-        }
-        if target.contains("multi-user.target") {
-            return BootContext::MultiUser;
-        }
-        if target.contains("rescue.target") {
-            return BootContext::Rescue;
+    // Check active systemd target
+    if let Ok(target) = systemd.default_target() {
+        match target.as_str() {
+            "graphical.target" => return BootContext::Graphical,
+            "multi-user.target" => return BootContext::MultiUser,
+            "rescue.target" => return BootContext::Rescue,
+            _ => {}
         }
     }
 
     // Fallback: check if system is fully up
-    let state_output = Command::new("systemctl")
-    .arg("is-system-running")
-    .output();
+    if let Ok(state) = systemd.system_state() {
+        match state.as_str() {
+            "running" => return BootContext::MultiUser,
+            "initializing" | "starting" => return BootContext::EarlyBoot,
+            _ => {}
+        }
+    }
+
+    BootContext::Unknown
+}
+
+/// Check if network is usable on every configured layer (see `network`)
+pub fn network_ok(runner: &dyn CommandRunner) -> bool {
+    network::probe(runner).ok()
+}
 
-    if let Ok(out) = state_output {
-        let status = String::from_utf8_lossy(&out.stdout);
+/// Check if the mount holding `path` is read-write
+pub fn filesystem_rw(path: &str) -> bool {
+    let mounts = mountinfo::read();
+
+    mountinfo::mount_for(&mounts, path)
+    .map(|m| !m.is_read_only())
+    .unwrap_or(false)
+}
+
+/// Watched or fstab mounts that are read-only without being configured so
+pub fn unexpected_read_only() -> Vec<mountinfo::MountInfo> {
+    mountinfo::unexpected_read_only(&mountinfo::read(), &config::get().disk.paths)
+}
+
+
+// ─────────────────────────────
+// Disk space
+// ─────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiskPressure {
+    Ok,
+    Warning,
+    Critical,
+}
+
+/// statvfs() of one mount point
+#[derive(Debug, Clone)]
+pub struct DiskUsage {
+    /// Mount point as configured (without the root prefix)
+    pub mount: String,
+
+    /// st_dev of the mount point, to tell which files live on it
+    pub device: u64,
+
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+
+    /// Space available to unprivileged users
+    pub avail_bytes: u64,
+
+    /// Zero on filesystems without a fixed inode table (btrfs)
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+}
+
+impl DiskUsage {
+    /// Used space as `df` shows it (reserved blocks count as unavailable)
+    pub fn used_percent(&self) -> u8 {
+        percent(self.used_bytes, self.used_bytes + self.avail_bytes)
+    }
 
-        if status.contains("running") {
-            return BootContext::MultiUser;
+    pub fn inode_percent(&self) -> u8 {
+        percent(self.total_inodes - self.free_inodes, self.total_inodes)
+    }
+
+    pub fn pressure(&self) -> DiskPressure {
+        let disk = &config::get().disk;
+
+        let space = level(self.used_percent(), disk.warn_percent, disk.critical_percent);
+        let inodes = level(self.inode_percent(), disk.inode_warn_percent, disk.inode_critical_percent);
+
+        space.max(inodes)
+    }
+
+    /// "/var: 96% used, 1.2 GiB of 30 GiB free, 40% inodes"
+    pub fn describe(&self) -> String {
+        format!(
+            "{}: {}% used, {} of {} free, {}% inodes",
+            self.mount,
+            self.used_percent(),
+            format_bytes(self.avail_bytes),
+            format_bytes(self.total_bytes),
+            self.inode_percent()
+        )
+    }
+}
+
+fn percent(part: u64, whole: u64) -> u8 {
+    if whole == 0 {
+        return 0;
+    }
+
+    (part.saturating_mul(100).div_ceil(whole)).min(100) as u8
+}
+
+fn level(value: u8, warn: u8, critical: u8) -> DiskPressure {
+    if value >= critical {
+        DiskPressure::Critical
+    } else if value >= warn {
+        DiskPressure::Warning
+    } else {
+        DiskPressure::Ok
+    }
+}
+
+pub fn disk_usage(mount: &str) -> Option<DiskUsage> {
+    let path = paths::resolve(mount);
+    let device = std::fs::metadata(&path).ok()?.dev();
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+
+    Some(DiskUsage::from_statvfs(mount, device, &st))
+}
+
+impl DiskUsage {
+    /// Sizes from a statvfs() result; blocks are counted in f_frsize units.
+    /// The fields are u32 on 32-bit targets, hence `u64::from`.
+    #[allow(clippy::useless_conversion)]
+    fn from_statvfs(mount: &str, device: u64, st: &libc::statvfs) -> Self {
+        let frsize = u64::from(st.f_frsize);
+        let blocks = u64::from(st.f_blocks);
+        let files = u64::from(st.f_files);
+
+        DiskUsage {
+            mount: mount.to_string(),
+            device,
+            total_bytes: blocks * frsize,
+            used_bytes: blocks.saturating_sub(u64::from(st.f_bfree)) * frsize,
+            avail_bytes: u64::from(st.f_bavail) * frsize,
+            total_inodes: files,
+            free_inodes: u64::from(st.f_ffree).min(files),
         }
-        if status.contains("starting") {
-            return BootContext::EarlyBoot;
+    }
+}
+
+/// Usage of every configured mount point that exists, once per filesystem
+/// (a /var that is part of / is reported as /)
+pub fn check_disks() -> Vec<DiskUsage> {
+    let mut seen = HashSet::new();
+
+    config::get()
+    .disk
+    .paths
+    .iter()
+    .filter_map(|mount| disk_usage(mount))
+    .filter(|usage| seen.insert(usage.device))
+    .collect()
+}
+
+/// "1.2 GiB", "340 MiB", "12 KiB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 || value >= 100.0 {
+        format!("{:.0} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+// ─────────────────────────────
+// Per-unit memory (cgroup v2)
+// ─────────────────────────────
+
+/// Memory accounting of one unit's cgroup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitMemory {
+    pub unit: String,
+    pub current: u64,
+
+    /// memory.max / memory.high; None means "max" (no limit)
+    pub max: Option<u64>,
+    pub high: Option<u64>,
+
+    /// memory.events: times the limit was hit and processes the OOM killer took
+    pub oom: u64,
+    pub oom_kill: u64,
+}
+
+/// Read the unit's cgroup; None once the unit has stopped and its cgroup is gone
+pub fn unit_memory(unit: &str, systemd: &dyn SystemdBackend) -> Option<UnitMemory> {
+    let cgroup = systemd
+    .control_group(unit)
+    .ok()
+    .filter(|c| !c.is_empty())
+    .unwrap_or_else(|| format!("/system.slice/{}", unit));
+
+    let dir = paths::cgroup_root().join(cgroup.trim_start_matches('/'));
+
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).ok();
+
+    let (oom, oom_kill) = parse_memory_events(&read("memory.events")?);
+
+    Some(UnitMemory {
+        unit: unit.to_string(),
+        current: read("memory.current")?.trim().parse().ok()?,
+        max: read("memory.max").and_then(|v| parse_memory_limit(&v)),
+        high: read("memory.high").and_then(|v| parse_memory_limit(&v)),
+        oom,
+        oom_kill,
+    })
+}
+
+/// (oom, oom_kill) from memory.events:
+///
+///     low 0
+///     high 12
+///     max 40
+///     oom 3
+///     oom_kill 3
+pub fn parse_memory_events(text: &str) -> (u64, u64) {
+    let mut oom = 0;
+    let mut oom_kill = 0;
+
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let key = fields.next();
+        let value = fields.next().and_then(|v| v.parse().ok()).unwrap_or(0);
+
+        match key {
+            Some("oom") => oom = value,
+            Some("oom_kill") => oom_kill = value,
+            _ => {}
         }
     }
 
-    BootContext::Unknown
+    (oom, oom_kill)
+}
+
+/// memory.max / memory.high value; "max" means unlimited
+fn parse_memory_limit(text: &str) -> Option<u64> {
+    text.trim().parse().ok()
 }
 
-/// Check if network is usable (ping + DNS)
-fn network_ok() -> bool {
-    let ping = Command::new("ping")
-    .args(["-c", "1", "-W", "1", "1.1.1.1"])
-    .stdout(Stdio::null())
-    .stderr(Stdio::null())
-    .status();
+/// Why the unit is believed to have been OOM-killed, if it was.
+/// Checks the cgroup counters while they exist and systemd's Result= after.
+pub fn oom_kill_cause(unit: &str, systemd: &dyn SystemdBackend) -> Option<String> {
+    if let Some(memory) = unit_memory(unit, systemd).filter(|m| m.oom_kill > 0) {
+        return Some(match memory.max {
+            Some(max) => format!(
+                "oom_kill={} in memory.events, limit memory.max={}",
+                memory.oom_kill,
+                format_bytes(max)
+            ),
+            None => format!("oom_kill={} in memory.events, no memory.max set", memory.oom_kill),
+        });
+    }
 
-    let dns = Command::new("getent")
-    .args(["hosts", "archlinux.org"])
-    .stdout(Stdio::null())
-    .stderr(Stdio::null())
-    .status();
+    let exit = systemd.service_exit(unit).ok()?;
 
-    matches!(ping, Ok(p) if p.success())
-    && matches!(dns, Ok(d) if d.success())
+    (exit.result == "oom-kill").then(|| "systemd reports Result=oom-kill".to_string())
 }
 
-/// Check if filesystem is mounted read-write
-fn filesystem_rw(path: &str) -> bool {
-    let output = Command::new("mount")
-    .stdout(Stdio::null())
-    .stderr(Stdio::null())
-    .output();
+// ─────────────────────────────
+// Boot state
+// ─────────────────────────────
 
-    if let Ok(o) = output {
-        let stdout = String::from_utf8_lossy(&o.stdout);
-        stdout.contains(path) && stdout.contains("(rw")
-    } else {
-        false
+/// Failed units of the current boot, split by when they failed
+#[derive(Debug, Clone)]
+pub struct BootSummary {
+    /// Manager state, as `systemctl is-system-running` prints it
+    pub system_state: String,
+
+    /// Failed before startup finished
+    pub failed_at_boot: Vec<String>,
+
+    /// Failed after startup finished
+    pub failed_at_runtime: Vec<String>,
+
+    /// `systemd-analyze` "Startup finished in …" line, if boot is complete
+    pub timing: Option<String>,
+}
+
+impl BootSummary {
+    /// "failed at boot: a.service, b.service; failed at runtime: c.service (Startup finished in …)"
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+
+        if !self.failed_at_boot.is_empty() {
+            parts.push(format!("failed at boot: {}", self.failed_at_boot.join(", ")));
+        }
+
+        if !self.failed_at_runtime.is_empty() {
+            parts.push(format!("failed at runtime: {}", self.failed_at_runtime.join(", ")));
+        }
+
+        if parts.is_empty() {
+            parts.push(format!("system state is {}", self.system_state));
+        }
+
+        match &self.timing {
+            Some(timing) => format!("{} ({})", parts.join("; "), timing),
+            None => parts.join("; "),
+        }
+    }
+}
+
+pub fn boot_summary(runner: &dyn CommandRunner) -> Option<BootSummary> {
+    let systemd = backend();
+
+    let system_state = systemd.system_state().ok()?;
+    let failed = systemd.failed_units().ok()?;
+    let finished = systemd.boot_finished_usec().unwrap_or(0);
+
+    let mut failed_at_boot = Vec::new();
+    let mut failed_at_runtime = Vec::new();
+
+    for unit in failed {
+        // Startup not finished yet means everything so far happened at boot
+        let at_runtime = finished > 0
+        && systemd
+        .state_change_usec(&unit.name)
+        .map(|t| t > finished)
+        .unwrap_or(false);
+
+        if at_runtime {
+            failed_at_runtime.push(unit.name);
+        } else {
+            failed_at_boot.push(unit.name);
+        }
     }
+
+    Some(BootSummary {
+        system_state,
+        failed_at_boot,
+        failed_at_runtime,
+        timing: boot_timing(runner),
+    })
 }
 
+/// First line of `systemd-analyze`; it fails while boot is still in progress
+fn boot_timing(runner: &dyn CommandRunner) -> Option<String> {
+    let output = runner.run("systemd-analyze", &["time"]).ok()?;
+
+    if !output.success() {
+        return None;
+    }
+
+    output
+    .stdout
+    .lines()
+    .next()
+    .map(|l| l.trim().to_string())
+    .filter(|l| !l.is_empty())
+}
 
-pub fn disk_full() -> bool {
-    false
+/// The boot summary when systemd reports the system as degraded
+pub fn boot_degraded(runner: &dyn CommandRunner) -> Option<BootSummary> {
+    boot_summary(runner).filter(|s| s.system_state == "degraded")
 }
 
-pub fn boot_degraded() -> bool {
-    false
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statvfs(blocks: u64, bfree: u64, bavail: u64, files: u64, ffree: u64) -> libc::statvfs {
+        let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+        st.f_frsize = 4096;
+        st.f_bsize = 4096;
+        st.f_blocks = blocks as _;
+        st.f_bfree = bfree as _;
+        st.f_bavail = bavail as _;
+        st.f_files = files as _;
+        st.f_ffree = ffree as _;
+        st
+    }
+
+    #[test]
+    fn statvfs_counts_reserved_blocks_as_used() {
+        // 40 GiB filesystem, 2 GiB free of which 512 MiB reserved for root
+        let usage = DiskUsage::from_statvfs("/var", 42, &statvfs(10_485_760, 524_288, 393_216, 2_621_440, 2_000_000));
+
+        assert_eq!(usage.total_bytes, 40 * 1024 * 1024 * 1024);
+        assert_eq!(usage.avail_bytes, 1536 * 1024 * 1024);
+        assert_eq!(usage.used_percent(), 97);
+        assert_eq!(usage.inode_percent(), 24);
+        assert_eq!(usage.pressure(), DiskPressure::Critical);
+    }
+
+    #[test]
+    fn statvfs_without_inode_table() {
+        // btrfs reports no fixed inode count
+        let usage = DiskUsage::from_statvfs("/", 1, &statvfs(1000, 900, 900, 0, 0));
+
+        assert_eq!(usage.inode_percent(), 0);
+        assert_eq!(usage.used_percent(), 10);
+        assert_eq!(usage.pressure(), DiskPressure::Ok);
+    }
+
+    #[test]
+    fn memory_events_counters() {
+        let events = "low 0\nhigh 12\nmax 40\noom 3\noom_kill 2\noom_group_kill 0\n";
+
+        assert_eq!(parse_memory_events(events), (3, 2));
+        assert_eq!(parse_memory_events(""), (0, 0));
+    }
+
+    #[test]
+    fn memory_limits() {
+        assert_eq!(parse_memory_limit("536870912\n"), Some(536_870_912));
+        assert_eq!(parse_memory_limit("max\n"), None);
+    }
+
+    #[test]
+    fn byte_formatting() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536 * 1024 * 1024), "1.5 GiB");
+        assert_eq!(format_bytes(340 * 1024 * 1024), "340 MiB");
+    }
 }
@@ -1,48 +1,19 @@
 use std::fs::{OpenOptions, create_dir_all};
 use std::io::Write;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::process::Command;
+use voxlinux::command_runner::CommandRunner;
+use voxlinux::paths;
+use voxlinux::repair_action::RepairAction;
 use voxlinux::repair_plan::{RepairPlan, RiskLevel};
-use sha2::{Sha256, Digest};
-use hex;
-use std::fs;
+use voxlinux::plan_status::PlanStatus;
+use voxlinux::plan_store::{PlanStore, StoreError};
+use voxlinux::signing::{verify_plan, SignatureStatus};
+use voxlinux::transaction::{self, Transaction};
+use voxlinux::protocol::{DaemonError, Event, ExecutionReport, HistoryOutcome};
+use crate::{events, history, keystore, verifier};
 
-fn compute_hash(plan: &RepairPlan) -> String {
-    let mut hasher = Sha256::new();
-
-    hasher.update(&plan.id);
-    hasher.update(&plan.issue);
-    hasher.update(format!("{:?}", plan.risk));
-    hasher.update(plan.confidence_high.to_string());
-    hasher.update(plan.reversible.to_string());
-    hasher.update(plan.requires_reboot.to_string());
-
-    for action in &plan.actions {
-        hasher.update(action);
-    }
-
-    for block in &plan.explain {
-        hasher.update(block.level.to_string());
-        hasher.update(format!("{:?}", block.category));
-        hasher.update(&block.content);
-    }
-
-    hex::encode(hasher.finalize())
-}
-
-fn verify_integrity(plan: &RepairPlan) -> bool {
-    let original_hash = plan.integrity_hash.clone();
-
-    let mut cloned = plan.clone();
-    cloned.integrity_hash = String::new();
-
-    let recalculated = compute_hash(&cloned);
-
-    original_hash == recalculated
-}
-
-fn log_event(message: &str) {
-    let _ = create_dir_all("/tmp/voxlinux");
+pub fn log_event(message: &str) {
+    let _ = create_dir_all(paths::log_dir());
 
     let ts = SystemTime::now()
     .duration_since(UNIX_EPOCH)
@@ -52,68 +23,333 @@ fn log_event(message: &str) {
     if let Ok(mut file) = OpenOptions::new()
         .create(true)
         .append(true)
-        .open("/tmp/voxlinux/exec.log")
+        .open(paths::exec_log())
         {
             let _ = writeln!(file, "[{}] {}", ts, message);
         }
 }
 
-pub fn apply_plan(plan: RepairPlan) {
+/// Integrity, signature and policy checks shared by apply and dry-run
+fn check_plan(plan: &RepairPlan) -> Result<(), DaemonError> {
+    if let Err(e) = plan.verify_integrity() {
+        println!("[EXECUTOR] ❌ Integrity verification failed: {}", e);
+        log_event(&format!("Integrity failure {}: {}", plan.id, e));
+        return Err(DaemonError::IntegrityFailure(e.to_string()));
+    }
 
-    println!("[EXECUTOR] Applying plan {}", plan.id);
+    let signature = verify_plan(plan, &keystore::verifying_key());
+
+    if signature != SignatureStatus::Valid {
+        println!("[EXECUTOR] ❌ Signature check failed: {}", signature.describe());
+        log_event(&format!("Signature failure {} ({:?})", plan.id, signature));
+        return Err(DaemonError::SignatureInvalid(signature.describe().into()));
+    }
 
-    if !verify_integrity(&plan) {
-        println!("[EXECUTOR] ❌ Integrity verification failed.");
-        log_event("Integrity failure");
-        return;
+    if let Some(reason) = plan.apply_blocker() {
+        println!("[EXECUTOR] Plan {} blocked: {}", plan.id, reason);
+        log_event(&format!("Blocked plan {}: {}", plan.id, reason));
+        return Err(DaemonError::PolicyBlocked(reason));
     }
 
     if plan.risk == RiskLevel::High {
         println!("[EXECUTOR] High-risk plan blocked by policy.");
         log_event("Blocked high-risk plan");
-        return;
+        return Err(DaemonError::PolicyBlocked("high-risk plans are never applied automatically".into()));
     }
 
     if !plan.confidence_high {
         println!("[EXECUTOR] Low-confidence plan blocked.");
         log_event("Blocked low-confidence plan");
-        return;
+        return Err(DaemonError::PolicyBlocked("plan confidence is not high".into()));
     }
 
-    log_event(&format!("PLAN START {}", plan.id));
+    Ok(())
+}
+
+/// Move the stored plan to `to`; the in-memory plan follows the stored
+/// one. The store checks the transition under its lock, so of two
+/// requests racing for the same plan only one gets it to Running.
+fn set_status(
+    store: &dyn PlanStore,
+    plan: &mut RepairPlan,
+    to: PlanStatus,
+    by: &str,
+    reason: Option<String>,
+) -> Result<(), DaemonError> {
+    match store.update_status(&plan.id, to, by, reason) {
+        Ok(stored) => {
+            plan.status = stored.status;
+            plan.status_history = stored.status_history;
+            log_event(&format!("STATUS {} → {} by {}", plan.id, to, by));
+            Ok(())
+        }
+        Err(e) => {
+            println!("[EXECUTOR] Failed to store plan status: {}", e);
+            log_event(&format!("STATUS {} → {} failed: {}", plan.id, to, e));
+
+            Err(match e {
+                StoreError::Transition(e) => DaemonError::PolicyBlocked(e.to_string()),
+                e => DaemonError::Internal(e.to_string()),
+            })
+        }
+    }
+}
+
+/// Plans left Running by a daemon that stopped mid-execution. Nothing
+/// will finish them, so they become Failed; a kept journal still lets
+/// the operator roll back what ran.
+pub fn fail_interrupted_plans(store: &dyn PlanStore) {
+    let running = match store.list_by_status(PlanStatus::Running) {
+        Ok(running) => running,
+        Err(e) => {
+            println!("[EXECUTOR] Cannot list running plans: {}", e);
+            return;
+        }
+    };
+
+    for mut plan in running {
+        let reason = match Transaction::load(&plan.id) {
+            Ok(Some(tx)) => format!(
+                "voxlinuxd stopped while the plan was running; {} action(s) ran and can be rolled back",
+                tx.completed.len()
+            ),
+            Ok(None) => "voxlinuxd stopped before the plan ran".to_string(),
+            Err(e) => format!("voxlinuxd stopped while the plan was running; {}", e),
+        };
+
+        println!("[EXECUTOR] Plan {} was interrupted: {}", plan.id, reason);
+
+        if set_status(store, &mut plan, PlanStatus::Failed, "voxlinuxd", Some(reason.clone())).is_ok() {
+            history::record(&plan.id, &plan.issue, HistoryOutcome::Failed { error: reason });
+        }
+    }
+}
+
+/// Watch the targets of plans `intentctl --local` ran, then move them to
+/// Succeeded or Failed like a plan the daemon applied itself
+pub fn verify_unverified_plans(store: &'static dyn PlanStore, runner: &'static dyn CommandRunner) {
+    let plans = match store.list_by_status(PlanStatus::Unverified) {
+        Ok(plans) => plans,
+        Err(e) => {
+            println!("[EXECUTOR] Cannot list unverified plans: {}", e);
+            return;
+        }
+    };
+
+    for plan in plans {
+        if verifier::is_watching(&plan.id) {
+            continue;
+        }
+
+        println!("[EXECUTOR] Verifying plan {} applied outside the daemon", plan.id);
+
+        let plan_id = plan.id.clone();
+        let targets = plan.verify.clone();
+
+        verifier::watch_in_background(&plan_id, targets, runner, move |result| {
+            finish_plan(plan, result, store);
+        });
+    }
+}
 
-    for action in plan.actions {
+/// `by` is whoever asked for the execution; it is recorded as the approver.
+/// Returns once the actions ran; the plan is verified in the background
+/// and ends Succeeded or Failed with an `ExecutionFinished` event.
+pub fn apply_plan(
+    plan: RepairPlan,
+    dry_run: bool,
+    by: &str,
+    runner: &'static dyn CommandRunner,
+    store: &'static dyn PlanStore,
+) -> Result<ExecutionReport, DaemonError> {
+    apply_plan_then(plan, dry_run, by, runner, store, |_| {})
+}
+
+/// `apply_plan`, then `done` with the outcome once a plan whose actions
+/// ran ended Succeeded (true) or Failed (false). Not called for a dry run
+/// or a plan that was refused before anything ran.
+pub fn apply_plan_then(
+    mut plan: RepairPlan,
+    dry_run: bool,
+    by: &str,
+    runner: &'static dyn CommandRunner,
+    store: &'static dyn PlanStore,
+    done: impl FnOnce(bool) + Send + 'static,
+) -> Result<ExecutionReport, DaemonError> {
+
+    println!("[EXECUTOR] {} plan {}", if dry_run { "Dry-running" } else { "Applying" }, plan.id);
+
+    if let Err(e) = check_plan(&plan) {
+        if !dry_run {
+            history::record(&plan.id, &plan.issue, HistoryOutcome::Blocked {
+                reason: e.to_string(),
+            });
+            events::publish(Event::HealingBlocked {
+                plan_id: Some(plan.id.clone()),
+                reason: e.to_string(),
+            });
+        }
+        return Err(e);
+    }
 
-        println!("[EXECUTOR] Running: {}", action);
-        log_event(&format!("EXEC {}", action));
+    let report = ExecutionReport {
+        plan_id: plan.id.clone(),
+        dry_run,
+        actions: plan.actions.clone(),
+        verifying: !dry_run && !plan.verify.is_empty(),
+    };
+
+    if dry_run {
+        for action in &plan.actions {
+            println!("[EXECUTOR] DRY RUN → {}", action);
+            log_event(&format!("DRY RUN {:?}", action));
+        }
+        return Ok(report);
+    }
+
+    // Nothing runs unless the store took both steps
+    if plan.status != PlanStatus::Approved {
+        set_status(store, &mut plan, PlanStatus::Approved, by, None)?;
+    }
 
-        let status = Command::new("sh")
-        .arg("-c")
-        .arg(&action)
-        .status();
+    set_status(store, &mut plan, PlanStatus::Running, by, None)?;
 
-        match status {
-            Ok(s) if s.success() => {
+    log_event(&format!("PLAN START {}", plan.id));
+    events::publish(Event::ExecutionStarted { plan_id: plan.id.clone() });
+
+    let plan_id = plan.id.clone();
+
+    let mut observe = |action: &RepairAction, result: &Result<(), String>| {
+        println!("[EXECUTOR] Ran: {}", action);
+        log_event(&format!("EXEC {:?}", action));
+
+        match result {
+            Ok(()) => {
                 println!("[EXECUTOR] ✔ Success");
                 log_event("RESULT success");
             }
-            _ => {
-                println!("[EXECUTOR] ✖ Failed. Aborting.");
-                log_event("RESULT failure");
-                return;
+            Err(e) => {
+                println!("[EXECUTOR] ✖ Failed ({}). Rolling back.", e);
+                log_event(&format!("RESULT failure: {}", e));
             }
         }
+
+        events::publish(Event::ActionResult {
+            plan_id: plan_id.clone(),
+            action: action.clone(),
+            error: result.clone().err(),
+        });
+    };
+
+    if let Err(failure) = transaction::run(&plan, runner, &mut observe) {
+        let summary = failure.describe();
+
+        println!("[EXECUTOR] Plan {} failed: {}", plan.id, summary);
+        log_event(&format!("ROLLBACK {}: {}", plan.id, summary));
+        history::record(&plan.id, &plan.issue, HistoryOutcome::Failed {
+            error: summary.clone(),
+        });
+        let _ = set_status(store, &mut plan, PlanStatus::Failed, "voxlinuxd", Some(summary));
+        events::publish(Event::ExecutionFinished {
+            plan_id: plan.id.clone(),
+            success: false,
+        });
+        done(false);
+        return Err(DaemonError::ExecutionFailed {
+            action: failure.action.map(|a| a.to_string()).unwrap_or_else(|| "backup".into()),
+            error: failure.error,
+        });
     }
 
     log_event(&format!("PLAN END {}", plan.id));
-    println!("[EXECUTOR] Plan executed successfully.");
 
-    let plan_path = format!("/run/voxlinux/plans/{}.json", plan.id);
+    // The plan stays Running until its targets held for the window
+    let targets = plan.verify.clone();
 
+    verifier::watch_in_background(&plan_id, targets, runner, move |result| {
+        done(finish_plan(plan, result, store))
+    });
 
+    Ok(report)
+}
 
-    match std::fs::remove_file(&plan_path) {
-        Ok(_) => println!("[EXECUTOR] Removed plan file {}", plan.id),
-        Err(e) => println!("[EXECUTOR] Failed to remove plan file: {}", e),
+/// Record the verification result of a plan whose actions all ran;
+/// returns whether it held
+fn finish_plan(mut plan: RepairPlan, result: Result<(), String>, store: &dyn PlanStore) -> bool {
+    let success = result.is_ok();
+
+    match result {
+        Ok(()) => {
+            println!("[EXECUTOR] Plan {} executed successfully.", plan.id);
+            history::record(&plan.id, &plan.issue, HistoryOutcome::Succeeded);
+            let _ = set_status(store, &mut plan, PlanStatus::Succeeded, "voxlinuxd", None);
+        }
+        Err(e) => {
+            println!("[EXECUTOR] ✖ Verification of {} failed: {}", plan.id, e);
+            log_event(&format!("VERIFY failure {}: {}", plan.id, e));
+            history::record(&plan.id, &plan.issue, HistoryOutcome::Failed {
+                error: format!("verification: {}", e),
+            });
+            let _ = set_status(store, &mut plan, PlanStatus::Failed, "voxlinuxd", Some(e));
+        }
+    }
+
+    events::publish(Event::ExecutionFinished {
+        plan_id: plan.id.clone(),
+        success,
+    });
+
+    success
+}
+
+/// Undo a plan that ran, newest action first. Only plans whose kept
+/// transaction holds nothing irreversible can be rolled back.
+pub fn rollback_plan(
+    mut plan: RepairPlan,
+    by: &str,
+    runner: &dyn CommandRunner,
+    store: &dyn PlanStore,
+) -> Result<RepairPlan, DaemonError> {
+
+    println!("[EXECUTOR] Rolling back plan {}", plan.id);
+
+    if !plan.status.can_become(PlanStatus::RolledBack) {
+        return Err(DaemonError::PolicyBlocked(format!("a {} plan cannot be rolled back", plan.status)));
+    }
+
+    let mut tx = match Transaction::load(&plan.id) {
+        Ok(Some(tx)) if !tx.completed.is_empty() => tx,
+        Ok(_) => {
+            return Err(DaemonError::PolicyBlocked("no applied actions are recorded for this plan".into()));
+        }
+        Err(e) => return Err(DaemonError::Internal(e)),
+    };
+
+    if let Some(action) = tx.irreversible() {
+        return Err(DaemonError::PolicyBlocked(format!("'{}' cannot be undone", action)));
+    }
+
+    log_event(&format!("ROLLBACK START {} by {}", plan.id, by));
+
+    let errors = tx.rollback(runner);
+
+    if !errors.is_empty() {
+        let error = errors.join("; ");
+        println!("[EXECUTOR] ✖ Rollback incomplete: {}", error);
+        log_event(&format!("ROLLBACK failure {}: {}", plan.id, error));
+        return Err(DaemonError::ExecutionFailed {
+            action: "rollback".into(),
+            error,
+        });
     }
+
+    tx.discard();
+
+    log_event(&format!("ROLLBACK END {}", plan.id));
+    history::record(&plan.id, &plan.issue, HistoryOutcome::RolledBack);
+    set_status(store, &mut plan, PlanStatus::RolledBack, by, None)?;
+
+    println!("[EXECUTOR] Plan {} rolled back.", plan.id);
+
+    Ok(plan)
 }
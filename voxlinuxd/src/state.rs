@@ -1,10 +1,14 @@
 use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
 use std::sync::{Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
+use crate::config;
+use voxlinux::paths;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BootContext {
     EarlyBoot,
@@ -16,8 +20,9 @@ pub enum BootContext {
 }
 
 
-const STATE_DIR: &str = "/var/lib/voxlinux";
-const STATE_FILE: &str = "/var/lib/voxlinux/state.json";
+fn state_file() -> PathBuf {
+    paths::state_dir().join("state.json")
+}
 
 /// Global singleton state (thread-safe)
 static STATE: OnceLock<Mutex<HealState>> = OnceLock::new();
@@ -43,8 +48,15 @@ pub struct HealState {
     // ─────────────────────────────────────────
     // Predictive healing (history & trends)
     // ─────────────────────────────────────────
-    last_restart_count: HashMap<String, u32>,
-    last_observed_at: HashMap<String, u64>,
+    #[serde(default)]
+    restart_samples: HashMap<String, Vec<RestartSample>>,
+}
+
+/// NRestarts of a service as seen at one observation
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RestartSample {
+    pub at: u64,
+    pub count: u32,
 }
 
 impl HealState {
@@ -54,13 +66,12 @@ impl HealState {
             next_retry: HashMap::new(),
             healing_level: HashMap::new(),
             confidence: HashMap::new(),
-            last_restart_count: HashMap::new(),
-            last_observed_at: HashMap::new(),
+            restart_samples: HashMap::new(),
         }
     }
 
     fn load_from_disk() -> Self {
-        if let Ok(data) = fs::read_to_string(STATE_FILE) {
+        if let Ok(data) = fs::read_to_string(state_file()) {
             if let Ok(state) = serde_json::from_str(&data) {
                 return state;
             }
@@ -69,9 +80,9 @@ impl HealState {
     }
 
     fn save_to_disk(&self) {
-        let _ = fs::create_dir_all(STATE_DIR);
+        let _ = fs::create_dir_all(paths::state_dir());
         if let Ok(json) = serde_json::to_string_pretty(self) {
-            let _ = fs::write(STATE_FILE, json);
+            let _ = fs::write(state_file(), json);
         }
     }
 }
@@ -107,10 +118,11 @@ fn now_ts() -> u64 {
 
 pub fn should_retry(key: &str) -> bool {
     with_state(|state| {
+        let backoff = &config::get().backoff;
         let retries = state.retries.entry(key.to_string()).or_insert(0);
 
-        // Max 3 retry attempts per level
-        if *retries >= 3 {
+        // Max retry attempts per level
+        if *retries >= backoff.max_retries {
             return false;
         }
 
@@ -124,8 +136,8 @@ pub fn should_retry(key: &str) -> bool {
 
         *retries += 1;
 
-        // Exponential backoff: 10s, 20s, 40s
-        let delay = 10 * (1 << (*retries - 1));
+        // Exponential backoff: base, 2×base, 4×base, …
+        let delay = backoff.base_delay_secs << (*retries - 1);
         state
         .next_retry
         .insert(key.to_string(), now + delay);
@@ -211,19 +223,23 @@ pub fn drop_confidence(key: &str) {
 // ─────────────────────────────────────────────
 //
 
-pub fn get_last_restart_count(service: &str) -> Option<u32> {
+/// Add an NRestarts observation and return the samples inside the window
+/// (oldest first). A counter that went down means the service was started
+/// by hand or the machine rebooted, so the history starts over.
+pub fn record_restart_sample(service: &str, count: u32, window_secs: u64) -> Vec<RestartSample> {
     with_state(|state| {
-        state.last_restart_count.get(service).copied()
-    })
-}
+        let now = now_ts();
+        let samples = state.restart_samples.entry(service.to_string()).or_default();
 
-pub fn set_last_restart_count(service: &str, count: u32) {
-    with_state(|state| {
-        state
-        .last_restart_count
-        .insert(service.to_string(), count);
-        state
-        .last_observed_at
-        .insert(service.to_string(), now_ts());
-    });
+        if samples.last().map(|s| count < s.count).unwrap_or(false) {
+            samples.clear();
+        }
+
+        samples.push(RestartSample { at: now, count });
+
+        let cutoff = now.saturating_sub(window_secs);
+        samples.retain(|s| s.at >= cutoff);
+
+        samples.clone()
+    })
 }
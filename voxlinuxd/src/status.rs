@@ -0,0 +1,231 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config, events};
+use voxlinux::failure_class::FailureClass;
+use voxlinux::healing_level::HealingLevel;
+use voxlinux::paths;
+use voxlinux::protocol::{DaemonStatus, Event, LevelChange, Pause};
+
+/// Healing level and pause, kept across daemon restarts
+fn level_file() -> PathBuf {
+    paths::state_dir().join("healing-level.json")
+}
+
+/// Append-only audit trail of every level change (one JSON object per line)
+fn level_log() -> PathBuf {
+    paths::state_dir().join("level-changes.log")
+}
+
+/// Live daemon status shared between the main loop and IPC clients
+static STATUS: OnceLock<Mutex<DaemonStatus>> = OnceLock::new();
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedLevel {
+    healing_level: HealingLevel,
+    pause: Option<Pause>,
+    last_level_change: Option<LevelChange>,
+}
+
+fn with_status<F, R>(f: F) -> R
+where
+F: FnOnce(&mut DaemonStatus) -> R,
+{
+    let mutex = STATUS.get_or_init(|| {
+        let persisted = load_level();
+
+        Mutex::new(DaemonStatus {
+            healing_level: persisted
+            .as_ref()
+            .map(|p| p.healing_level)
+            .unwrap_or(config::get().daemon.healing_level),
+            failure_class: None,
+            healing_allowed: false,
+            last_scan: None,
+            plans: 0,
+            pause: persisted.as_ref().and_then(|p| p.pause.clone()),
+            last_level_change: persisted.and_then(|p| p.last_level_change),
+        })
+    });
+
+    let mut guard = mutex.lock().unwrap();
+    f(&mut guard)
+}
+
+pub fn snapshot() -> DaemonStatus {
+    with_status(|s| {
+        expire_pause(s);
+        s.clone()
+    })
+}
+
+/// Current healing level (read by the main loop every cycle)
+pub fn healing_level() -> HealingLevel {
+    with_status(|s| {
+        expire_pause(s);
+        s.healing_level
+    })
+}
+
+/// Time left in the running pause. The main loop wakes up when it ends,
+/// so the level comes back, and `HealingLevelChanged` goes out, on time
+/// rather than at the next scan.
+pub fn pause_left() -> Option<Duration> {
+    with_status(|s| {
+        expire_pause(s);
+        s.pause.as_ref().map(|p| Duration::from_secs(p.until.saturating_sub(now_ts())))
+    })
+}
+
+/// Set the level explicitly; this also ends any running pause
+pub fn set_healing_level(level: HealingLevel, by: &str, reason: Option<String>) {
+    with_status(|s| {
+        if s.pause.take().is_some() {
+            println!("[LEVEL] Pause cancelled by {}", by);
+        }
+
+        change_level(s, level, by, reason);
+        save_level(s);
+    });
+}
+
+/// Drop to ObserveOnly until `duration_secs` from now.
+/// Pausing again while paused extends the pause but keeps the original resume level.
+pub fn pause(duration_secs: u64, reason: String, by: &str) -> Pause {
+    with_status(|s| {
+        let resume_level = match &s.pause {
+            Some(p) => p.resume_level,
+            None => s.healing_level,
+        };
+
+        let pause = Pause {
+            until: now_ts().saturating_add(duration_secs),
+            reason: reason.clone(),
+            resume_level,
+            by: by.to_string(),
+        };
+
+        println!("[LEVEL] Paused by {} for {}s: {}", by, duration_secs, reason);
+
+        s.pause = Some(pause.clone());
+        change_level(s, HealingLevel::ObserveOnly, by, Some(reason));
+        save_level(s);
+        pause
+    })
+}
+
+/// End a pause early and restore the level it interrupted.
+/// Without a running pause this is a no-op returning the current level.
+pub fn resume(by: &str) -> HealingLevel {
+    with_status(|s| {
+        if let Some(pause) = s.pause.take() {
+            change_level(s, pause.resume_level, by, Some("pause ended early".into()));
+            save_level(s);
+        }
+
+        s.healing_level
+    })
+}
+
+/// Record the outcome of one scan cycle
+pub fn record_scan(
+    failure_class: FailureClass,
+    healing_allowed: bool,
+    plans: usize,
+    timestamp: u64,
+) {
+    with_status(|s| {
+        s.failure_class = Some(failure_class);
+        s.healing_allowed = healing_allowed;
+        s.plans = plans;
+        s.last_scan = Some(timestamp);
+    });
+}
+
+fn expire_pause(s: &mut DaemonStatus) {
+    let expired = s.pause.as_ref().map(|p| p.until <= now_ts()).unwrap_or(false);
+
+    if !expired {
+        return;
+    }
+
+    if let Some(pause) = s.pause.take() {
+        change_level(s, pause.resume_level, "voxlinuxd", Some("pause expired".into()));
+        save_level(s);
+    }
+}
+
+fn change_level(s: &mut DaemonStatus, level: HealingLevel, by: &str, reason: Option<String>) {
+    if s.healing_level == level {
+        return;
+    }
+
+    println!("[LEVEL] {:?} → {:?} (by {})", s.healing_level, level, by);
+
+    let change = LevelChange {
+        timestamp: now_ts(),
+        from: s.healing_level,
+        to: level,
+        by: by.to_string(),
+        reason,
+    };
+
+    s.healing_level = level;
+    s.last_level_change = Some(change.clone());
+
+    append_level_log(&change);
+    events::publish(Event::HealingLevelChanged(change));
+}
+
+fn load_level() -> Option<PersistedLevel> {
+    let path = level_file();
+    let data = fs::read_to_string(&path).ok()?;
+
+    match serde_json::from_str(&data) {
+        Ok(level) => Some(level),
+        Err(e) => {
+            println!("[LEVEL] Ignoring unreadable {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn save_level(s: &DaemonStatus) {
+    let persisted = PersistedLevel {
+        healing_level: s.healing_level,
+        pause: s.pause.clone(),
+        last_level_change: s.last_level_change.clone(),
+    };
+
+    let _ = fs::create_dir_all(paths::state_dir());
+
+    if let Ok(json) = serde_json::to_string_pretty(&persisted)
+    && let Err(e) = fs::write(level_file(), json)
+    {
+        println!("[LEVEL] Failed to persist healing level: {}", e);
+    }
+}
+
+fn append_level_log(change: &LevelChange) {
+    let Ok(line) = serde_json::to_string(change) else {
+        return;
+    };
+
+    let _ = fs::create_dir_all(paths::state_dir());
+
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(level_log()) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+fn now_ts() -> u64 {
+    SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap()
+    .as_secs()
+}
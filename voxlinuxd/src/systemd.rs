@@ -1,55 +1,39 @@
-use std::process::Command;
+use crate::config;
+use crate::systemd_backend::backend;
 use crate::core::opinion::Opinion;
 
-pub fn get_restart_count(_service: &str) -> Option<u32> {
-    None
+/// NRestarts of a service; None if systemd cannot be asked or it is not a service
+pub fn get_restart_count(service: &str) -> Option<u32> {
+    backend().restart_count(service).ok()
 }
 
+/// One-line summary of failed units, None when there are none
 pub fn check() -> Option<String> {
-    None
-}
+    let failed = backend().failed_units().ok()?;
+
+    if failed.is_empty() {
+        return None;
+    }
 
-pub fn is_active(_service: &str) -> bool {
-    true
+    let names: Vec<&str> = failed.iter().map(|u| u.name.as_str()).collect();
+    Some(format!("{} failed unit(s): {}", names.len(), names.join(", ")))
 }
 
-const CORE_UNITS: &[&str] = &[
-    "basic.target",
-"sysinit.target",
-"multi-user.target",
-"graphical.target",
-"systemd-journald.service",
-"systemd-logind.service",
-"systemd-udevd.service",
-"dbus.service",
-];
+pub fn is_active(service: &str) -> bool {
+    backend()
+    .unit_status(service)
+    .map(|u| u.is_active())
+    .unwrap_or(false)
+}
 
 pub fn assess() -> Opinion {
-    let output = Command::new("systemctl")
-    .args(["list-units", "--failed", "--no-legend"])
-    .output();
-
-    let output = match output {
-        Ok(o) if o.status.success() => o,
-        _ => return Opinion::Broken {
+    let failed_units: Vec<String> = match backend().failed_units() {
+        Ok(units) => units.into_iter().map(|u| u.name).collect(),
+        Err(_) => return Opinion::Broken {
             reason: "Unable to query systemd state".into(),
         },
     };
 
-    let failed_units: Vec<String> = String::from_utf8_lossy(&output.stdout)
-    .lines()
-    .filter_map(|line| {
-        let mut parts = line.split_whitespace();
-        let first = parts.next()?;
-
-        if first == "●" {
-            parts.next().map(|s| s.to_string())
-        } else {
-            Some(first.to_string())
-        }
-    })
-    .collect();
-
     if failed_units.is_empty() {
         return Opinion::Ok;
     }
@@ -57,7 +41,7 @@ pub fn assess() -> Opinion {
     // Separate core vs non-core failures
     let core_failed: Vec<&String> = failed_units
     .iter()
-    .filter(|u| CORE_UNITS.contains(&u.as_str()))
+    .filter(|u| config::get().units.is_core(u))
     .collect();
 
     if !core_failed.is_empty() {
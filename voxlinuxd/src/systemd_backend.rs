@@ -0,0 +1,930 @@
+// systemd_backend.rs
+//
+// Talks to systemd (org.freedesktop.systemd1) over D-Bus instead of running
+// systemctl and splitting its human-readable output. Everything that needs
+// unit state or restarts a unit goes through `backend()`.
+//
+// Unit failures are also pushed to the main loop as they happen: JobRemoved
+// signals with a non-"done" result and ActiveState changes to "failed".
+//
+// The bus is the system bus unless `systemd.bus_address` is set in the
+// config, which lets the daemon run against a mock systemd on a private
+// session bus.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use zbus::blocking::{connection, Connection, MessageIterator};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::MatchRule;
+
+use crate::config;
+
+/// Finished jobs remembered for waiters that arrive after the signal
+const JOB_HISTORY: usize = 256;
+
+/// Delay before re-subscribing after the unit signal stream broke
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+const UNIT_PATH_PREFIX: &str = "/org/freedesktop/systemd1/unit/";
+
+/// Error GetUnit returns for units systemd has not loaded
+const NO_SUCH_UNIT: &str = "org.freedesktop.systemd1.NoSuchUnit";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitStatus {
+    pub name: String,
+    pub description: String,
+    pub load_state: String,
+    pub active_state: String,
+    pub sub_state: String,
+}
+
+impl UnitStatus {
+    pub fn is_active(&self) -> bool {
+        self.active_state == "active"
+    }
+}
+
+/// How a service's main process last ended (org.freedesktop.systemd1.Service)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceExit {
+    /// `Result=`: "success", "exit-code", "signal", "timeout", "core-dump", …
+    pub result: String,
+
+    /// "exited", "killed" or "dumped"; None if the process never ran
+    pub code: Option<String>,
+
+    /// Exit status, or the signal number when killed/dumped
+    pub status: i32,
+}
+
+/// A unit failure reported by systemd as it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnitEvent {
+    /// A job for the unit finished with `result` ("failed", "timeout", "dependency", …)
+    JobFailed { unit: String, result: String },
+
+    /// The unit's ActiveState became "failed"
+    EnteredFailed { unit: String },
+}
+
+impl UnitEvent {
+    pub fn unit(&self) -> &str {
+        match self {
+            UnitEvent::JobFailed { unit, .. } | UnitEvent::EnteredFailed { unit } => unit,
+        }
+    }
+}
+
+impl fmt::Display for UnitEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitEvent::JobFailed { unit, result } => write!(f, "{} job {}", unit, result),
+            UnitEvent::EnteredFailed { unit } => write!(f, "{} entered failed state", unit),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemdError {
+    Bus(String),
+
+    /// systemd has no such unit loaded; it is neither running nor failed
+    NoSuchUnit { unit: String },
+
+    /// The job finished with a result other than "done"
+    JobFailed { unit: String, result: String },
+
+    JobTimeout { unit: String },
+}
+
+impl fmt::Display for SystemdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SystemdError::Bus(e) => write!(f, "systemd D-Bus error: {}", e),
+            SystemdError::NoSuchUnit { unit } => write!(f, "unit {} is not loaded", unit),
+            SystemdError::JobFailed { unit, result } => {
+                write!(f, "job for {} finished with result '{}'", unit, result)
+            }
+            SystemdError::JobTimeout { unit } => write!(f, "job for {} did not finish in time", unit),
+        }
+    }
+}
+
+impl From<zbus::Error> for SystemdError {
+    fn from(e: zbus::Error) -> Self {
+        SystemdError::Bus(e.to_string())
+    }
+}
+
+pub trait SystemdBackend: Send + Sync {
+    /// Units whose active state is "failed"
+    fn failed_units(&self) -> Result<Vec<UnitStatus>, SystemdError>;
+
+    fn unit_status(&self, unit: &str) -> Result<UnitStatus, SystemdError>;
+
+    /// Restart `unit` and wait for the job to finish
+    fn restart_unit(&self, unit: &str) -> Result<(), SystemdError>;
+
+    /// Manager state: "running", "degraded", "starting", "maintenance", …
+    fn system_state(&self) -> Result<String, SystemdError>;
+
+    fn default_target(&self) -> Result<String, SystemdError>;
+
+    /// CLOCK_MONOTONIC µs at which startup finished; 0 while still booting
+    fn boot_finished_usec(&self) -> Result<u64, SystemdError>;
+
+    /// CLOCK_MONOTONIC µs of the unit's last active-state change
+    fn state_change_usec(&self, unit: &str) -> Result<u64, SystemdError>;
+
+    /// Result and main-process exit of a service unit
+    fn service_exit(&self, unit: &str) -> Result<ServiceExit, SystemdError>;
+
+    /// cgroup of a service relative to the cgroup root ("/system.slice/foo.service");
+    /// empty once the service has stopped
+    fn control_group(&self, unit: &str) -> Result<String, SystemdError>;
+
+    /// Automatic restarts of a service (`NRestarts`) since it was last started by hand
+    fn restart_count(&self, unit: &str) -> Result<u32, SystemdError>;
+
+    /// Stream of unit failures; the backend keeps it alive across bus restarts
+    fn unit_events(&self) -> Receiver<UnitEvent>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1",
+    gen_async = false
+)]
+trait Manager {
+    #[allow(clippy::type_complexity)]
+    fn list_units_filtered(
+        &self,
+        states: &[&str],
+    ) -> zbus::Result<
+        Vec<(
+            String,
+            String,
+            String,
+            String,
+            String,
+            String,
+            OwnedObjectPath,
+            u32,
+            String,
+            OwnedObjectPath,
+        )>,
+    >;
+
+    /// Unlike LoadUnit this never loads the unit as a side effect
+    fn get_unit(&self, name: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn restart_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+
+    fn get_default_target(&self) -> zbus::Result<String>;
+
+    /// Ask systemd to emit job signals to this client
+    fn subscribe(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn system_state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn finish_timestamp_monotonic(&self) -> zbus::Result<u64>;
+
+    #[zbus(signal)]
+    fn job_removed(
+        &self,
+        id: u32,
+        job: zbus::zvariant::ObjectPath<'_>,
+        unit: &str,
+        result: &str,
+    ) -> zbus::Result<()>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.systemd1.Unit",
+    default_service = "org.freedesktop.systemd1",
+    gen_async = false
+)]
+trait Unit {
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn description(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn load_state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn active_state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn sub_state(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn state_change_timestamp_monotonic(&self) -> zbus::Result<u64>;
+}
+
+#[zbus::proxy(
+    interface = "org.freedesktop.systemd1.Service",
+    default_service = "org.freedesktop.systemd1",
+    gen_async = false
+)]
+trait Service {
+    #[zbus(property)]
+    fn result(&self) -> zbus::Result<String>;
+
+    /// CLD_* code of the main process (1 exited, 2 killed, 3 dumped)
+    #[zbus(property)]
+    fn exec_main_code(&self) -> zbus::Result<i32>;
+
+    #[zbus(property)]
+    fn exec_main_status(&self) -> zbus::Result<i32>;
+
+    #[zbus(property, name = "NRestarts")]
+    fn n_restarts(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn control_group(&self) -> zbus::Result<String>;
+}
+
+/// Results of recently finished jobs, filled by the JobRemoved listener
+#[derive(Default)]
+struct JobTracker {
+    finished: Mutex<VecDeque<(OwnedObjectPath, String)>>,
+    changed: Condvar,
+}
+
+impl JobTracker {
+    fn finish(&self, job: OwnedObjectPath, result: String) {
+        let mut finished = self.finished.lock().unwrap();
+
+        if finished.len() >= JOB_HISTORY {
+            finished.pop_front();
+        }
+
+        finished.push_back((job, result));
+        self.changed.notify_all();
+    }
+
+    fn wait(&self, job: &OwnedObjectPath, timeout: Duration) -> Option<String> {
+        let deadline = Instant::now() + timeout;
+        let mut finished = self.finished.lock().unwrap();
+
+        loop {
+            if let Some(pos) = finished.iter().position(|(path, _)| path == job) {
+                return finished.remove(pos).map(|(_, result)| result);
+            }
+
+            let left = deadline.checked_duration_since(Instant::now())?;
+            finished = self.changed.wait_timeout(finished, left).unwrap().0;
+        }
+    }
+}
+
+struct Bus {
+    connection: Connection,
+    jobs: Arc<JobTracker>,
+}
+
+impl Bus {
+    /// Subscribe to job signals on `connection` and track finished jobs
+    fn attach(connection: Connection) -> Result<Self, SystemdError> {
+        let manager = ManagerProxy::new(&connection)?;
+        manager.subscribe()?;
+
+        let jobs = Arc::new(JobTracker::default());
+        let signals = manager.receive_job_removed()?;
+        let tracker = jobs.clone();
+
+        thread::spawn(move || {
+            for signal in signals {
+                if let Ok(args) = signal.args() {
+                    tracker.finish(args.job.clone().into(), args.result.to_string());
+                }
+            }
+        });
+
+        Ok(Bus { connection, jobs })
+    }
+
+    /// Object path of a loaded unit
+    fn unit_path(&self, unit: &str) -> Result<OwnedObjectPath, SystemdError> {
+        match ManagerProxy::new(&self.connection)?.get_unit(unit) {
+            Ok(path) => Ok(path),
+            Err(zbus::Error::MethodError(name, _, _)) if name.as_str() == NO_SUCH_UNIT => {
+                Err(SystemdError::NoSuchUnit {
+                    unit: unit.to_string(),
+                })
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// systemd over D-Bus; connects lazily and reconnects after bus errors
+pub struct DbusBackend {
+    address: Option<String>,
+    job_timeout: Duration,
+    bus: Mutex<Option<Arc<Bus>>>,
+}
+
+impl DbusBackend {
+    pub fn new(address: Option<String>, job_timeout: Duration) -> Self {
+        DbusBackend {
+            address,
+            job_timeout,
+            bus: Mutex::new(None),
+        }
+    }
+
+    fn bus(&self) -> Result<Arc<Bus>, SystemdError> {
+        let mut cached = self.bus.lock().unwrap();
+
+        if let Some(bus) = cached.as_ref() {
+            return Ok(bus.clone());
+        }
+
+        let bus = Arc::new(Bus::attach(connect(self.address.as_deref())?)?);
+        *cached = Some(bus.clone());
+        Ok(bus)
+    }
+
+    /// Run `f` against the bus, dropping the connection if it failed
+    fn with_bus<F, R>(&self, f: F) -> Result<R, SystemdError>
+    where
+    F: FnOnce(&Bus) -> Result<R, SystemdError>,
+    {
+        let bus = self.bus()?;
+        let result = f(&bus);
+
+        if let Err(SystemdError::Bus(e)) = &result {
+            println!("[SYSTEMD] D-Bus call failed, reconnecting next time: {}", e);
+            *self.bus.lock().unwrap() = None;
+        }
+
+        result
+    }
+}
+
+impl SystemdBackend for DbusBackend {
+    fn failed_units(&self) -> Result<Vec<UnitStatus>, SystemdError> {
+        self.with_bus(|bus| {
+            let units = ManagerProxy::new(&bus.connection)?.list_units_filtered(&["failed"])?;
+
+            Ok(units
+            .into_iter()
+            .map(|(name, description, load_state, active_state, sub_state, ..)| UnitStatus {
+                name,
+                description,
+                load_state,
+                active_state,
+                sub_state,
+            })
+            .collect())
+        })
+    }
+
+    fn unit_status(&self, unit: &str) -> Result<UnitStatus, SystemdError> {
+        self.with_bus(|bus| {
+            let path = bus.unit_path(unit)?;
+
+            let proxy = UnitProxy::builder(&bus.connection)
+            .path(path)?
+            .build()?;
+
+            Ok(UnitStatus {
+                name: proxy.id()?,
+                description: proxy.description()?,
+                load_state: proxy.load_state()?,
+                active_state: proxy.active_state()?,
+                sub_state: proxy.sub_state()?,
+            })
+        })
+    }
+
+    fn boot_finished_usec(&self) -> Result<u64, SystemdError> {
+        self.with_bus(|bus| Ok(ManagerProxy::new(&bus.connection)?.finish_timestamp_monotonic()?))
+    }
+
+    fn state_change_usec(&self, unit: &str) -> Result<u64, SystemdError> {
+        self.with_bus(|bus| {
+            let path = bus.unit_path(unit)?;
+
+            let proxy = UnitProxy::builder(&bus.connection)
+            .path(path)?
+            .build()?;
+
+            Ok(proxy.state_change_timestamp_monotonic()?)
+        })
+    }
+
+    fn service_exit(&self, unit: &str) -> Result<ServiceExit, SystemdError> {
+        self.with_bus(|bus| {
+            let path = bus.unit_path(unit)?;
+
+            let proxy = ServiceProxy::builder(&bus.connection)
+            .path(path)?
+            .build()?;
+
+            let code = match proxy.exec_main_code()? {
+                1 => Some("exited".to_string()),
+                2 => Some("killed".to_string()),
+                3 => Some("dumped".to_string()),
+                _ => None,
+            };
+
+            Ok(ServiceExit {
+                result: proxy.result()?,
+                code,
+                status: proxy.exec_main_status()?,
+            })
+        })
+    }
+
+    fn control_group(&self, unit: &str) -> Result<String, SystemdError> {
+        self.with_bus(|bus| {
+            let path = bus.unit_path(unit)?;
+
+            let proxy = ServiceProxy::builder(&bus.connection)
+            .path(path)?
+            .build()?;
+
+            Ok(proxy.control_group()?)
+        })
+    }
+
+    fn restart_count(&self, unit: &str) -> Result<u32, SystemdError> {
+        self.with_bus(|bus| {
+            let path = bus.unit_path(unit)?;
+
+            let proxy = ServiceProxy::builder(&bus.connection)
+            .path(path)?
+            .build()?;
+
+            Ok(proxy.n_restarts()?)
+        })
+    }
+
+    fn restart_unit(&self, unit: &str) -> Result<(), SystemdError> {
+        self.with_bus(|bus| {
+            let job = ManagerProxy::new(&bus.connection)?.restart_unit(unit, "replace")?;
+
+            match bus.jobs.wait(&job, self.job_timeout) {
+                Some(result) if result == "done" => Ok(()),
+                Some(result) => Err(SystemdError::JobFailed {
+                    unit: unit.to_string(),
+                    result,
+                }),
+                None => Err(SystemdError::JobTimeout {
+                    unit: unit.to_string(),
+                }),
+            }
+        })
+    }
+
+    fn system_state(&self) -> Result<String, SystemdError> {
+        self.with_bus(|bus| Ok(ManagerProxy::new(&bus.connection)?.system_state()?))
+    }
+
+    fn default_target(&self) -> Result<String, SystemdError> {
+        self.with_bus(|bus| Ok(ManagerProxy::new(&bus.connection)?.get_default_target()?))
+    }
+
+    fn unit_events(&self) -> Receiver<UnitEvent> {
+        let (tx, rx) = mpsc::channel();
+        let address = self.address.clone();
+
+        thread::spawn(move || loop {
+            match watch_units(address.as_deref(), &tx) {
+                // Receiver dropped: nobody is listening any more
+                Ok(()) => return,
+                Err(e) => println!("[SYSTEMD] Unit signal stream lost: {}", e),
+            }
+
+            thread::sleep(RESUBSCRIBE_DELAY);
+        });
+
+        rx
+    }
+}
+
+fn connect(address: Option<&str>) -> Result<Connection, SystemdError> {
+    let connection = match address {
+        Some(address) => connection::Builder::address(address)?.build()?,
+        None => Connection::system()?,
+    };
+
+    Ok(connection)
+}
+
+/// Forward unit failures until the bus goes away (Err) or `tx` is closed (Ok)
+fn watch_units(address: Option<&str>, tx: &Sender<UnitEvent>) -> Result<(), SystemdError> {
+    let connection = connect(address)?;
+    let manager = ManagerProxy::new(&connection)?;
+    manager.subscribe()?;
+
+    let jobs = manager.receive_job_removed()?;
+
+    let rule = MatchRule::builder()
+    .msg_type(zbus::message::Type::Signal)
+    .sender("org.freedesktop.systemd1")?
+    .interface("org.freedesktop.DBus.Properties")?
+    .member("PropertiesChanged")?
+    .path_namespace("/org/freedesktop/systemd1/unit")?
+    .arg(0, "org.freedesktop.systemd1.Unit")?
+    .build();
+
+    let properties = MessageIterator::for_match_rule(rule, &connection, None)?;
+
+    println!("[SYSTEMD] Subscribed to unit signals");
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            for signal in jobs {
+                let Ok(args) = signal.args() else { continue };
+
+                if args.result == "done" {
+                    continue;
+                }
+
+                let event = UnitEvent::JobFailed {
+                    unit: args.unit.to_string(),
+                    result: args.result.to_string(),
+                };
+
+                if tx.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+
+        for message in properties {
+            let Ok(message) = message else { continue };
+
+            let header = message.header();
+            let Some(unit) = header.path().and_then(|p| unit_from_path(p.as_str())) else {
+                continue;
+            };
+
+            let Ok((_, changed, _)) = message
+            .body()
+            .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+            else {
+                continue;
+            };
+
+            let failed = changed
+            .get("ActiveState")
+            .and_then(|v| String::try_from(v.clone()).ok())
+            .map(|state| state == "failed")
+            .unwrap_or(false);
+
+            if failed && tx.send(UnitEvent::EnteredFailed { unit }).is_err() {
+                return Ok(());
+            }
+        }
+
+        Err(SystemdError::Bus("signal stream ended".into()))
+    })
+}
+
+/// "/org/freedesktop/systemd1/unit/foo_2dbar_2eservice" → "foo-bar.service"
+fn unit_from_path(path: &str) -> Option<String> {
+    let escaped = path.strip_prefix(UNIT_PATH_PREFIX)?.as_bytes();
+    let mut name = Vec::with_capacity(escaped.len());
+    let mut i = 0;
+
+    while i < escaped.len() {
+        if escaped[i] == b'_' && i + 2 < escaped.len() {
+            let hex = std::str::from_utf8(&escaped[i + 1..i + 3]).ok()?;
+            name.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            name.push(escaped[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(name).ok()
+}
+
+static BACKEND: OnceLock<Box<dyn SystemdBackend>> = OnceLock::new();
+
+/// The process-wide systemd backend
+pub fn backend() -> &'static dyn SystemdBackend {
+    BACKEND
+    .get_or_init(|| {
+        let cfg = &config::get().systemd;
+
+        Box::new(DbusBackend::new(
+            cfg.bus_address.clone(),
+            Duration::from_secs(cfg.job_timeout_secs),
+        ))
+    })
+    .as_ref()
+}
+
+/// Canned systemd for tests of code that takes a `&dyn SystemdBackend`.
+/// Queries it was not given an answer for fail with a bus error.
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeSystemd {
+    pub failed: Vec<UnitStatus>,
+    pub exits: HashMap<String, ServiceExit>,
+}
+
+#[cfg(test)]
+impl FakeSystemd {
+    pub fn exit(mut self, unit: &str, exit: ServiceExit) -> Self {
+        self.exits.insert(unit.to_string(), exit);
+        self
+    }
+
+    fn not_faked<T>(what: &str) -> Result<T, SystemdError> {
+        Err(SystemdError::Bus(format!("{} is not faked", what)))
+    }
+}
+
+#[cfg(test)]
+impl SystemdBackend for FakeSystemd {
+    fn failed_units(&self) -> Result<Vec<UnitStatus>, SystemdError> {
+        Ok(self.failed.clone())
+    }
+
+    fn unit_status(&self, unit: &str) -> Result<UnitStatus, SystemdError> {
+        self.failed
+        .iter()
+        .find(|u| u.name == unit)
+        .cloned()
+        .ok_or_else(|| SystemdError::NoSuchUnit { unit: unit.to_string() })
+    }
+
+    fn restart_unit(&self, _unit: &str) -> Result<(), SystemdError> {
+        Self::not_faked("RestartUnit")
+    }
+
+    fn system_state(&self) -> Result<String, SystemdError> {
+        Self::not_faked("SystemState")
+    }
+
+    fn default_target(&self) -> Result<String, SystemdError> {
+        Self::not_faked("GetDefaultTarget")
+    }
+
+    fn boot_finished_usec(&self) -> Result<u64, SystemdError> {
+        Self::not_faked("FinishTimestampMonotonic")
+    }
+
+    fn state_change_usec(&self, _unit: &str) -> Result<u64, SystemdError> {
+        Self::not_faked("StateChangeTimestampMonotonic")
+    }
+
+    fn service_exit(&self, unit: &str) -> Result<ServiceExit, SystemdError> {
+        self.exits
+        .get(unit)
+        .cloned()
+        .ok_or_else(|| SystemdError::NoSuchUnit { unit: unit.to_string() })
+    }
+
+    fn control_group(&self, _unit: &str) -> Result<String, SystemdError> {
+        Self::not_faked("ControlGroup")
+    }
+
+    fn restart_count(&self, _unit: &str) -> Result<u32, SystemdError> {
+        Self::not_faked("NRestarts")
+    }
+
+    fn unit_events(&self) -> Receiver<UnitEvent> {
+        mpsc::channel().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use zbus::object_server::SignalEmitter;
+    use zbus::zvariant::ObjectPath;
+
+    const MANAGER_PATH: &str = "/org/freedesktop/systemd1";
+    const SSHD_PATH: &str = "/org/freedesktop/systemd1/unit/sshd_2eservice";
+
+    #[derive(Debug, zbus::DBusError)]
+    #[zbus(prefix = "org.freedesktop.systemd1")]
+    enum MockError {
+        #[zbus(error)]
+        ZBus(zbus::Error),
+        NoSuchUnit(String),
+    }
+
+    /// systemd with one loaded unit, sshd.service; restart jobs end with `job_result`
+    struct MockManager {
+        job_result: String,
+        next_job: AtomicU32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.systemd1.Manager")]
+    impl MockManager {
+        fn get_unit(&self, name: &str) -> Result<OwnedObjectPath, MockError> {
+            match name {
+                "sshd.service" => Ok(OwnedObjectPath::try_from(SSHD_PATH).unwrap()),
+                _ => Err(MockError::NoSuchUnit(format!("Unit {} not loaded.", name))),
+            }
+        }
+
+        fn subscribe(&self) {}
+
+        /// Announces the job before replying, like a job that finishes at once
+        async fn restart_unit(
+            &self,
+            name: &str,
+            _mode: &str,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> Result<OwnedObjectPath, MockError> {
+            let id = self.next_job.fetch_add(1, Ordering::SeqCst);
+            let job = OwnedObjectPath::try_from(format!("{}/job/{}", MANAGER_PATH, id)).unwrap();
+
+            Self::job_removed(&emitter, id, job.as_ref(), name, &self.job_result).await?;
+            Ok(job)
+        }
+
+        #[zbus(signal)]
+        async fn job_removed(
+            emitter: &SignalEmitter<'_>,
+            id: u32,
+            job: ObjectPath<'_>,
+            unit: &str,
+            result: &str,
+        ) -> zbus::Result<()>;
+    }
+
+    struct MockUnit;
+
+    #[zbus::interface(name = "org.freedesktop.systemd1.Unit")]
+    impl MockUnit {
+        #[zbus(property)]
+        fn id(&self) -> String {
+            "sshd.service".into()
+        }
+
+        #[zbus(property)]
+        fn description(&self) -> String {
+            "OpenSSH Daemon".into()
+        }
+
+        #[zbus(property)]
+        fn load_state(&self) -> String {
+            "loaded".into()
+        }
+
+        #[zbus(property)]
+        fn active_state(&self) -> String {
+            "active".into()
+        }
+
+        #[zbus(property)]
+        fn sub_state(&self) -> String {
+            "running".into()
+        }
+    }
+
+    struct MockService;
+
+    #[zbus::interface(name = "org.freedesktop.systemd1.Service")]
+    impl MockService {
+        #[zbus(property, name = "NRestarts")]
+        fn n_restarts(&self) -> u32 {
+            3
+        }
+    }
+
+    /// Backend connected to a mock systemd over a socket pair; keep the
+    /// returned server connection alive for the duration of the test
+    fn mock_backend(job_result: &str) -> (DbusBackend, Connection) {
+        let (server, client) = UnixStream::pair().unwrap();
+        let manager = MockManager {
+            job_result: job_result.to_string(),
+            next_job: AtomicU32::new(1),
+        };
+
+        let server = thread::spawn(move || {
+            connection::Builder::async_io_unix_stream(server)
+            .server(zbus::Guid::generate())?
+            .p2p()
+            .serve_at(MANAGER_PATH, manager)?
+            .serve_at(SSHD_PATH, MockUnit)?
+            .serve_at(SSHD_PATH, MockService)?
+            .build()
+        });
+
+        let client = connection::Builder::async_io_unix_stream(client).p2p().build().unwrap();
+        let server = server.join().unwrap().unwrap();
+
+        let backend = DbusBackend::new(None, Duration::from_secs(5));
+        *backend.bus.lock().unwrap() = Some(Arc::new(Bus::attach(client).unwrap()));
+
+        (backend, server)
+    }
+
+    #[test]
+    fn unit_status_reads_loaded_unit() {
+        let (backend, _server) = mock_backend("done");
+
+        let status = backend.unit_status("sshd.service").unwrap();
+
+        assert_eq!(status.name, "sshd.service");
+        assert_eq!(status.sub_state, "running");
+        assert!(status.is_active());
+        assert_eq!(backend.restart_count("sshd.service"), Ok(3));
+    }
+
+    #[test]
+    fn unknown_unit_is_not_present_and_keeps_connection() {
+        let (backend, _server) = mock_backend("done");
+
+        assert_eq!(
+            backend.unit_status("nope.service"),
+            Err(SystemdError::NoSuchUnit {
+                unit: "nope.service".into()
+            })
+        );
+        assert!(backend.bus.lock().unwrap().is_some());
+    }
+
+    #[test]
+    fn restart_waits_for_job_result() {
+        let (backend, _server) = mock_backend("done");
+        assert_eq!(backend.restart_unit("sshd.service"), Ok(()));
+
+        let (backend, _server) = mock_backend("failed");
+        assert_eq!(
+            backend.restart_unit("sshd.service"),
+            Err(SystemdError::JobFailed {
+                unit: "sshd.service".into(),
+                result: "failed".into(),
+            })
+        );
+    }
+
+    fn job(id: usize) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(format!("/org/freedesktop/systemd1/job/{}", id)).unwrap()
+    }
+
+    #[test]
+    fn job_tracker_returns_result_finished_before_wait() {
+        let tracker = JobTracker::default();
+        tracker.finish(job(1), "done".into());
+        tracker.finish(job(2), "timeout".into());
+
+        assert_eq!(tracker.wait(&job(2), Duration::ZERO), Some("timeout".into()));
+        assert_eq!(tracker.wait(&job(1), Duration::ZERO), Some("done".into()));
+
+        // Each result is handed out once
+        assert_eq!(tracker.wait(&job(1), Duration::ZERO), None);
+    }
+
+    #[test]
+    fn job_tracker_wakes_waiter() {
+        let tracker = Arc::new(JobTracker::default());
+        let finisher = tracker.clone();
+
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            finisher.finish(job(7), "dependency".into());
+        });
+
+        assert_eq!(tracker.wait(&job(7), Duration::from_secs(5)), Some("dependency".into()));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn job_tracker_forgets_oldest_jobs() {
+        let tracker = JobTracker::default();
+
+        for id in 0..=JOB_HISTORY {
+            tracker.finish(job(id), "done".into());
+        }
+
+        assert_eq!(tracker.wait(&job(0), Duration::ZERO), None);
+        assert_eq!(tracker.wait(&job(JOB_HISTORY), Duration::ZERO), Some("done".into()));
+    }
+
+    #[test]
+    fn unit_names_are_unescaped() {
+        assert_eq!(
+            unit_from_path("/org/freedesktop/systemd1/unit/foo_2dbar_2eservice"),
+            Some("foo-bar.service".into())
+        );
+        assert_eq!(unit_from_path("/org/freedesktop/systemd1/job/12"), None);
+    }
+}
@@ -1,9 +1,15 @@
-use crate::{systemd, pacman};
-use crate::system_state::SystemState;
-use std::process::{Command, Stdio};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{config, systemd, pacman};
+use crate::probe::{self, DiskPressure};
+use voxlinux::system_state::SystemState;
+use voxlinux::command_runner::CommandRunner;
 
 /// Verify whether a system state is currently healthy
-pub fn verify(state: &SystemState) -> bool {
+pub fn verify(state: &SystemState, runner: &dyn CommandRunner) -> bool {
     match state {
         // Package is healthy if pacman is not broken
         SystemState::PackageConsistent => {
@@ -15,30 +21,118 @@ pub fn verify(state: &SystemState) -> bool {
             systemd::is_active(service)
         }
 
-        // Network is healthy if ping works
+        // Network is healthy if every configured layer answers
         SystemState::NetworkReachable => {
-            let ping = Command::new("ping")
-            .args(["-c", "1", "-W", "1", "1.1.1.1"])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status();
-
-            matches!(ping, Ok(p) if p.success())
+            probe::network_ok(runner)
         }
 
         // Filesystem is healthy if mounted rw
         SystemState::FilesystemWritable(path) => {
-            let output = Command::new("mount")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .output();
-
-            if let Ok(o) = output {
-                let stdout = String::from_utf8_lossy(&o.stdout);
-                stdout.contains(path) && stdout.contains("(rw")
-            } else {
-                false
+            probe::filesystem_rw(path)
+        }
+
+        // Disk is healthy below the critical space and inode thresholds
+        SystemState::DiskSpaceAvailable(path) => {
+            probe::disk_usage(path)
+            .map(|usage| usage.pressure() != DiskPressure::Critical)
+            .unwrap_or(false)
+        }
+    }
+}
+
+/// Watch `targets` for the configured stability window after a repair.
+///
+/// Every target is checked once per poll interval, the first time one
+/// interval after the repair so restarted units have settled. A service
+/// also fails the check if systemd restarted it again meanwhile, which
+/// catches units that crash and come back between two polls.
+pub fn watch_stability(targets: &[SystemState], runner: &dyn CommandRunner) -> Result<(), String> {
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    let cfg = &config::get().verify;
+    let window = Duration::from_secs(cfg.stability_window_secs);
+    let interval = Duration::from_secs(cfg.poll_interval_secs);
+
+    println!(
+        "[VERIFY] Watching {} target(s) for {}s",
+        targets.len(),
+        cfg.stability_window_secs
+    );
+
+    let restarts: HashMap<&str, u32> = targets
+    .iter()
+    .filter_map(|t| match t {
+        SystemState::ServiceActive(unit) => {
+            systemd::get_restart_count(unit).map(|n| (unit.as_str(), n))
+        }
+        _ => None,
+    })
+    .collect();
+
+    let started = Instant::now();
+
+    loop {
+        thread::sleep(interval.min(window.saturating_sub(started.elapsed())));
+
+        let elapsed = started.elapsed().as_secs();
+
+        for target in targets {
+            if !verify(target, runner) {
+                return Err(format!("{} failed {}s after the repair", target, elapsed));
+            }
+
+            if let SystemState::ServiceActive(unit) = target {
+                let before = restarts.get(unit.as_str());
+                let now = systemd::get_restart_count(unit);
+
+                if let (Some(before), Some(now)) = (before, now)
+                && now > *before
+                {
+                    return Err(format!(
+                        "{} restarted {} time(s) within {}s of the repair",
+                        unit,
+                        now - before,
+                        elapsed
+                    ));
+                }
             }
         }
+
+        if started.elapsed() >= window {
+            println!("[VERIFY] All targets held for {}s", cfg.stability_window_secs);
+            return Ok(());
+        }
     }
 }
+
+/// Plans whose stability window is still open
+static WATCHED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn watched() -> std::sync::MutexGuard<'static, HashSet<String>> {
+    WATCHED.get_or_init(Default::default).lock().unwrap()
+}
+
+/// True while `watch_in_background` is watching the targets of `plan_id`
+pub fn is_watching(plan_id: &str) -> bool {
+    watched().contains(plan_id)
+}
+
+/// `watch_stability` on a worker thread, so neither the IPC thread nor
+/// the main loop waits out the window; `done` gets the result there
+pub fn watch_in_background(
+    plan_id: &str,
+    targets: Vec<SystemState>,
+    runner: &'static dyn CommandRunner,
+    done: impl FnOnce(Result<(), String>) + Send + 'static,
+) {
+    let plan_id = plan_id.to_string();
+    watched().insert(plan_id.clone());
+
+    thread::spawn(move || {
+        let result = watch_stability(&targets, runner);
+        watched().remove(&plan_id);
+        done(result);
+    });
+}